@@ -0,0 +1,27 @@
+#![no_main]
+
+use chip8::emulator::Emulator;
+use libfuzzer_sys::fuzz_target;
+
+// The first 64 bytes drive the keypad, the rest is loaded as the rom.
+// Ticking must never panic, whatever the rom does.
+fuzz_target!(|data: &[u8]| {
+    let (keys, rom) = data.split_at(data.len().min(64));
+    let mut emulator = Emulator::new();
+    if emulator.load_rom(rom).is_err() {
+        return;
+    }
+
+    for step in 0..10_000 {
+        if let Some(key) = keys.get(step % 64) {
+            if key & 0x10 == 0 {
+                emulator.press_key(*key);
+            } else {
+                emulator.release_key(*key);
+            }
+        }
+        if emulator.try_tick().is_err() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,530 @@
+#[cfg(all(feature = "js", not(feature = "std")))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    Arc,
+};
+
+use crate::io::timer::TICKS_PER_SECOND;
+
+#[cfg(feature = "cpal")]
+mod cpal_buzzer;
+
+#[cfg(feature = "cpal")]
+pub use cpal_buzzer::CpalBuzzer;
+
+/// The pitch of the buzzer, unless configured otherwise
+pub const DEFAULT_BUZZER_HZ: f32 = 440.0;
+
+/// The playback rate of an XO-CHIP audio pattern at the default pitch
+pub const DEFAULT_PATTERN_RATE: f32 = 4000.0;
+/// The pitch that plays a pattern at [`DEFAULT_PATTERN_RATE`]
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// The 48 steps of the pitch within one octave, `2^(step/48)`
+#[rustfmt::skip]
+const PITCH_STEPS: [f32; 48] = [
+    1.0, 1.0145453, 1.0293022, 1.0442738, 1.0594631, 1.0748733,
+    1.0905077, 1.1063695, 1.122462, 1.1387886, 1.1553527, 1.1721577,
+    1.1892071, 1.2065045, 1.2240535, 1.2418578, 1.259921, 1.278247,
+    1.2968396, 1.3157025, 1.3348399, 1.3542555, 1.3739536, 1.3939383,
+    core::f32::consts::SQRT_2, 1.4347838, 1.4556532, 1.4768261, 1.4983071, 1.5201005,
+    1.5422108, 1.5646428, 1.5874011, 1.6104903, 1.6339155, 1.6576813,
+    1.6817928, 1.7062551, 1.7310731, 1.7562522, 1.7817974, 1.8077143,
+    1.8340081, 1.8606843, 1.8877486, 1.9152066, 1.9430639, 1.9713264,
+];
+
+/// The bits per second an XO-CHIP audio pattern plays at,
+/// `4000 * 2^((pitch - 64) / 48)`
+pub fn pattern_rate(pitch: u8) -> f32 {
+    let octaves = pitch as i32 - DEFAULT_PITCH as i32;
+    let octave = octaves.div_euclid(48);
+    let step = PITCH_STEPS[octaves.rem_euclid(48) as usize];
+    let scale = if octave < 0 {
+        1.0 / (1u32 << -octave) as f32
+    } else {
+        (1u32 << octave) as f32
+    };
+    DEFAULT_PATTERN_RATE * scale * step
+}
+
+/// Gets notified whenever the buzzer turns on or off, e.g. to drive
+/// a piezo through a GPIO or PWM peripheral instead of polling
+pub trait SoundSink {
+    /// Called once per change, never while the buzzer stays on or off
+    fn sound_changed(&mut self, on: bool);
+}
+
+/// A [`SoundSink`] ignoring all changes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullSink;
+
+impl SoundSink for NullSink {
+    fn sound_changed(&mut self, _on: bool) {}
+}
+
+/// Limit a volume to 0.0..=1.0, treating NaN as muted
+pub fn clamp_volume(volume: f32) -> f32 {
+    if volume.is_nan() {
+        0.0
+    } else {
+        volume.clamp(0.0, 1.0)
+    }
+}
+
+/// The values a sample type plays the buzzer with
+trait Sample: Copy {
+    const HIGH: Self;
+    const SILENCE: Self;
+    fn scale(self, volume: f32) -> Self;
+    fn negate(self) -> Self;
+}
+
+impl Sample for f32 {
+    const HIGH: Self = 1.0;
+    const SILENCE: Self = 0.0;
+    fn scale(self, volume: f32) -> Self {
+        self * volume
+    }
+    fn negate(self) -> Self {
+        -self
+    }
+}
+
+impl Sample for i16 {
+    const HIGH: Self = i16::MAX;
+    const SILENCE: Self = 0;
+    fn scale(self, volume: f32) -> Self {
+        (self as f32 * volume) as i16
+    }
+    fn negate(self) -> Self {
+        -self
+    }
+}
+
+/// Generates the square wave of the buzzer. The phase is kept
+/// between buffers, so consecutive buffers join without clicks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SquareWave {
+    frequency: f32,
+    volume: f32,
+    /// Position inside of the current period, from 0 to 1
+    phase: f32,
+}
+
+impl Default for SquareWave {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUZZER_HZ)
+    }
+}
+
+impl SquareWave {
+    pub fn new(frequency: f32) -> Self {
+        Self {
+            frequency,
+            volume: 1.0,
+            phase: 0.0,
+        }
+    }
+
+    pub fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    /// Change the pitch, keeping the current phase
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency;
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    /// Set the volume, clamped to 0.0..=1.0
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = clamp_volume(volume);
+    }
+
+    /// Fill the buffer with the wave for its first `on_samples`
+    /// samples and with silence after them
+    pub fn fill(&mut self, buf: &mut [f32], sample_rate: u32, on_samples: usize) {
+        self.fill_with(buf, sample_rate, on_samples);
+    }
+
+    /// Like [`SquareWave::fill`], for hosts playing signed 16 bit samples
+    pub fn fill_i16(&mut self, buf: &mut [i16], sample_rate: u32, on_samples: usize) {
+        self.fill_with(buf, sample_rate, on_samples);
+    }
+
+    fn fill_with<T: Sample>(&mut self, buf: &mut [T], sample_rate: u32, on_samples: usize) {
+        let step = self.frequency / sample_rate.max(1) as f32;
+        let high = T::HIGH.scale(self.volume);
+        let (on, off) = buf.split_at_mut(on_samples.min(buf.len()));
+        for sample in on {
+            *sample = if self.phase < 0.5 {
+                high
+            } else {
+                high.negate()
+            };
+            self.phase = (self.phase + step) % 1.0;
+        }
+        off.fill(T::SILENCE);
+    }
+}
+
+/// Plays an XO-CHIP audio pattern, 128 bits played from
+/// the most significant bit of the first byte on, as a loop
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternWave {
+    volume: f32,
+    /// Position inside of the pattern, in bits
+    position: f32,
+}
+
+impl Default for PatternWave {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            position: 0.0,
+        }
+    }
+}
+
+impl PatternWave {
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    /// Set the volume, clamped to 0.0..=1.0
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = clamp_volume(volume);
+    }
+
+    /// Fill the buffer with the pattern played at the given pitch for its
+    /// first `on_samples` samples and with silence after them
+    pub fn fill(
+        &mut self,
+        pattern: &[u8; 16],
+        pitch: u8,
+        buf: &mut [f32],
+        sample_rate: u32,
+        on_samples: usize,
+    ) {
+        self.fill_with(pattern, pitch, buf, sample_rate, on_samples);
+    }
+
+    /// Like [`PatternWave::fill`], for hosts playing signed 16 bit samples
+    pub fn fill_i16(
+        &mut self,
+        pattern: &[u8; 16],
+        pitch: u8,
+        buf: &mut [i16],
+        sample_rate: u32,
+        on_samples: usize,
+    ) {
+        self.fill_with(pattern, pitch, buf, sample_rate, on_samples);
+    }
+
+    fn fill_with<T: Sample>(
+        &mut self,
+        pattern: &[u8; 16],
+        pitch: u8,
+        buf: &mut [T],
+        sample_rate: u32,
+        on_samples: usize,
+    ) {
+        let step = pattern_rate(pitch) / sample_rate.max(1) as f32;
+        let high = T::HIGH.scale(self.volume);
+        let (on, off) = buf.split_at_mut(on_samples.min(buf.len()));
+        for sample in on {
+            let bit = self.position as usize % 128;
+            let is_set = pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+            *sample = if is_set { high } else { high.negate() };
+            self.position = (self.position + step) % 128.0;
+        }
+        off.fill(T::SILENCE);
+    }
+}
+
+/// Shares the buzzer state between the emulator loop and an audio
+/// callback without locking. The emulator loop calls [`BuzzerHandle::set_on`]
+/// once per frame, the audio thread renders with [`BuzzerHandle::render`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct BuzzerHandle {
+    shared: Arc<SharedBuzzer>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct SharedBuzzer {
+    on: AtomicBool,
+    /// The bits of the f32 frequency
+    frequency: AtomicU32,
+    /// The bits of the f32 volume
+    volume: AtomicU32,
+}
+
+#[cfg(feature = "std")]
+impl Default for BuzzerHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl BuzzerHandle {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(SharedBuzzer {
+                on: AtomicBool::new(false),
+                frequency: AtomicU32::new(DEFAULT_BUZZER_HZ.to_bits()),
+                volume: AtomicU32::new(1.0f32.to_bits()),
+            }),
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.shared.on.load(Ordering::Relaxed)
+    }
+
+    pub fn set_on(&self, on: bool) {
+        self.shared.on.store(on, Ordering::Relaxed);
+    }
+
+    pub fn frequency(&self) -> f32 {
+        f32::from_bits(self.shared.frequency.load(Ordering::Relaxed))
+    }
+
+    pub fn set_frequency(&self, frequency: f32) {
+        self.shared
+            .frequency
+            .store(frequency.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn volume(&self) -> f32 {
+        f32::from_bits(self.shared.volume.load(Ordering::Relaxed))
+    }
+
+    /// Set the volume, clamped to 0.0..=1.0
+    pub fn set_volume(&self, volume: f32) {
+        let volume = clamp_volume(volume);
+        self.shared
+            .volume
+            .store(volume.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Fill an output buffer from the audio thread. The wave is owned
+    /// by the audio thread, so its phase carries over between buffers.
+    pub fn render(&self, wave: &mut SquareWave, buf: &mut [f32], sample_rate: u32) {
+        self.render_with(wave, buf, sample_rate);
+    }
+
+    fn render_with<T: Sample>(&self, wave: &mut SquareWave, buf: &mut [T], sample_rate: u32) {
+        wave.set_frequency(self.frequency());
+        wave.set_volume(self.volume());
+        let on_samples = if self.is_on() { buf.len() } else { 0 };
+        wave.fill_with(buf, sample_rate, on_samples);
+    }
+}
+
+/// The buzzer state for a browser AudioWorklet. Update it once per frame
+/// with [`crate::emulator::Emulator::export_audio`], then render from it.
+/// Rendering uses up the remaining time, so a beep ends in the middle
+/// of a frame even though the state is only updated once per frame.
+#[cfg(feature = "js")]
+#[derive(Debug, Clone, Default)]
+pub struct AudioExport {
+    remaining_ms: f64,
+    wave: SquareWave,
+    /// Samples JS can view in the wasm memory without copying them
+    samples: Vec<f32>,
+}
+
+#[cfg(feature = "js")]
+impl AudioExport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.remaining_ms > 0.0
+    }
+
+    /// How long the current beep keeps playing
+    pub fn remaining_ms(&self) -> f64 {
+        self.remaining_ms
+    }
+
+    pub(crate) fn update(&mut self, remaining: core::time::Duration, wave: &SquareWave) {
+        self.remaining_ms = remaining.as_secs_f64() * 1000.0;
+        self.wave.set_frequency(wave.frequency());
+        self.wave.set_volume(wave.volume());
+    }
+
+    /// Render the buzzer into the given buffer, using up its time
+    pub fn fill_samples(&mut self, out: &mut [f32], sample_rate: u32) {
+        let sample_rate = sample_rate.max(1);
+        let on_samples = (self.remaining_ms * sample_rate as f64 / 1000.0) as usize;
+        self.wave.fill(out, sample_rate, on_samples);
+        let rendered_ms = out.len() as f64 * 1000.0 / sample_rate as f64;
+        self.remaining_ms = (self.remaining_ms - rendered_ms).max(0.0);
+    }
+
+    /// Render `len` samples into the internal buffer,
+    /// which JS can then view through [`AudioExport::samples_ptr`]
+    pub fn fill_buffer(&mut self, len: usize, sample_rate: u32) {
+        let mut samples = core::mem::take(&mut self.samples);
+        samples.resize(len, 0.0);
+        self.fill_samples(&mut samples, sample_rate);
+        self.samples = samples;
+    }
+
+    /// The start of the internal buffer, to construct a
+    /// `Float32Array` on the wasm memory together with [`AudioExport::samples_len`]
+    pub fn samples_ptr(&self) -> *const f32 {
+        self.samples.as_ptr()
+    }
+
+    pub fn samples_len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+}
+
+/// The number of samples the given value of the sound
+/// register keeps the buzzer on for
+pub(crate) fn sound_samples(sound: u8, sample_rate: u32) -> usize {
+    unit_samples(sound as u64 * 1000, sample_rate)
+}
+
+/// The number of samples lasting the given thousandths of timer steps
+pub(crate) fn unit_samples(units: u64, sample_rate: u32) -> usize {
+    (units * sample_rate as u64 / (TICKS_PER_SECOND * 1000)) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wave_switches_at_half_period() {
+        let mut wave = SquareWave::new(1000.0);
+        let mut buf = [0.0; 8];
+        wave.fill(&mut buf, 4000, 8);
+        assert_eq!([1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0, -1.0], buf);
+    }
+
+    #[test]
+    fn phase_continues_across_buffers() {
+        let mut wave = SquareWave::new(1000.0);
+        let mut buf = [0; 3];
+        wave.fill_i16(&mut buf, 4000, 3);
+        assert_eq!([i16::MAX, i16::MAX, -i16::MAX], buf);
+        wave.fill_i16(&mut buf, 4000, 2);
+        assert_eq!([-i16::MAX, i16::MAX, 0], buf);
+    }
+
+    #[test]
+    fn frequency_changes_keep_the_phase() {
+        let mut wave = SquareWave::new(1000.0);
+        wave.set_volume(-1.0);
+        assert_eq!(0.0, wave.volume());
+        wave.set_volume(0.25);
+        let mut buf = [0.0; 3];
+        wave.fill(&mut buf, 4000, 3);
+        assert_eq!([0.25, 0.25, -0.25], buf);
+
+        // Half the frequency continues in the low half of the period
+        wave.set_frequency(500.0);
+        let mut buf = [0.0; 6];
+        wave.fill(&mut buf, 4000, 6);
+        assert_eq!([-0.25, -0.25, 0.25, 0.25, 0.25, 0.25], buf);
+    }
+
+    #[test]
+    fn pitch_scales_by_octaves() {
+        assert_eq!(4000.0, pattern_rate(64));
+        assert_eq!(8000.0, pattern_rate(112));
+        assert_eq!(2000.0, pattern_rate(16));
+        assert!((pattern_rate(76) - 4756.828).abs() < 0.01);
+    }
+
+    #[test]
+    fn pattern_plays_bits_in_order() {
+        let mut pattern = [0; 16];
+        pattern[0] = 0b1011_0000;
+        pattern[15] = 0b0000_0001;
+        let mut wave = PatternWave::default();
+        // One bit per sample at the default pitch
+        let mut buf = [0.0; 6];
+        wave.fill(&pattern, DEFAULT_PITCH, &mut buf, 4000, 5);
+        assert_eq!([1.0, -1.0, 1.0, 1.0, -1.0, 0.0], buf);
+
+        let mut buf = [0; 128];
+        wave.fill_i16(&pattern, DEFAULT_PITCH, &mut buf, 4000, 128);
+        assert_eq!(-i16::MAX, buf[121]);
+        assert_eq!(i16::MAX, buf[122]);
+        // The pattern loops around
+        assert_eq!(i16::MAX, buf[123]);
+        assert_eq!(-i16::MAX, buf[124]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn handle_renders_from_other_threads() {
+        let handle = BuzzerHandle::new();
+        handle.set_frequency(1000.0);
+        handle.set_volume(2.0);
+        assert_eq!(1.0, handle.volume());
+
+        let audio = handle.clone();
+        let render = move || {
+            let mut wave = SquareWave::default();
+            let mut buf = [0.0; 4];
+            audio.render(&mut wave, &mut buf, 4000);
+            buf
+        };
+        assert_eq!([0.0; 4], std::thread::spawn(render.clone()).join().unwrap());
+
+        handle.set_on(true);
+        handle.set_volume(0.5);
+        assert_eq!(
+            [0.5, 0.5, -0.5, -0.5],
+            std::thread::spawn(render).join().unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "js")]
+    fn export_uses_up_the_beep() {
+        let mut export = AudioExport::new();
+        export.update(
+            core::time::Duration::from_millis(10),
+            &SquareWave::new(1000.0),
+        );
+        assert!(export.is_on());
+
+        // 10 ms play 40 samples at 4000 Hz
+        export.fill_buffer(32, 4000);
+        assert_eq!(32, export.samples_len());
+        assert!(export.samples().iter().all(|sample| *sample != 0.0));
+        assert_eq!(2.0, export.remaining_ms());
+
+        export.fill_buffer(32, 4000);
+        assert!(export.samples()[..8].iter().all(|sample| *sample != 0.0));
+        assert!(export.samples()[8..].iter().all(|sample| *sample == 0.0));
+        assert!(!export.is_on());
+    }
+
+    #[test]
+    fn sound_register_converts_to_samples() {
+        assert_eq!(0, sound_samples(0, 44_100));
+        assert_eq!(735, sound_samples(1, 44_100));
+        assert_eq!(48_000, sound_samples(60, 48_000));
+    }
+}
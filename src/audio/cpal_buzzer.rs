@@ -0,0 +1,128 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+use super::{BuzzerHandle, Sample, SquareWave};
+use crate::error::AudioError;
+
+/// Plays the buzzer on a cpal output device. The stream runs on its own
+/// audio thread; the emulator loop only updates the shared state, e.g.
+/// `buzzer.set_on(emulator.is_sound_on())` once per frame.
+/// Dropping the buzzer stops and closes the stream.
+pub struct CpalBuzzer {
+    handle: BuzzerHandle,
+    stream: cpal::Stream,
+}
+
+impl core::fmt::Debug for CpalBuzzer {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CpalBuzzer")
+            .field("handle", &self.handle)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CpalBuzzer {
+    /// Open the default output device of the default host
+    pub fn default_output() -> Result<Self, AudioError> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or(AudioError::NoDevice)?;
+        Self::new(device)
+    }
+
+    /// Play on the given device, in its default output configuration
+    pub fn new(device: cpal::Device) -> Result<Self, AudioError> {
+        let supported = device
+            .default_output_config()
+            .map_err(AudioError::DefaultConfig)?;
+        let format = supported.sample_format();
+        let config = supported.config();
+        let handle = BuzzerHandle::new();
+        let stream = match format {
+            cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config, handle.clone()),
+            cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config, handle.clone()),
+            other => return Err(AudioError::UnsupportedFormat(other)),
+        }?;
+        stream.play().map_err(AudioError::PlayStream)?;
+        Ok(Self { handle, stream })
+    }
+
+    /// A handle sharing the state of this buzzer, e.g. for another thread
+    pub fn handle(&self) -> BuzzerHandle {
+        self.handle.clone()
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.handle.is_on()
+    }
+
+    pub fn set_on(&self, on: bool) {
+        self.handle.set_on(on);
+    }
+
+    pub fn frequency(&self) -> f32 {
+        self.handle.frequency()
+    }
+
+    pub fn set_frequency(&self, frequency: f32) {
+        self.handle.set_frequency(frequency);
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.handle.volume()
+    }
+
+    /// Set the volume, clamped to 0.0..=1.0
+    pub fn set_volume(&self, volume: f32) {
+        self.handle.set_volume(volume);
+    }
+}
+
+impl Drop for CpalBuzzer {
+    fn drop(&mut self) {
+        // Silence the device right away, the stream closes when it is dropped
+        self.handle.set_on(false);
+        let _ = self.stream.pause();
+    }
+}
+
+fn build_stream<T: Sample + cpal::SizedSample>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    handle: BuzzerHandle,
+) -> Result<cpal::Stream, AudioError> {
+    let channels = config.channels.max(1) as usize;
+    let sample_rate = config.sample_rate.0;
+    let mut wave = SquareWave::default();
+    device
+        .build_output_stream(
+            config,
+            move |buf: &mut [T], _: &cpal::OutputCallbackInfo| {
+                // Render one sample per frame, then copy it to every channel.
+                // Going backwards never overwrites a sample before it is copied.
+                let frames = buf.len() / channels;
+                handle.render_with(&mut wave, &mut buf[..frames], sample_rate);
+                for frame in (0..frames).rev() {
+                    let sample = buf[frame];
+                    buf[frame * channels..(frame + 1) * channels].fill(sample);
+                }
+            },
+            |err| log_warn!("audio stream error: {}", err),
+            None,
+        )
+        .map_err(AudioError::BuildStream)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a sound device"]
+    fn plays_on_the_default_device() {
+        let buzzer = CpalBuzzer::default_output().unwrap();
+        buzzer.set_volume(0.1);
+        buzzer.set_on(true);
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert!(buzzer.is_on());
+    }
+}
@@ -1,9 +1,24 @@
-use crate::opcode::OpCode;
+#[cfg(feature = "schip")]
+use crate::display::DisplayMode;
+use crate::{debug::InstructionKind, opcode::OpCode};
 
 #[rustfmt::skip]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum Command {
     ClearScreen,
     ReturnFromSubroutine,
+    #[cfg(feature = "schip")]
+    SetDisplayMode { mode: DisplayMode },
+    #[cfg(feature = "schip")]
+    ScrollDown { rows: u8 },
+    #[cfg(feature = "xochip")]
+    ScrollUp { rows: u8 },
+    #[cfg(feature = "schip")]
+    ScrollRight,
+    #[cfg(feature = "schip")]
+    ScrollLeft,
+    #[cfg(feature = "schip")]
+    Exit,
     Jump { address: u16 },
     JumpOffset { address: u16, register: u8 },
     Call { address: u16 },
@@ -14,6 +29,8 @@ pub(crate) enum Command {
     Load { register: u8, value: u8 },
     LoadI { value: u16 },
     LoadSpriteDigitIntoI { read_register: u8 },
+    #[cfg(feature = "schip")]
+    LoadLargeSpriteDigitIntoI { read_register: u8 },
     LoadBcd { read_register: u8 },
     Add { register: u8, value: u8 },
     AddRegisters { write: u8, read: u8 },
@@ -36,7 +53,29 @@ pub(crate) enum Command {
     WaitKeyPress {register: u8 },
     DumpAll { until_register: u8 },
     LoadAll { until_register: u8 },
-    NoOp,
+    #[cfg(feature = "schip")]
+    SaveFlags { until_register: u8 },
+    #[cfg(feature = "schip")]
+    LoadFlags { until_register: u8 },
+    /// Select the planes drawing, clearing and scrolling act on by a bitmask
+    #[cfg(feature = "xochip")]
+    SelectPlanes { planes: u8 },
+    /// Save the registers from first to last to memory at I,
+    /// in reverse order if last is the lower register
+    #[cfg(feature = "xochip")]
+    SaveRegisterRange { first: u8, last: u8 },
+    /// Load the registers from first to last from memory at I
+    #[cfg(feature = "xochip")]
+    LoadRegisterRange { first: u8, last: u8 },
+    /// Load I from the word following the instruction
+    #[cfg(feature = "xochip")]
+    LoadLongI,
+    #[cfg(feature = "xochip")]
+    LoadAudioPattern,
+    #[cfg(feature = "xochip")]
+    SetPitch { register: u8 },
+    /// An opcode that could not be decoded
+    Invalid { opcode: u16 },
 }
 
 impl From<OpCode> for Command {
@@ -44,6 +83,28 @@ impl From<OpCode> for Command {
         match value {
             OpCode::ClearScreen(_) => Command::ClearScreen,
             OpCode::Return(_) => Command::ReturnFromSubroutine,
+            #[cfg(feature = "schip")]
+            OpCode::ScrollDown(value) => Command::ScrollDown {
+                rows: value.nibble_3(),
+            },
+            #[cfg(feature = "xochip")]
+            OpCode::ScrollUp(value) => Command::ScrollUp {
+                rows: value.nibble_3(),
+            },
+            #[cfg(feature = "schip")]
+            OpCode::ScrollRight(_) => Command::ScrollRight,
+            #[cfg(feature = "schip")]
+            OpCode::ScrollLeft(_) => Command::ScrollLeft,
+            #[cfg(feature = "schip")]
+            OpCode::Exit(_) => Command::Exit,
+            #[cfg(feature = "schip")]
+            OpCode::LowRes(_) => Command::SetDisplayMode {
+                mode: DisplayMode::LowRes,
+            },
+            #[cfg(feature = "schip")]
+            OpCode::HighRes(_) => Command::SetDisplayMode {
+                mode: DisplayMode::HighRes,
+            },
             OpCode::Jump(value) => Command::Jump {
                 address: value.skip_first_nibble(),
             },
@@ -150,16 +211,222 @@ impl From<OpCode> for Command {
             OpCode::LoadSprite(value) => Command::LoadSpriteDigitIntoI {
                 read_register: value.nibble_1(),
             },
+            #[cfg(feature = "schip")]
+            OpCode::LoadLargeSprite(value) => Command::LoadLargeSpriteDigitIntoI {
+                read_register: value.nibble_1(),
+            },
             OpCode::LoadBcd(value) => Command::LoadBcd {
                 read_register: value.nibble_1(),
             },
             OpCode::LoadAll(value) => Command::LoadAll {
                 until_register: value.nibble_1(),
             },
+            #[cfg(feature = "schip")]
+            OpCode::SaveFlags(value) => Command::SaveFlags {
+                until_register: value.nibble_1(),
+            },
+            #[cfg(feature = "schip")]
+            OpCode::LoadFlags(value) => Command::LoadFlags {
+                until_register: value.nibble_1(),
+            },
             OpCode::DumpAll(value) => Command::DumpAll {
                 until_register: value.nibble_1(),
             },
-            OpCode::Invalid(_) => Command::NoOp,
+            #[cfg(feature = "xochip")]
+            OpCode::SelectPlanes(value) => Command::SelectPlanes {
+                planes: value.nibble_1(),
+            },
+            #[cfg(feature = "xochip")]
+            OpCode::SaveRegisterRange(value) => Command::SaveRegisterRange {
+                first: value.nibble_1(),
+                last: value.nibble_2(),
+            },
+            #[cfg(feature = "xochip")]
+            OpCode::LoadRegisterRange(value) => Command::LoadRegisterRange {
+                first: value.nibble_1(),
+                last: value.nibble_2(),
+            },
+            #[cfg(feature = "xochip")]
+            OpCode::LoadLongI(_) => Command::LoadLongI,
+            #[cfg(feature = "xochip")]
+            OpCode::LoadAudioPattern(_) => Command::LoadAudioPattern,
+            #[cfg(feature = "xochip")]
+            OpCode::SetPitch(value) => Command::SetPitch {
+                register: value.nibble_1(),
+            },
+            OpCode::Invalid(value) => Command::Invalid { opcode: value },
+        }
+    }
+}
+
+impl Command {
+    pub(crate) fn kind(&self) -> InstructionKind {
+        match self {
+            Command::ClearScreen => InstructionKind::ClearScreen,
+            Command::ReturnFromSubroutine => InstructionKind::ReturnFromSubroutine,
+            #[cfg(feature = "schip")]
+            Command::SetDisplayMode { .. } => InstructionKind::SetDisplayMode,
+            #[cfg(feature = "schip")]
+            Command::ScrollDown { .. } => InstructionKind::ScrollDown,
+            #[cfg(feature = "xochip")]
+            Command::ScrollUp { .. } => InstructionKind::ScrollUp,
+            #[cfg(feature = "schip")]
+            Command::ScrollRight => InstructionKind::ScrollRight,
+            #[cfg(feature = "schip")]
+            Command::ScrollLeft => InstructionKind::ScrollLeft,
+            #[cfg(feature = "schip")]
+            Command::Exit => InstructionKind::Exit,
+            Command::Jump { .. } => InstructionKind::Jump,
+            Command::JumpOffset { .. } => InstructionKind::JumpOffset,
+            Command::Call { .. } => InstructionKind::Call,
+            Command::SkipIfValueEqual { .. } => InstructionKind::SkipIfValueEqual,
+            Command::SkipIfValueNotEqual { .. } => InstructionKind::SkipIfValueNotEqual,
+            Command::SkipIfRegisterEqual { .. } => InstructionKind::SkipIfRegisterEqual,
+            Command::SkipIfRegisterNotEqual { .. } => InstructionKind::SkipIfRegisterNotEqual,
+            Command::Load { .. } => InstructionKind::Load,
+            Command::LoadI { .. } => InstructionKind::LoadI,
+            Command::LoadSpriteDigitIntoI { .. } => InstructionKind::LoadSpriteDigitIntoI,
+            #[cfg(feature = "schip")]
+            Command::LoadLargeSpriteDigitIntoI { .. } => InstructionKind::LoadLargeSpriteDigitIntoI,
+            Command::LoadBcd { .. } => InstructionKind::LoadBcd,
+            Command::Add { .. } => InstructionKind::Add,
+            Command::AddRegisters { .. } => InstructionKind::AddRegisters,
+            Command::AddI { .. } => InstructionKind::AddI,
+            Command::CopyRegister { .. } => InstructionKind::CopyRegister,
+            Command::Or { .. } => InstructionKind::Or,
+            Command::And { .. } => InstructionKind::And,
+            Command::Xor { .. } => InstructionKind::Xor,
+            Command::Sub { .. } => InstructionKind::Sub,
+            Command::SubInverse { .. } => InstructionKind::SubInverse,
+            Command::ShiftRight { .. } => InstructionKind::ShiftRight,
+            Command::ShiftLeft { .. } => InstructionKind::ShiftLeft,
+            Command::RandomAnd { .. } => InstructionKind::RandomAnd,
+            Command::DrawSprite { .. } => InstructionKind::DrawSprite,
+            Command::SkipIfKeyPressed { .. } => InstructionKind::SkipIfKeyPressed,
+            Command::SkipIfKeyNotPressed { .. } => InstructionKind::SkipIfKeyNotPressed,
+            Command::LoadDelay { .. } => InstructionKind::LoadDelay,
+            Command::SetDelay { .. } => InstructionKind::SetDelay,
+            Command::SetSound { .. } => InstructionKind::SetSound,
+            Command::WaitKeyPress { .. } => InstructionKind::WaitKeyPress,
+            Command::DumpAll { .. } => InstructionKind::DumpAll,
+            Command::LoadAll { .. } => InstructionKind::LoadAll,
+            #[cfg(feature = "schip")]
+            Command::SaveFlags { .. } => InstructionKind::SaveFlags,
+            #[cfg(feature = "schip")]
+            Command::LoadFlags { .. } => InstructionKind::LoadFlags,
+            #[cfg(feature = "xochip")]
+            Command::SelectPlanes { .. } => InstructionKind::SelectPlanes,
+            #[cfg(feature = "xochip")]
+            Command::SaveRegisterRange { .. } => InstructionKind::SaveRegisterRange,
+            #[cfg(feature = "xochip")]
+            Command::LoadRegisterRange { .. } => InstructionKind::LoadRegisterRange,
+            #[cfg(feature = "xochip")]
+            Command::LoadLongI => InstructionKind::LoadLongI,
+            #[cfg(feature = "xochip")]
+            Command::LoadAudioPattern => InstructionKind::LoadAudioPattern,
+            #[cfg(feature = "xochip")]
+            Command::SetPitch { .. } => InstructionKind::SetPitch,
+            Command::Invalid { .. } => InstructionKind::Invalid,
+        }
+    }
+}
+
+/// Mnemonics in the style of Cowgod's reference, with hex operands
+impl core::fmt::Display for Command {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Command::ClearScreen => write!(f, "CLS"),
+            Command::ReturnFromSubroutine => write!(f, "RET"),
+            #[cfg(feature = "schip")]
+            Command::SetDisplayMode { mode } => match mode {
+                DisplayMode::LowRes => write!(f, "LOW"),
+                DisplayMode::HighRes => write!(f, "HIGH"),
+            },
+            #[cfg(feature = "schip")]
+            Command::ScrollDown { rows } => write!(f, "SCD {}", rows),
+            #[cfg(feature = "xochip")]
+            Command::ScrollUp { rows } => write!(f, "SCU {}", rows),
+            #[cfg(feature = "schip")]
+            Command::ScrollRight => write!(f, "SCR"),
+            #[cfg(feature = "schip")]
+            Command::ScrollLeft => write!(f, "SCL"),
+            #[cfg(feature = "schip")]
+            Command::Exit => write!(f, "EXIT"),
+            Command::Jump { address } => write!(f, "JP {:#05X}", address),
+            Command::JumpOffset { address, .. } => write!(f, "JP V0, {:#05X}", address),
+            Command::Call { address } => write!(f, "CALL {:#05X}", address),
+            Command::SkipIfValueEqual { register, value } => {
+                write!(f, "SE V{:X}, {:#04X}", register, value)
+            }
+            Command::SkipIfValueNotEqual { register, value } => {
+                write!(f, "SNE V{:X}, {:#04X}", register, value)
+            }
+            Command::SkipIfRegisterEqual {
+                register_a,
+                register_b,
+            } => write!(f, "SE V{:X}, V{:X}", register_a, register_b),
+            Command::SkipIfRegisterNotEqual {
+                register_a,
+                register_b,
+            } => write!(f, "SNE V{:X}, V{:X}", register_a, register_b),
+            Command::Load { register, value } => write!(f, "LD V{:X}, {:#04X}", register, value),
+            Command::LoadI { value } => write!(f, "LD I, {:#05X}", value),
+            Command::LoadSpriteDigitIntoI { read_register } => {
+                write!(f, "LD F, V{:X}", read_register)
+            }
+            #[cfg(feature = "schip")]
+            Command::LoadLargeSpriteDigitIntoI { read_register } => {
+                write!(f, "LD HF, V{:X}", read_register)
+            }
+            Command::LoadBcd { read_register } => write!(f, "LD B, V{:X}", read_register),
+            Command::Add { register, value } => write!(f, "ADD V{:X}, {:#04X}", register, value),
+            Command::AddRegisters { write, read } => write!(f, "ADD V{:X}, V{:X}", write, read),
+            Command::AddI { read } => write!(f, "ADD I, V{:X}", read),
+            Command::CopyRegister { write, read } => write!(f, "LD V{:X}, V{:X}", write, read),
+            Command::Or { write, read } => write!(f, "OR V{:X}, V{:X}", write, read),
+            Command::And { write, read } => write!(f, "AND V{:X}, V{:X}", write, read),
+            Command::Xor { write, read } => write!(f, "XOR V{:X}, V{:X}", write, read),
+            Command::Sub { write, read } => write!(f, "SUB V{:X}, V{:X}", write, read),
+            Command::SubInverse { write, read } => write!(f, "SUBN V{:X}, V{:X}", write, read),
+            Command::ShiftRight { write, read } => write!(f, "SHR V{:X}, V{:X}", write, read),
+            Command::ShiftLeft { write, read } => write!(f, "SHL V{:X}, V{:X}", write, read),
+            Command::RandomAnd { register, value } => {
+                write!(f, "RND V{:X}, {:#04X}", register, value)
+            }
+            Command::DrawSprite {
+                register_x,
+                register_y,
+                value,
+            } => write!(f, "DRW V{:X}, V{:X}, {}", register_x, register_y, value),
+            Command::SkipIfKeyPressed { key_register } => write!(f, "SKP V{:X}", key_register),
+            Command::SkipIfKeyNotPressed { key_register } => write!(f, "SKNP V{:X}", key_register),
+            Command::LoadDelay { register } => write!(f, "LD V{:X}, DT", register),
+            Command::SetDelay { register } => write!(f, "LD DT, V{:X}", register),
+            Command::SetSound { register } => write!(f, "LD ST, V{:X}", register),
+            Command::WaitKeyPress { register } => write!(f, "LD V{:X}, K", register),
+            Command::DumpAll { until_register } => write!(f, "LD [I], V{:X}", until_register),
+            Command::LoadAll { until_register } => write!(f, "LD V{:X}, [I]", until_register),
+            #[cfg(feature = "schip")]
+            Command::SaveFlags { until_register } => write!(f, "LD R, V{:X}", until_register),
+            #[cfg(feature = "schip")]
+            Command::LoadFlags { until_register } => write!(f, "LD V{:X}, R", until_register),
+            #[cfg(feature = "xochip")]
+            Command::SelectPlanes { planes } => write!(f, "PLANE {}", planes),
+            #[cfg(feature = "xochip")]
+            Command::SaveRegisterRange { first, last } => {
+                write!(f, "SAVE V{:X} - V{:X}", first, last)
+            }
+            #[cfg(feature = "xochip")]
+            Command::LoadRegisterRange { first, last } => {
+                write!(f, "LOAD V{:X} - V{:X}", first, last)
+            }
+            #[cfg(feature = "xochip")]
+            Command::LoadLongI => write!(f, "LD I, LONG"),
+            #[cfg(feature = "xochip")]
+            Command::LoadAudioPattern => write!(f, "AUDIO"),
+            #[cfg(feature = "xochip")]
+            Command::SetPitch { register } => write!(f, "PITCH V{:X}", register),
+            Command::Invalid { opcode } => write!(f, "DW {:#06X}", opcode),
         }
     }
 }
@@ -171,7 +438,6 @@ trait OpCodeShift {
     fn nibble_1(&self) -> Self::HalfOutput;
     fn nibble_2(&self) -> Self::HalfOutput;
     fn nibble_3(&self) -> Self::HalfOutput;
-    fn front(&self) -> Self::HalfOutput;
     fn back(&self) -> Self::HalfOutput;
 }
 
@@ -189,10 +455,6 @@ impl OpCodeShift for u16 {
         (result >> 12) as u8
     }
 
-    fn front(&self) -> Self::HalfOutput {
-        (*self >> 8) as u8
-    }
-
     fn back(&self) -> Self::HalfOutput {
         let result = *self << 8;
         (result >> 8) as u8
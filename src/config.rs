@@ -1,3 +1,35 @@
+use crate::audio::DEFAULT_BUZZER_HZ;
+use crate::error::ConfigError;
+use crate::memory::{CHIP8_START, ETI660_START, MAX_MEMORY_SIZE, MAX_STACK_DEPTH, MEMORY_SIZE};
+
+#[cfg(feature = "std")]
+mod library;
+#[cfg(feature = "std")]
+pub use library::RomLibraryConfig;
+
+/// Programs for the original chip-8 interpreter are loaded at 0x200
+pub const CHIP8_START_ADDRESS: u16 = CHIP8_START as u16;
+/// Programs for the ETI-660 computer are loaded at 0x600
+pub const ETI660_START_ADDRESS: u16 = ETI660_START as u16;
+/// The font sprites are traditionally stored at 0x050
+pub const DEFAULT_FONT_BASE: u16 = 0x050;
+/// On SUPER-CHIP and XO-CHIP the large font FX30 points into
+/// is stored right after the 80 bytes of the small font
+pub const LARGE_FONT_OFFSET: u16 = 80;
+/// The large font has 16 glyphs of 10 bytes each
+const LARGE_FONT_LEN: usize = 160;
+/// The 4 KiB of memory of chip-8 and SUPER-CHIP
+pub const CHIP8_MEMORY_SIZE: usize = MEMORY_SIZE;
+/// XO-CHIP roms address the full 64 KiB of 16-bit addresses
+pub const XO_CHIP_MEMORY_SIZE: usize = 0x10000;
+/// The original COSMAC VIP interpreter allowed 12 nested subroutines
+pub const ORIGINAL_STACK_DEPTH: usize = 12;
+/// More modern interpreters allow 16 nested subroutines,
+/// which is also the maximum supported depth
+pub const MODERN_STACK_DEPTH: usize = MAX_STACK_DEPTH;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ShiftStyle {
     /// Shift the value in the given register in-place
     ShiftInPlace,
@@ -5,38 +37,911 @@ pub enum ShiftStyle {
     /// The value that got copied into the x register
     CopyThenShift,
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum JumpOffsetStyle {
-    /// Always calculate the offset from the value stored in register v0
+    /// BNNN jumps to NNN plus the value stored in register v0
     OffsetFromV0,
-    /// Load the offset dynamically from the register given in the opcode
+    /// BXNN jumps to XNN plus the value stored in register vX, like CHIP-48 and
+    /// SUPER-CHIP. The X nibble selects the register and is also the high
+    /// nibble of the address, so it is not masked off.
     OffsetVariable,
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DumpLoadStyle {
-    /// The original interpreter increments the I register while
-    /// performing a register dump / load
-    AffectIRegister,
+    /// The original interpreter increments the I register for every
+    /// register dumped / loaded, so I ends up at I + X + 1
+    IncrementI,
+    /// CHIP-48 increments the I register by one less,
+    /// so I ends up at I + X, pointing at the last address
+    IncrementIByX,
     /// More modern interpreters use a temporary variable while
     /// performing a register dump / load, so the I register stays static
     StaticIRegister,
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PcOverflowStyle {
+    /// Continue execution at the configured start address
+    Wrap,
+    /// Stop the emulator with a [`crate::error::Chip8Error::PcOutOfRange`]
+    Fault,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InvalidOpcodeStyle {
+    /// Log the invalid opcode and continue with the next instruction
+    Skip,
+    /// Stop the emulator with a [`crate::error::Chip8Error::InvalidOpcode`]
+    Halt,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WaitKeyStyle {
+    /// FX0A completes as soon as a key is pressed
+    OnPress,
+    /// FX0A remembers the key that goes down and only completes once it
+    /// is released again, like on the COSMAC VIP
+    OnRelease,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyInputStyle {
+    /// FX0A reads which keys are currently pressed
+    Level,
+    /// FX0A consumes key events, so holding a key only satisfies a single FX0A
+    Events,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimerStyle {
+    /// Every tick drains the timers by the time passed on the emulator's clock
+    WallClock,
+    /// The timers are left to the host, which calls
+    /// [`crate::emulator::Emulator::tick_timers`] at 60 Hz,
+    /// or once per [`crate::emulator::Emulator::run_frame`]
+    HostDriven,
+    /// The timers step once every n executed instructions, without consulting
+    /// any clock. This makes runs reproducible regardless of host speed.
+    PerInstructions(u32),
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LoresSpriteStyle {
+    /// DXY0 draws a sprite of zero rows, so nothing, like on chip-8
+    Nothing,
+    /// DXY0 draws 16 rows of 8 pixels, like SUPER-CHIP 1.1
+    Tall,
+    /// DXY0 draws the same 16x16 sprite as in high resolution, like XO-CHIP
+    Large,
+}
+/// What DXYN sets VF to in high resolution.
+/// Sprites are clipped at the edges of the screen, so rows below
+/// the bottom edge are never drawn and can't collide by themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CollisionStyle {
+    /// VF is 1 if any pixel was turned off, like in low resolution
+    Flag,
+    /// VF counts the sprite rows that turned off a pixel
+    Rows,
+    /// VF counts the rows that turned off a pixel plus the rows
+    /// clipped at the bottom of the screen, like SUPER-CHIP 1.1
+    RowsAndClipped,
+}
+/// How far 00CN, 00DN, 00FB and 00FC scroll in low resolution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScrollStyle {
+    /// Scroll by pixels of the high resolution, which are half
+    /// a low resolution pixel, like SUPER-CHIP 1.1
+    HalfPixel,
+    /// Scroll by pixels of the active resolution
+    FullPixel,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Platform {
+    /// The original chip-8 instruction set
+    Chip8,
+    /// The SUPER-CHIP extensions on top of chip-8
+    SuperChip,
+    /// The XO-CHIP extensions, which include the SUPER-CHIP ones
+    XoChip,
+}
+
+impl Platform {
+    /// Whether the SUPER-CHIP instructions are available
+    pub const fn has_super_chip(self) -> bool {
+        matches!(self, Platform::SuperChip | Platform::XoChip)
+    }
+
+    /// Whether the cargo feature of the platform's extensions is enabled
+    pub const fn is_enabled(self) -> bool {
+        match self {
+            Platform::Chip8 => true,
+            Platform::SuperChip => cfg!(feature = "schip"),
+            Platform::XoChip => cfg!(feature = "xochip"),
+        }
+    }
+}
+
+/// The choices for the instructions the interpreters disagree on.
+/// Start from a preset and change single quirks with the builder methods:
+///
+/// ```
+/// use chip8::config::{QuirkSet, ShiftStyle};
+///
+/// let quirks = QuirkSet::cosmac_vip().with_shift(ShiftStyle::ShiftInPlace);
+/// assert!(quirks.logic_resets_vf);
+/// ```
+///
+/// No state of the emulator depends on the quirks it ran with before,
+/// so they can be changed between any two ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct QuirkSet {
+    pub shift: ShiftStyle,
+    pub jump: JumpOffsetStyle,
+    pub dump_load: DumpLoadStyle,
+    /// The COSMAC VIP interpreter clears VF as a side effect
+    /// of the logic instructions 8XY1, 8XY2 and 8XY3
+    pub logic_resets_vf: bool,
+    /// The Amiga interpreter sets VF to 1 when FX1E moves I past 0xFFF
+    /// and to 0 otherwise. Spacefight 2091! depends on it, while
+    /// other games break with it, so none of the presets enable it.
+    pub index_add_sets_flag: bool,
+    /// When waiting for a key with FX0A completes
+    pub wait_key: WaitKeyStyle,
+    /// Most interpreters clear the screen when 00FE and 00FF switch
+    /// the resolution, SUPER-CHIP 1.1 keeps the picture
+    pub mode_switch_clears: bool,
+    /// What DXY0 draws in low resolution on SUPER-CHIP and XO-CHIP.
+    /// In high resolution it always draws a 16x16 sprite.
+    pub lores_dxy0: LoresSpriteStyle,
+    /// What DXYN sets VF to in high resolution.
+    /// In low resolution VF is always 0 or 1.
+    pub collision: CollisionStyle,
+    /// How far the scroll instructions move the picture in low resolution.
+    /// In high resolution they always scroll by whole pixels.
+    pub scroll: ScrollStyle,
+}
+
+impl QuirkSet {
+    /// The original interpreter of the COSMAC VIP
+    pub const fn cosmac_vip() -> Self {
+        Self {
+            shift: ShiftStyle::CopyThenShift,
+            jump: JumpOffsetStyle::OffsetFromV0,
+            dump_load: DumpLoadStyle::IncrementI,
+            logic_resets_vf: true,
+            index_add_sets_flag: false,
+            wait_key: WaitKeyStyle::OnRelease,
+            mode_switch_clears: true,
+            lores_dxy0: LoresSpriteStyle::Nothing,
+            collision: CollisionStyle::Flag,
+            scroll: ScrollStyle::FullPixel,
+        }
+    }
+
+    /// CHIP-48 on the HP-48 calculators
+    pub const fn chip48() -> Self {
+        Self {
+            dump_load: DumpLoadStyle::IncrementIByX,
+            ..Self::super_chip_modern()
+        }
+    }
+
+    /// SUPER-CHIP 1.1 as it shipped for the HP-48 calculators
+    pub const fn super_chip_legacy() -> Self {
+        Self {
+            mode_switch_clears: false,
+            lores_dxy0: LoresSpriteStyle::Tall,
+            collision: CollisionStyle::RowsAndClipped,
+            scroll: ScrollStyle::HalfPixel,
+            ..Self::super_chip_modern()
+        }
+    }
+
+    /// SUPER-CHIP as most modern interpreters implement it
+    pub const fn super_chip_modern() -> Self {
+        Self {
+            shift: ShiftStyle::ShiftInPlace,
+            jump: JumpOffsetStyle::OffsetVariable,
+            dump_load: DumpLoadStyle::StaticIRegister,
+            logic_resets_vf: false,
+            index_add_sets_flag: false,
+            wait_key: WaitKeyStyle::OnPress,
+            mode_switch_clears: true,
+            lores_dxy0: LoresSpriteStyle::Large,
+            collision: CollisionStyle::Flag,
+            scroll: ScrollStyle::FullPixel,
+        }
+    }
+
+    /// XO-CHIP, which goes back to the COSMAC VIP behavior
+    /// for most of the ambiguous instructions.
+    ///
+    /// This deliberately differs from modern SUPER-CHIP shifts and a static I
+    /// for FX55/FX65. XO-CHIP roms are written and tested in Octo, which
+    /// shifts a copy of VY, increments I and jumps relative to V0.
+    pub const fn xo_chip() -> Self {
+        Self {
+            shift: ShiftStyle::CopyThenShift,
+            jump: JumpOffsetStyle::OffsetFromV0,
+            dump_load: DumpLoadStyle::IncrementI,
+            ..Self::super_chip_modern()
+        }
+    }
+
+    pub const fn with_shift(self, shift: ShiftStyle) -> Self {
+        Self { shift, ..self }
+    }
+
+    pub const fn with_jump(self, jump: JumpOffsetStyle) -> Self {
+        Self { jump, ..self }
+    }
+
+    pub const fn with_dump_load(self, dump_load: DumpLoadStyle) -> Self {
+        Self { dump_load, ..self }
+    }
+
+    pub const fn with_logic_resets_vf(self, logic_resets_vf: bool) -> Self {
+        Self {
+            logic_resets_vf,
+            ..self
+        }
+    }
+
+    pub const fn with_index_add_sets_flag(self, index_add_sets_flag: bool) -> Self {
+        Self {
+            index_add_sets_flag,
+            ..self
+        }
+    }
+
+    pub const fn with_wait_key(self, wait_key: WaitKeyStyle) -> Self {
+        Self { wait_key, ..self }
+    }
+
+    pub const fn with_lores_dxy0(self, lores_dxy0: LoresSpriteStyle) -> Self {
+        Self { lores_dxy0, ..self }
+    }
+
+    pub const fn with_collision(self, collision: CollisionStyle) -> Self {
+        Self { collision, ..self }
+    }
+
+    pub const fn with_scroll(self, scroll: ScrollStyle) -> Self {
+        Self { scroll, ..self }
+    }
+
+    pub const fn with_mode_switch_clears(self, mode_switch_clears: bool) -> Self {
+        Self {
+            mode_switch_clears,
+            ..self
+        }
+    }
+}
+
+/// The default are the quirks of [`QuirkSet::super_chip_modern`]
+impl Default for QuirkSet {
+    fn default() -> Self {
+        Self::super_chip_modern()
+    }
+}
 
 /// The behavior of the emulator can be configured towards the different
 /// sometimes conflicting specifications of chip-8 emulation.
 /// The default version leans more towards more modern emulation,
 /// so if you want to properly playback old roms, you might need
-/// to configure the emulator accordingly.
+/// to configure the emulator accordingly, e.g. starting from one of
+/// the presets like [`EmulatorConfiguration::cosmac_vip`].
+///
+/// Single choices can be changed with the struct update syntax:
+///
+/// ```
+/// use chip8::config::{EmulatorConfiguration, QuirkSet, ShiftStyle};
+///
+/// let configuration = EmulatorConfiguration {
+///     quirks: QuirkSet::default().with_shift(ShiftStyle::CopyThenShift),
+///     ..Default::default()
+/// };
+/// assert_ne!(EmulatorConfiguration::default(), configuration);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
 pub struct EmulatorConfiguration {
-    pub shift: ShiftStyle,
-    pub jump: JumpOffsetStyle,
-    pub r_register: DumpLoadStyle,
+    pub quirks: QuirkSet,
+    /// The address roms get loaded to and the program counter starts at
+    pub start_address: u16,
+    /// The address the font sprites are stored at and FX29 points into.
+    /// The large font follows at [`LARGE_FONT_OFFSET`] from here.
+    pub font_base: u16,
+    /// The number of nested subroutines, capped at [`MODERN_STACK_DEPTH`]
+    pub stack_depth: usize,
+    /// What happens when the program counter runs past the end of memory
+    pub pc_overflow: PcOverflowStyle,
+    /// What happens when an opcode can not be decoded
+    pub invalid_opcode: InvalidOpcodeStyle,
+    /// Whether FX0A reads the key state or consumes key events
+    pub key_input: KeyInputStyle,
+    /// Whether ticking also runs the delay and sound timers
+    pub timers: TimerStyle,
+    /// The number of instructions [`crate::emulator::Emulator::run_frame`]
+    /// executes per 60 Hz frame
+    pub instructions_per_frame: u32,
+    /// Which extensions of the instruction set are available.
+    /// Opcodes of other extensions are treated as invalid.
+    pub platform: Platform,
+    /// The bytes of memory, between [`CHIP8_MEMORY_SIZE`] and [`XO_CHIP_MEMORY_SIZE`].
+    /// Roms can fill it up from the start address.
+    pub memory_size: usize,
+    /// The pitch of the buzzer in Hz
+    pub buzzer_hz: f32,
+    /// The volume of the buzzer, from 0.0 to 1.0. Other values are clamped.
+    pub buzzer_volume: f32,
+}
+
+impl EmulatorConfiguration {
+    /// The original interpreter of the COSMAC VIP
+    pub fn cosmac_vip() -> Self {
+        Self {
+            quirks: QuirkSet::cosmac_vip(),
+            stack_depth: ORIGINAL_STACK_DEPTH,
+            ..Self::default()
+        }
+    }
+
+    /// CHIP-48 on the HP-48 calculators, which introduced
+    /// the in-place shifts and the variable jump offset
+    pub fn chip48() -> Self {
+        Self {
+            quirks: QuirkSet::chip48(),
+            ..Self::default()
+        }
+    }
+
+    /// SUPER-CHIP with all of its instructions and the quirks roms
+    /// written for it expect, the same as [`Self::super_chip_modern`].
+    /// Use [`Self::super_chip_legacy`] for roms that depend on the
+    /// exact behavior of SUPER-CHIP 1.1.
+    pub fn super_chip() -> Self {
+        Self::super_chip_modern()
+    }
+
+    /// SUPER-CHIP 1.1 as it shipped for the HP-48 calculators
+    pub fn super_chip_legacy() -> Self {
+        Self {
+            quirks: QuirkSet::super_chip_legacy(),
+            platform: Platform::SuperChip,
+            ..Self::default()
+        }
+    }
+
+    /// SUPER-CHIP as most modern interpreters implement it
+    pub fn super_chip_modern() -> Self {
+        Self {
+            quirks: QuirkSet::super_chip_modern(),
+            platform: Platform::SuperChip,
+            ..Self::default()
+        }
+    }
+
+    /// XO-CHIP, which goes back to the COSMAC VIP behavior
+    /// for most of the ambiguous instructions
+    pub fn xo_chip() -> Self {
+        Self {
+            quirks: QuirkSet::xo_chip(),
+            platform: Platform::XoChip,
+            memory_size: XO_CHIP_MEMORY_SIZE,
+            ..Self::default()
+        }
+    }
+
+    /// The recommended configuration for a well-known rom,
+    /// or `None` if the rom is unknown and the default should be used
+    #[cfg(feature = "rom-db")]
+    pub fn for_rom(rom: &[u8]) -> Option<Self> {
+        crate::rom_db::lookup(rom)
+    }
+
+    /// Check the choices are consistent with each other,
+    /// e.g. that the font does not end up inside the rom
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let start_address = self.start_address as usize;
+        let font_base = self.font_base as usize;
+        if !self.platform.is_enabled() {
+            return Err(ConfigError::PlatformDisabled);
+        }
+        if !(CHIP8_MEMORY_SIZE..=MAX_MEMORY_SIZE).contains(&self.memory_size) {
+            return Err(ConfigError::InvalidMemorySize);
+        }
+        if start_address >= self.memory_size {
+            return Err(ConfigError::InvalidStartAddress);
+        }
+        let font_end = font_base + self.font_len();
+        if font_end > self.memory_size {
+            return Err(ConfigError::InvalidFontBase);
+        }
+        // The program runs to the end of memory, so the font has to come first
+        if font_end > start_address {
+            return Err(ConfigError::FontOverlapsProgram);
+        }
+        if self.stack_depth == 0 {
+            return Err(ConfigError::InvalidStackDepth);
+        }
+        if self.instructions_per_frame == 0 || matches!(self.timers, TimerStyle::PerInstructions(0))
+        {
+            return Err(ConfigError::NoInstructions);
+        }
+        if !(self.buzzer_hz.is_finite() && self.buzzer_hz > 0.0) {
+            return Err(ConfigError::InvalidBuzzer);
+        }
+        Ok(())
+    }
+
+    /// The bytes the fonts take up, including the large font if there is one
+    pub(crate) fn font_len(&self) -> usize {
+        if self.platform.has_super_chip() {
+            LARGE_FONT_OFFSET as usize + LARGE_FONT_LEN
+        } else {
+            LARGE_FONT_OFFSET as usize
+        }
+    }
+
+    /// Read a configuration from the top level keys of a
+    /// [`RomLibraryConfig`] document, which may not contain any tables
+    #[cfg(feature = "std")]
+    pub fn from_toml_str(document: &str) -> Result<Self, ConfigError> {
+        library::parse_single(document)
+    }
 }
 
+/// The names [`EmulatorConfiguration`] parses from, one for each preset
+pub const PRESET_NAMES: [&str; 5] = ["cosmac-vip", "chip-48", "schip-legacy", "schip", "xo-chip"];
+
+/// Parse the name of a preset, e.g. from a command line flag
+impl core::str::FromStr for EmulatorConfiguration {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cosmac-vip" => Ok(Self::cosmac_vip()),
+            "chip-48" => Ok(Self::chip48()),
+            "schip-legacy" => Ok(Self::super_chip_legacy()),
+            "schip" => Ok(Self::super_chip_modern()),
+            "xo-chip" => Ok(Self::xo_chip()),
+            _ => Err(ConfigError::UnknownPreset),
+        }
+    }
+}
+
+/// The default is [`EmulatorConfiguration::super_chip_modern`]
+/// limited to the chip-8 instruction set:
+/// - 8XY6 and 8XYE shift VX in place
+/// - BXNN jumps offset by VX
+/// - FX55 and FX65 leave I unchanged
+/// - VF is only changed by arithmetic, never reset by the logic instructions
+///   or set by FX1E
+/// - roms load at 0x200, the font lives at 0x050 and 16 subroutines can be nested
+/// - running past the end of memory halts, invalid opcodes are skipped
+/// - FX0A completes on a key press, reading the key state
+/// - the timers run on the emulator's clock, 10 instructions make a frame
+/// - the buzzer plays 440 Hz at full volume
 impl Default for EmulatorConfiguration {
     fn default() -> Self {
         Self {
-            shift: ShiftStyle::ShiftInPlace,
-            jump: JumpOffsetStyle::OffsetVariable,
-            r_register: DumpLoadStyle::StaticIRegister,
+            quirks: QuirkSet::super_chip_modern(),
+            start_address: CHIP8_START_ADDRESS,
+            font_base: DEFAULT_FONT_BASE,
+            stack_depth: MODERN_STACK_DEPTH,
+            pc_overflow: PcOverflowStyle::Fault,
+            invalid_opcode: InvalidOpcodeStyle::Skip,
+            key_input: KeyInputStyle::Level,
+            timers: TimerStyle::WallClock,
+            instructions_per_frame: 10,
+            platform: Platform::Chip8,
+            memory_size: CHIP8_MEMORY_SIZE,
+            buzzer_hz: DEFAULT_BUZZER_HZ,
+            buzzer_volume: 1.0,
         }
     }
 }
+
+impl core::fmt::Display for ShiftStyle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ShiftStyle::ShiftInPlace => write!(f, "shift vx in place"),
+            ShiftStyle::CopyThenShift => write!(f, "copy vy into vx, then shift"),
+        }
+    }
+}
+
+impl core::fmt::Display for JumpOffsetStyle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            JumpOffsetStyle::OffsetFromV0 => write!(f, "offset by v0"),
+            JumpOffsetStyle::OffsetVariable => write!(f, "offset by vx"),
+        }
+    }
+}
+
+impl core::fmt::Display for DumpLoadStyle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DumpLoadStyle::IncrementI => write!(f, "increment i past the last register"),
+            DumpLoadStyle::IncrementIByX => write!(f, "increment i to the last register"),
+            DumpLoadStyle::StaticIRegister => write!(f, "leave i unchanged"),
+        }
+    }
+}
+
+impl core::fmt::Display for PcOverflowStyle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PcOverflowStyle::Wrap => write!(f, "wrap to the start address"),
+            PcOverflowStyle::Fault => write!(f, "halt"),
+        }
+    }
+}
+
+impl core::fmt::Display for InvalidOpcodeStyle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InvalidOpcodeStyle::Skip => write!(f, "skip"),
+            InvalidOpcodeStyle::Halt => write!(f, "halt"),
+        }
+    }
+}
+
+impl core::fmt::Display for WaitKeyStyle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            WaitKeyStyle::OnPress => write!(f, "on press"),
+            WaitKeyStyle::OnRelease => write!(f, "on release"),
+        }
+    }
+}
+
+impl core::fmt::Display for KeyInputStyle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            KeyInputStyle::Level => write!(f, "key state"),
+            KeyInputStyle::Events => write!(f, "key events"),
+        }
+    }
+}
+
+impl core::fmt::Display for TimerStyle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TimerStyle::WallClock => write!(f, "wall clock"),
+            TimerStyle::HostDriven => write!(f, "host driven"),
+            TimerStyle::PerInstructions(instructions) => {
+                write!(f, "every {} instructions", instructions)
+            }
+        }
+    }
+}
+
+impl core::fmt::Display for LoresSpriteStyle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LoresSpriteStyle::Nothing => write!(f, "nothing"),
+            LoresSpriteStyle::Tall => write!(f, "8x16"),
+            LoresSpriteStyle::Large => write!(f, "16x16"),
+        }
+    }
+}
+
+impl core::fmt::Display for CollisionStyle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CollisionStyle::Flag => write!(f, "flag"),
+            CollisionStyle::Rows => write!(f, "count rows"),
+            CollisionStyle::RowsAndClipped => write!(f, "count rows and clipped rows"),
+        }
+    }
+}
+
+impl core::fmt::Display for ScrollStyle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ScrollStyle::HalfPixel => write!(f, "half pixels"),
+            ScrollStyle::FullPixel => write!(f, "full pixels"),
+        }
+    }
+}
+
+impl core::fmt::Display for Platform {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Platform::Chip8 => write!(f, "chip-8"),
+            Platform::SuperChip => write!(f, "super-chip"),
+            Platform::XoChip => write!(f, "xo-chip"),
+        }
+    }
+}
+
+/// Lists every choice on a line of its own
+impl core::fmt::Display for EmulatorConfiguration {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let on_off = |value: bool| if value { "on" } else { "off" };
+        writeln!(f, "platform: {}", self.platform)?;
+        writeln!(f, "memory: {} bytes", self.memory_size)?;
+        writeln!(f, "shift (8XY6, 8XYE): {}", self.quirks.shift)?;
+        writeln!(f, "jump with offset (BNNN): {}", self.quirks.jump)?;
+        writeln!(f, "dump and load (FX55, FX65): {}", self.quirks.dump_load)?;
+        writeln!(
+            f,
+            "logic resets vf (8XY1-8XY3): {}",
+            on_off(self.quirks.logic_resets_vf)
+        )?;
+        writeln!(
+            f,
+            "index overflow sets vf (FX1E): {}",
+            on_off(self.quirks.index_add_sets_flag)
+        )?;
+        writeln!(f, "wait for key (FX0A): {}", self.quirks.wait_key)?;
+        writeln!(
+            f,
+            "mode switch clears (00FE, 00FF): {}",
+            on_off(self.quirks.mode_switch_clears)
+        )?;
+        writeln!(f, "lores DXY0 draws: {}", self.quirks.lores_dxy0)?;
+        writeln!(f, "hires collisions (DXYN): {}", self.quirks.collision)?;
+        writeln!(f, "lores scrolls by: {}", self.quirks.scroll)?;
+        writeln!(f, "key input: {}", self.key_input)?;
+        writeln!(f, "start address: {:#05X}", self.start_address)?;
+        writeln!(f, "font base: {:#05X}", self.font_base)?;
+        writeln!(f, "stack depth: {}", self.stack_depth)?;
+        writeln!(f, "pc overflow: {}", self.pc_overflow)?;
+        writeln!(f, "invalid opcodes: {}", self.invalid_opcode)?;
+        writeln!(f, "timers: {}", self.timers)?;
+        writeln!(f, "instructions per frame: {}", self.instructions_per_frame)?;
+        writeln!(
+            f,
+            "buzzer: {} Hz at volume {}",
+            self.buzzer_hz, self.buzzer_volume
+        )
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quirk_builder_changes_single_quirks() {
+        let quirks = QuirkSet::cosmac_vip()
+            .with_shift(ShiftStyle::ShiftInPlace)
+            .with_index_add_sets_flag(true);
+        assert_eq!(ShiftStyle::ShiftInPlace, quirks.shift);
+        assert!(quirks.index_add_sets_flag);
+        assert_eq!(
+            QuirkSet::cosmac_vip(),
+            quirks
+                .with_shift(ShiftStyle::CopyThenShift)
+                .with_index_add_sets_flag(false)
+        );
+        assert_eq!(QuirkSet::default(), EmulatorConfiguration::default().quirks);
+    }
+
+    #[test]
+    fn presets_differ_in_signature_quirks() {
+        assert!(matches!(
+            EmulatorConfiguration::cosmac_vip().quirks.shift,
+            ShiftStyle::CopyThenShift
+        ));
+        assert!(matches!(
+            EmulatorConfiguration::chip48().quirks.shift,
+            ShiftStyle::ShiftInPlace
+        ));
+        assert!(EmulatorConfiguration::cosmac_vip().quirks.logic_resets_vf);
+        assert!(!EmulatorConfiguration::chip48().quirks.logic_resets_vf);
+        assert_eq!(
+            ORIGINAL_STACK_DEPTH,
+            EmulatorConfiguration::cosmac_vip().stack_depth
+        );
+        assert!(matches!(
+            EmulatorConfiguration::xo_chip().platform,
+            Platform::XoChip
+        ));
+    }
+
+    #[test]
+    fn parses_preset_names() {
+        for name in PRESET_NAMES {
+            assert!(name.parse::<EmulatorConfiguration>().is_ok(), "{}", name);
+        }
+        assert_eq!(
+            Ok(EmulatorConfiguration::cosmac_vip()),
+            "cosmac-vip".parse()
+        );
+        assert_eq!(
+            Ok(EmulatorConfiguration::super_chip_modern()),
+            "schip".parse()
+        );
+        assert_eq!(Ok(EmulatorConfiguration::xo_chip()), "xo-chip".parse());
+        assert_eq!(
+            Err(ConfigError::UnknownPreset),
+            "vip".parse::<EmulatorConfiguration>()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn presets_round_trip_through_json() {
+        for name in PRESET_NAMES {
+            let configuration: EmulatorConfiguration = name.parse().unwrap();
+            let json = serde_json::to_string(&configuration).unwrap();
+            assert_eq!(
+                configuration,
+                serde_json::from_str(&json).unwrap(),
+                "{}",
+                name
+            );
+        }
+        let timers = TimerStyle::PerInstructions(10);
+        let json = serde_json::to_string(&timers).unwrap();
+        assert_eq!(timers, serde_json::from_str(&json).unwrap());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_with_unknown_fields_is_rejected() {
+        let json = serde_json::to_string(&EmulatorConfiguration::default()).unwrap();
+        let unknown = json.replacen('{', "{\"turbo\":true,", 1);
+        let err = serde_json::from_str::<EmulatorConfiguration>(&unknown).unwrap_err();
+        assert!(err.to_string().contains("unknown field `turbo`"), "{}", err);
+
+        let quirks = r#"{"shift":"ShiftInPlace","turbo":true}"#;
+        assert!(serde_json::from_str::<QuirkSet>(quirks).is_err());
+    }
+
+    #[test]
+    fn configurations_can_be_cloned_and_compared() {
+        let default = EmulatorConfiguration::default();
+        let mut tweaked = default;
+        assert_eq!(default, tweaked);
+        tweaked.quirks.wait_key = WaitKeyStyle::OnRelease;
+        assert_ne!(default, tweaked);
+        assert_eq!(WaitKeyStyle::OnPress, default.quirks.wait_key);
+        assert_eq!(
+            "PerInstructions(10)",
+            format!("{:?}", TimerStyle::PerInstructions(10))
+        );
+
+        let mut quirks = std::collections::HashSet::new();
+        quirks.insert(EmulatorConfiguration::cosmac_vip().quirks.shift);
+        quirks.insert(EmulatorConfiguration::chip48().quirks.shift);
+        quirks.insert(EmulatorConfiguration::xo_chip().quirks.shift);
+        assert_eq!(2, quirks.len());
+    }
+
+    #[test]
+    fn presets_are_valid() {
+        for name in PRESET_NAMES {
+            let configuration: EmulatorConfiguration = name.parse().unwrap();
+            let expected = match configuration.platform.is_enabled() {
+                true => Ok(()),
+                false => Err(ConfigError::PlatformDisabled),
+            };
+            assert_eq!(expected, configuration.validate(), "{}", name);
+        }
+        assert_eq!(Ok(()), EmulatorConfiguration::default().validate());
+    }
+
+    #[test]
+    fn rejects_invalid_combinations() {
+        let font_in_program = EmulatorConfiguration {
+            font_base: 0x1E0,
+            ..Default::default()
+        };
+        assert_eq!(
+            Err(ConfigError::FontOverlapsProgram),
+            font_in_program.validate()
+        );
+        let eti660 = EmulatorConfiguration {
+            start_address: ETI660_START_ADDRESS,
+            ..font_in_program
+        };
+        assert_eq!(Ok(()), eti660.validate());
+
+        // Only SUPER-CHIP needs room for the large font
+        #[cfg(feature = "schip")]
+        {
+            let large_font_in_program = EmulatorConfiguration {
+                font_base: 0x150,
+                ..EmulatorConfiguration::super_chip_modern()
+            };
+            assert_eq!(
+                Err(ConfigError::FontOverlapsProgram),
+                large_font_in_program.validate()
+            );
+            let small_font_only = EmulatorConfiguration {
+                platform: Platform::Chip8,
+                ..large_font_in_program
+            };
+            assert_eq!(Ok(()), small_font_only.validate());
+        }
+
+        let font_past_memory = EmulatorConfiguration {
+            font_base: 0xFC0,
+            ..Default::default()
+        };
+        assert_eq!(
+            Err(ConfigError::InvalidFontBase),
+            font_past_memory.validate()
+        );
+
+        for memory_size in [0x800, 0x10001] {
+            let odd_memory = EmulatorConfiguration {
+                memory_size,
+                ..Default::default()
+            };
+            assert_eq!(Err(ConfigError::InvalidMemorySize), odd_memory.validate());
+        }
+        // Only XO-CHIP brings more than 4 KiB
+        let large_memory = EmulatorConfiguration {
+            memory_size: 0x2000,
+            ..Default::default()
+        };
+        assert_eq!(cfg!(feature = "xochip"), large_memory.validate().is_ok());
+
+        let never_steps = EmulatorConfiguration {
+            timers: TimerStyle::PerInstructions(0),
+            ..Default::default()
+        };
+        assert_eq!(Err(ConfigError::NoInstructions), never_steps.validate());
+
+        let no_stack = EmulatorConfiguration {
+            stack_depth: 0,
+            ..Default::default()
+        };
+        assert_eq!(Err(ConfigError::InvalidStackDepth), no_stack.validate());
+    }
+
+    #[test]
+    fn summarizes_default_configuration() {
+        assert_eq!(
+            "\
+platform: chip-8
+memory: 4096 bytes
+shift (8XY6, 8XYE): shift vx in place
+jump with offset (BNNN): offset by vx
+dump and load (FX55, FX65): leave i unchanged
+logic resets vf (8XY1-8XY3): off
+index overflow sets vf (FX1E): off
+wait for key (FX0A): on press
+mode switch clears (00FE, 00FF): on
+lores DXY0 draws: 16x16
+hires collisions (DXYN): flag
+lores scrolls by: full pixels
+key input: key state
+start address: 0x200
+font base: 0x050
+stack depth: 16
+pc overflow: halt
+invalid opcodes: skip
+timers: wall clock
+instructions per frame: 10
+buzzer: 440 Hz at volume 1
+",
+            EmulatorConfiguration::default().to_string()
+        );
+    }
+
+    #[test]
+    fn lists_active_choices() {
+        let description = EmulatorConfiguration::cosmac_vip().to_string();
+        assert!(description.contains("platform: chip-8\n"));
+        assert!(description.contains("shift (8XY6, 8XYE): copy vy into vx, then shift\n"));
+        assert!(description.contains("logic resets vf (8XY1-8XY3): on\n"));
+        assert!(description.contains("stack depth: 12\n"));
+    }
+}
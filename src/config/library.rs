@@ -0,0 +1,467 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use super::{
+    CollisionStyle, DumpLoadStyle, EmulatorConfiguration, JumpOffsetStyle, LoresSpriteStyle,
+    ScrollStyle, ShiftStyle, WaitKeyStyle,
+};
+use crate::error::ConfigError;
+
+/// The configurations of a library of roms, read from a sidecar file.
+/// The file uses the small subset of TOML the schema needs:
+///
+/// ```toml
+/// # The top level applies to every rom
+/// preset = "schip"
+/// instructions_per_frame = 15
+///
+/// # Roms are picked by file name ...
+/// [rom."pong.ch8"]
+/// preset = "cosmac-vip"
+/// shift = "in-place"
+///
+/// # ... or by the CRC32 of their contents
+/// [crc32.a5422aa5]
+/// logic_resets_vf = true
+/// ```
+///
+/// Tables start from the top level configuration, unless they name a preset.
+/// Within a table the preset applies first, then the single choices on top:
+/// - `preset`: one of [`super::PRESET_NAMES`]
+/// - `instructions_per_frame`: a number
+/// - `shift`: `"in-place"` or `"copy-then-shift"`
+/// - `jump`: `"v0"` or `"vx"`
+/// - `dump_load`: `"increment-i"`, `"increment-i-by-x"` or `"static"`
+/// - `logic_resets_vf`, `index_add_sets_flag`, `mode_switch_clears`: `true` or `false`
+/// - `wait_key`: `"press"` or `"release"`
+/// - `lores_dxy0`: `"nothing"`, `"8x16"` or `"16x16"`
+/// - `collision`: `"flag"`, `"rows"` or `"rows-and-clipped"`
+/// - `scroll`: `"half-pixel"` or `"full-pixel"`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RomLibraryConfig {
+    /// The configuration for roms without a table of their own
+    pub default: EmulatorConfiguration,
+    by_name: HashMap<String, EmulatorConfiguration>,
+    by_crc32: HashMap<u32, EmulatorConfiguration>,
+}
+
+impl RomLibraryConfig {
+    /// Read the library configuration from a file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let document =
+            std::fs::read_to_string(path).map_err(|error| ConfigError::Io(error.kind()))?;
+        document.parse()
+    }
+
+    /// The configuration of the rom with the given file name and contents.
+    /// A table for the file name wins over one for the hash.
+    pub fn for_rom(&self, file_name: &str, rom: &[u8]) -> EmulatorConfiguration {
+        self.by_name
+            .get(file_name)
+            .or_else(|| self.by_crc32.get(&crate::rom_db::crc32(rom)))
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// The configuration of the table for the given file name, if any
+    pub fn by_name(&self, file_name: &str) -> Option<&EmulatorConfiguration> {
+        self.by_name.get(file_name)
+    }
+
+    /// The configuration of the table for the given CRC32, if any
+    pub fn by_crc32(&self, crc32: u32) -> Option<&EmulatorConfiguration> {
+        self.by_crc32.get(&crc32)
+    }
+}
+
+impl core::str::FromStr for RomLibraryConfig {
+    type Err = ConfigError;
+
+    fn from_str(document: &str) -> Result<Self, Self::Err> {
+        let document = parse(document)?;
+        let default = document.top_level.apply(EmulatorConfiguration::default());
+        default.validate()?;
+        let mut library = Self {
+            default,
+            by_name: HashMap::new(),
+            by_crc32: HashMap::new(),
+        };
+        for (table, overrides, line) in document.tables {
+            let configuration = overrides.apply(default);
+            configuration.validate()?;
+            let is_new = match table {
+                Table::Name(name) => library.by_name.insert(name, configuration).is_none(),
+                Table::Crc32(crc32) => library.by_crc32.insert(crc32, configuration).is_none(),
+            };
+            if !is_new {
+                return Err(ConfigError::Syntax { line });
+            }
+        }
+        Ok(library)
+    }
+}
+
+/// Parse a document that only consists of top level keys
+pub(super) fn parse_single(document: &str) -> Result<EmulatorConfiguration, ConfigError> {
+    let document = parse(document)?;
+    match document.tables.first() {
+        Some((_, _, line)) => Err(ConfigError::UnsupportedTable { line: *line }),
+        None => {
+            let configuration = document.top_level.apply(EmulatorConfiguration::default());
+            configuration.validate()?;
+            Ok(configuration)
+        }
+    }
+}
+
+/// Which roms a table applies to
+enum Table {
+    Name(String),
+    Crc32(u32),
+}
+
+struct Document {
+    top_level: Overrides,
+    /// The tables with the line of their header
+    tables: Vec<(Table, Overrides, usize)>,
+}
+
+/// The choices a table makes, on top of its preset
+#[derive(Default)]
+struct Overrides {
+    preset: Option<EmulatorConfiguration>,
+    instructions_per_frame: Option<u32>,
+    shift: Option<ShiftStyle>,
+    jump: Option<JumpOffsetStyle>,
+    dump_load: Option<DumpLoadStyle>,
+    logic_resets_vf: Option<bool>,
+    index_add_sets_flag: Option<bool>,
+    wait_key: Option<WaitKeyStyle>,
+    mode_switch_clears: Option<bool>,
+    lores_dxy0: Option<LoresSpriteStyle>,
+    collision: Option<CollisionStyle>,
+    scroll: Option<ScrollStyle>,
+    /// The keys set so far, as each may only be set once
+    keys: HashSet<String>,
+}
+
+impl Overrides {
+    fn apply(&self, base: EmulatorConfiguration) -> EmulatorConfiguration {
+        let mut configuration = self.preset.unwrap_or(base);
+        let quirks = &mut configuration.quirks;
+        if let Some(shift) = self.shift {
+            quirks.shift = shift;
+        }
+        if let Some(jump) = self.jump {
+            quirks.jump = jump;
+        }
+        if let Some(dump_load) = self.dump_load {
+            quirks.dump_load = dump_load;
+        }
+        if let Some(logic_resets_vf) = self.logic_resets_vf {
+            quirks.logic_resets_vf = logic_resets_vf;
+        }
+        if let Some(index_add_sets_flag) = self.index_add_sets_flag {
+            quirks.index_add_sets_flag = index_add_sets_flag;
+        }
+        if let Some(wait_key) = self.wait_key {
+            quirks.wait_key = wait_key;
+        }
+        if let Some(mode_switch_clears) = self.mode_switch_clears {
+            quirks.mode_switch_clears = mode_switch_clears;
+        }
+        if let Some(lores_dxy0) = self.lores_dxy0 {
+            quirks.lores_dxy0 = lores_dxy0;
+        }
+        if let Some(collision) = self.collision {
+            quirks.collision = collision;
+        }
+        if let Some(scroll) = self.scroll {
+            quirks.scroll = scroll;
+        }
+        if let Some(instructions) = self.instructions_per_frame {
+            configuration.instructions_per_frame = instructions;
+        }
+        configuration
+    }
+
+    fn set(&mut self, key: &str, value: &str, line: usize) -> Result<(), ConfigError> {
+        let invalid = ConfigError::InvalidValue { line };
+        if !self.keys.insert(key.to_string()) {
+            return Err(ConfigError::DuplicateKey { line });
+        }
+        match key {
+            "preset" => {
+                let preset = string(value).and_then(|name| name.parse().ok());
+                self.preset = Some(preset.ok_or(invalid)?);
+            }
+            "instructions_per_frame" => {
+                self.instructions_per_frame = Some(value.parse().map_err(|_| invalid)?);
+            }
+            "shift" => {
+                self.shift = Some(match string(value) {
+                    Some("in-place") => ShiftStyle::ShiftInPlace,
+                    Some("copy-then-shift") => ShiftStyle::CopyThenShift,
+                    _ => return Err(invalid),
+                })
+            }
+            "jump" => {
+                self.jump = Some(match string(value) {
+                    Some("v0") => JumpOffsetStyle::OffsetFromV0,
+                    Some("vx") => JumpOffsetStyle::OffsetVariable,
+                    _ => return Err(invalid),
+                })
+            }
+            "dump_load" => {
+                self.dump_load = Some(match string(value) {
+                    Some("increment-i") => DumpLoadStyle::IncrementI,
+                    Some("increment-i-by-x") => DumpLoadStyle::IncrementIByX,
+                    Some("static") => DumpLoadStyle::StaticIRegister,
+                    _ => return Err(invalid),
+                })
+            }
+            "logic_resets_vf" => self.logic_resets_vf = Some(boolean(value).ok_or(invalid)?),
+            "index_add_sets_flag" => {
+                self.index_add_sets_flag = Some(boolean(value).ok_or(invalid)?)
+            }
+            "wait_key" => {
+                self.wait_key = Some(match string(value) {
+                    Some("press") => WaitKeyStyle::OnPress,
+                    Some("release") => WaitKeyStyle::OnRelease,
+                    _ => return Err(invalid),
+                })
+            }
+            "mode_switch_clears" => self.mode_switch_clears = Some(boolean(value).ok_or(invalid)?),
+            "lores_dxy0" => {
+                self.lores_dxy0 = Some(match string(value) {
+                    Some("nothing") => LoresSpriteStyle::Nothing,
+                    Some("8x16") => LoresSpriteStyle::Tall,
+                    Some("16x16") => LoresSpriteStyle::Large,
+                    _ => return Err(invalid),
+                })
+            }
+            "collision" => {
+                self.collision = Some(match string(value) {
+                    Some("flag") => CollisionStyle::Flag,
+                    Some("rows") => CollisionStyle::Rows,
+                    Some("rows-and-clipped") => CollisionStyle::RowsAndClipped,
+                    _ => return Err(invalid),
+                })
+            }
+            "scroll" => {
+                self.scroll = Some(match string(value) {
+                    Some("half-pixel") => ScrollStyle::HalfPixel,
+                    Some("full-pixel") => ScrollStyle::FullPixel,
+                    _ => return Err(invalid),
+                })
+            }
+            _ => return Err(ConfigError::UnknownKey { line }),
+        }
+        Ok(())
+    }
+}
+
+fn parse(document: &str) -> Result<Document, ConfigError> {
+    let mut top_level = Overrides::default();
+    let mut tables: Vec<(Table, Overrides, usize)> = Vec::new();
+
+    for (index, text) in document.lines().enumerate() {
+        let line = index + 1;
+        let text = strip_comment(text).trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = text.strip_prefix('[') {
+            let table = header
+                .strip_suffix(']')
+                .and_then(table)
+                .ok_or(ConfigError::Syntax { line })?;
+            tables.push((table, Overrides::default(), line));
+            continue;
+        }
+
+        let (key, value) = text.split_once('=').ok_or(ConfigError::Syntax { line })?;
+        let overrides = match tables.last_mut() {
+            Some((_, overrides, _)) => overrides,
+            None => &mut top_level,
+        };
+        overrides.set(key.trim(), value.trim(), line)?;
+    }
+
+    Ok(Document { top_level, tables })
+}
+
+/// The text up to a `#` outside of a string
+fn strip_comment(text: &str) -> &str {
+    let mut in_string = false;
+    for (index, c) in text.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &text[..index],
+            _ => (),
+        }
+    }
+    text
+}
+
+/// The table named by a header, without its brackets
+fn table(header: &str) -> Option<Table> {
+    let (kind, key) = header.trim().split_once('.')?;
+    let key = key.trim();
+    let key = string(key).unwrap_or(key);
+    match kind.trim() {
+        "rom" => Some(Table::Name(key.to_string())),
+        "crc32" => u32::from_str_radix(key, 16).ok().map(Table::Crc32),
+        _ => None,
+    }
+}
+
+fn string(value: &str) -> Option<&str> {
+    value.strip_prefix('"')?.strip_suffix('"')
+}
+
+fn boolean(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "schip")]
+    use crate::config::Platform;
+    use std::str::FromStr;
+
+    #[cfg(feature = "schip")]
+    const LIBRARY: &str = r#"
+# Played a bit faster than usual
+preset = "schip"
+instructions_per_frame = 15
+
+[rom."pong.ch8"]
+preset = "cosmac-vip"
+shift = "in-place" # the port from the hp-48
+
+[crc32.a5422aa5]
+logic_resets_vf = true
+wait_key = "release"
+collision = "rows"
+"#;
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn tables_apply_on_top_of_their_base() {
+        let library: RomLibraryConfig = LIBRARY.parse().unwrap();
+        assert_eq!(15, library.default.instructions_per_frame);
+        assert_eq!(Platform::SuperChip, library.default.platform);
+
+        let pong = library.for_rom("pong.ch8", &[]);
+        assert_eq!(ShiftStyle::ShiftInPlace, pong.quirks.shift);
+        assert_eq!(JumpOffsetStyle::OffsetFromV0, pong.quirks.jump);
+        assert!(pong.quirks.logic_resets_vf);
+        // The preset replaces the top level configuration
+        assert_eq!(10, pong.instructions_per_frame);
+
+        let bc_test = library.for_rom("bc.ch8", include_bytes!("../../roms/BC_test.ch8"));
+        assert_eq!(Some(&bc_test), library.by_crc32(0xa542_2aa5));
+        assert!(bc_test.quirks.logic_resets_vf);
+        assert_eq!(WaitKeyStyle::OnRelease, bc_test.quirks.wait_key);
+        assert_eq!(CollisionStyle::Rows, bc_test.quirks.collision);
+        assert_eq!(DumpLoadStyle::StaticIRegister, bc_test.quirks.dump_load);
+        assert_eq!(15, bc_test.instructions_per_frame);
+
+        assert_eq!(library.default, library.for_rom("other.ch8", &[0x00, 0xE0]));
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        assert_eq!(
+            Err(ConfigError::UnknownKey { line: 3 }),
+            RomLibraryConfig::from_str("preset = \"schip\"\n\nspeed = 3")
+        );
+        assert_eq!(
+            Err(ConfigError::InvalidValue { line: 2 }),
+            RomLibraryConfig::from_str("[rom.\"a.ch8\"]\nshift = \"sideways\"")
+        );
+        assert_eq!(
+            Err(ConfigError::InvalidValue { line: 1 }),
+            RomLibraryConfig::from_str("preset = \"gameboy\"")
+        );
+        assert_eq!(
+            Err(ConfigError::Syntax { line: 2 }),
+            RomLibraryConfig::from_str("jump = \"v0\"\n[rom.\"a.ch8\"")
+        );
+        assert_eq!(
+            Err(ConfigError::NoInstructions),
+            RomLibraryConfig::from_str("[rom.\"a.ch8\"]\ninstructions_per_frame = 0")
+        );
+        assert_eq!(
+            Err(ConfigError::Syntax { line: 3 }),
+            RomLibraryConfig::from_str("[crc32.1]\n\n[crc32.\"00000001\"]")
+        );
+    }
+
+    #[test]
+    fn keys_can_only_be_set_once_per_table() {
+        assert_eq!(
+            Err(ConfigError::DuplicateKey { line: 3 }),
+            RomLibraryConfig::from_str("jump = \"v0\"\nshift = \"in-place\"\njump = \"vx\"")
+        );
+        assert_eq!(
+            Err(ConfigError::DuplicateKey { line: 4 }),
+            RomLibraryConfig::from_str(
+                "[rom.\"a.ch8\"]\nlogic_resets_vf = true\n\nlogic_resets_vf = false"
+            )
+        );
+        // Every table starts with a clean slate
+        let library =
+            RomLibraryConfig::from_str("jump = \"v0\"\n[rom.\"a.ch8\"]\njump = \"vx\"").unwrap();
+        assert_eq!(
+            JumpOffsetStyle::OffsetVariable,
+            library.for_rom("a.ch8", &[]).quirks.jump
+        );
+    }
+
+    #[test]
+    fn single_configuration_has_no_tables() {
+        let configuration =
+            EmulatorConfiguration::from_toml_str("preset = \"chip-48\"\nwait_key = \"release\"")
+                .unwrap();
+        assert_eq!(
+            EmulatorConfiguration {
+                quirks: crate::config::QuirkSet::chip48().with_wait_key(WaitKeyStyle::OnRelease),
+                ..EmulatorConfiguration::chip48()
+            },
+            configuration
+        );
+        assert_eq!(
+            Err(ConfigError::UnsupportedTable { line: 2 }),
+            EmulatorConfiguration::from_toml_str("preset = \"schip\"\n[rom.\"a.ch8\"]")
+        );
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn can_load_from_file() {
+        let path = std::env::temp_dir().join(format!(
+            "chip8_{}_can_load_from_file.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, LIBRARY).unwrap();
+        let library = RomLibraryConfig::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            Some(&library.for_rom("pong.ch8", &[])),
+            library.by_name("pong.ch8")
+        );
+
+        assert!(matches!(
+            RomLibraryConfig::load(path),
+            Err(ConfigError::Io(std::io::ErrorKind::NotFound))
+        ));
+    }
+}
@@ -1,8 +1,103 @@
-use crate::memory::CHIP8_START;
+use crate::error::InvalidRegister;
+
+/// The 16 general purpose registers of the chip-8.
+/// VF doubles as the flag register for carry, borrow and collisions.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    V0,
+    V1,
+    V2,
+    V3,
+    V4,
+    V5,
+    V6,
+    V7,
+    V8,
+    V9,
+    VA,
+    VB,
+    VC,
+    VD,
+    VE,
+    VF,
+}
+
+impl Register {
+    pub const ALL: [Register; 16] = [
+        Register::V0,
+        Register::V1,
+        Register::V2,
+        Register::V3,
+        Register::V4,
+        Register::V5,
+        Register::V6,
+        Register::V7,
+        Register::V8,
+        Register::V9,
+        Register::VA,
+        Register::VB,
+        Register::VC,
+        Register::VD,
+        Register::VE,
+        Register::VF,
+    ];
+
+    /// The index of the register, as used in opcodes
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+}
+
+impl TryFrom<u8> for Register {
+    type Error = InvalidRegister;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Self::ALL
+            .get(value as usize)
+            .copied()
+            .ok_or(InvalidRegister(value))
+    }
+}
+
+/// A copy of the complete cpu state at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CpuSnapshot {
+    /// The general purpose registers V0..=VF
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    /// The stack pointer, which equals the number of entered subroutines
+    pub sp: u8,
+    pub delay: u8,
+    pub sound: u8,
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for CpuSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "PC={:04X} I={:04X} SP={:X} DT={:02X} ST={:02X}",
+            self.pc, self.i, self.sp, self.delay, self.sound
+        )?;
+        for (row, values) in self.v.chunks(8).enumerate() {
+            for (column, value) in values.iter().enumerate() {
+                if column > 0 {
+                    write!(f, " ")?;
+                }
+                write!(f, "V{:X}={:02X}", row * 8 + column, value)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
 
 /// The [`CPU`] Hosts all the registers and gates
 /// access to them.
-#[derive(Default)]
+#[derive(Default, Clone, Copy)]
 pub(crate) struct Cpu {
     /// The program counter pointer to the currently
     /// executed instruction in memory
@@ -14,9 +109,11 @@ pub(crate) struct Cpu {
 }
 
 impl Cpu {
-    pub fn new() -> Self {
+    /// Create a new [`Cpu`] with the program counter pointing
+    /// at the given program start address
+    pub fn new(start_address: u16) -> Self {
         Self {
-            pc: CHIP8_START as u16,
+            pc: start_address,
             ..Default::default()
         }
     }
@@ -30,18 +127,22 @@ impl Cpu {
     }
 
     pub(crate) fn advance_pc(&mut self) {
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
     pub(crate) fn rollback_pc(&mut self) {
-        self.pc -= 2;
+        self.pc = self.pc.wrapping_sub(2);
     }
 
+    /// Access the register with the given index.
+    /// Only the lower nibble of the index is used.
     pub(crate) fn register(&self, index: u8) -> &u8 {
-        &self.registers[index as usize]
+        &self.registers[(index & 0x0F) as usize]
     }
+    /// Mutably access the register with the given index.
+    /// Only the lower nibble of the index is used.
     pub(crate) fn register_mut(&mut self, index: u8) -> &mut u8 {
-        &mut self.registers[index as usize]
+        &mut self.registers[(index & 0x0F) as usize]
     }
     pub(crate) fn i(&self) -> &u16 {
         &self.i
@@ -49,6 +150,7 @@ impl Cpu {
     pub(crate) fn i_mut(&mut self) -> &mut u16 {
         &mut self.i
     }
+    #[cfg(test)]
     pub(crate) fn carry(&self) -> &u8 {
         &self.registers[15]
     }
@@ -58,6 +160,9 @@ impl Cpu {
     pub(crate) fn carry_off(&mut self) {
         self.registers[15] = 0;
     }
+    pub(crate) fn set_carry(&mut self, carry: bool) {
+        self.registers[15] = carry as u8;
+    }
     pub(crate) fn delay(&self) -> &u8 {
         &self.delay
     }
@@ -70,4 +175,74 @@ impl Cpu {
     pub(crate) fn sound_mut(&mut self) -> &mut u8 {
         &mut self.sound
     }
+    /// Copy the cpu state, the stack pointer lives
+    /// outside of the cpu and has to be provided.
+    pub(crate) fn snapshot(&self, sp: u8) -> CpuSnapshot {
+        CpuSnapshot {
+            v: self.registers,
+            i: self.i,
+            pc: self.pc,
+            sp,
+            delay: self.delay,
+            sound: self.sound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn registers_convert_from_index() {
+        for index in 0..16 {
+            assert_eq!(index, Register::try_from(index).unwrap().index());
+        }
+        assert_eq!(Ok(Register::VF), Register::try_from(0x0F));
+        assert_eq!(Err(InvalidRegister(0x10)), Register::try_from(0x10));
+    }
+
+    #[test]
+    fn vf_is_reachable_by_name() {
+        let flag = match Register::VF {
+            Register::V0
+            | Register::V1
+            | Register::V2
+            | Register::V3
+            | Register::V4
+            | Register::V5
+            | Register::V6
+            | Register::V7
+            | Register::V8
+            | Register::V9
+            | Register::VA
+            | Register::VB
+            | Register::VC
+            | Register::VD
+            | Register::VE => false,
+            Register::VF => true,
+        };
+        assert!(flag);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn can_display_snapshot() {
+        let mut cpu = Cpu::new(0x200);
+        *cpu.register_mut(0xA) = 0x3C;
+        assert_eq!(
+            "PC=0200 I=0000 SP=1 DT=00 ST=00
+V0=00 V1=00 V2=00 V3=00 V4=00 V5=00 V6=00 V7=00
+V8=00 V9=00 VA=3C VB=00 VC=00 VD=00 VE=00 VF=00
+",
+            cpu.snapshot(1).to_string()
+        );
+    }
+
+    #[test]
+    fn register_index_is_masked() {
+        let mut cpu = Cpu::new(0x200);
+        *cpu.register_mut(0x13) = 7;
+        assert_eq!(7, *cpu.register(3));
+    }
 }
@@ -0,0 +1,563 @@
+//! Breakpoints for stepping through a rom with
+//! [`crate::emulator::Emulator::run_until_breakpoint`],
+//! [`crate::emulator::Emulator::step_over`] and
+//! [`crate::emulator::Emulator::step_out`], and register watches.
+//! [`Debugger`] combines them into a session for frontends.
+//! A memory access log tells which instructions touched an address.
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use core::ops::Range;
+
+#[cfg(feature = "alloc")]
+mod access;
+#[cfg(feature = "alloc")]
+mod debugger;
+#[cfg(feature = "gdb")]
+pub mod gdb;
+mod idle;
+#[cfg(feature = "std")]
+mod trace;
+#[cfg(feature = "alloc")]
+mod undo;
+#[cfg(feature = "alloc")]
+pub(crate) use access::AccessLog;
+#[cfg(feature = "alloc")]
+pub use access::{AccessKind, MemoryAccess, MEMORY_ACCESS_LOG_LEN};
+#[cfg(feature = "alloc")]
+pub use debugger::Debugger;
+pub(crate) use idle::{can_be_quiet, IdleTracker};
+#[cfg(feature = "std")]
+pub(crate) use trace::write_trace_line;
+#[cfg(feature = "std")]
+pub use trace::TraceDetail;
+#[cfg(feature = "alloc")]
+pub(crate) use undo::UndoLog;
+#[cfg(feature = "alloc")]
+pub use undo::DEFAULT_UNDO_DEPTH;
+
+#[cfg(feature = "alloc")]
+use crate::cpu::CpuSnapshot;
+use crate::{command::Command, cpu::Register, opcode::OpCode};
+
+/// Identifies a breakpoint, to remove it again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BreakpointId(u32);
+
+/// Why [`crate::emulator::Emulator::run_until_breakpoint`] returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The instruction at the address is next and has not run yet
+    Breakpoint(u16),
+    /// A register watch fired after the instruction ran
+    Watch(WatchHit),
+    /// The step finished, see [`crate::emulator::Emulator::step_over`]
+    /// and [`crate::emulator::Emulator::step_out`]
+    Stepped,
+    /// All ticks of the budget ran
+    MaxTicks,
+    /// The emulator faulted or the rom exited,
+    /// see [`crate::emulator::Emulator::halt_reason`]
+    Halted,
+    /// A FX0A instruction waits for a key
+    WaitingForKey,
+    /// The emulator is paused and does not run
+    Paused,
+    /// The rom loops at the address without changing anything,
+    /// see [`crate::emulator::Emulator::is_spinning`]
+    IdleLoop { pc: u16 },
+}
+
+/// When a breakpoint stops, checked before the instruction at its address runs.
+/// Unlike a predicate this works without closures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakCondition {
+    /// The register holds the value
+    RegisterEquals(Register, u8),
+    /// The register holds another value than the last time the address was
+    /// reached. Reaching it for the first time only remembers the value.
+    RegisterChanged(Register),
+    /// The byte at the address holds the value
+    MemoryEquals { address: u16, value: u8 },
+}
+
+/// When a register watch fires, checked after every instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchMode {
+    /// The instruction wrote another value to the register
+    Changed,
+    /// The instruction set the register to the value, which it did not hold before
+    Equals(u8),
+}
+
+/// The instruction a register watch fired on and what it did to the register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub id: BreakpointId,
+    /// The address of the instruction, which already ran
+    pub pc: u16,
+    pub register: Register,
+    pub before: u8,
+    pub after: u8,
+}
+
+#[cfg(feature = "alloc")]
+struct Watch {
+    id: BreakpointId,
+    register: Register,
+    mode: WatchMode,
+}
+
+#[cfg(feature = "alloc")]
+impl Watch {
+    fn hit(&self, before: &CpuSnapshot, after: &CpuSnapshot) -> Option<WatchHit> {
+        let index = self.register.index() as usize;
+        let (old, new) = (before.v[index], after.v[index]);
+        let fires = match self.mode {
+            WatchMode::Changed => old != new,
+            WatchMode::Equals(value) => old != value && new == value,
+        };
+        fires.then_some(WatchHit {
+            id: self.id,
+            pc: before.pc,
+            register: self.register,
+            before: old,
+            after: new,
+        })
+    }
+}
+
+/// A decoded instruction, which displays as its mnemonic, e.g. `LD I, 0x22A`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction(pub(crate) Command);
+
+impl Instruction {
+    pub fn decode(opcode: u16) -> Self {
+        Self(OpCode::from(opcode).into())
+    }
+
+    pub fn kind(&self) -> InstructionKind {
+        self.0.kind()
+    }
+
+    /// Whether the instruction skips the next one if its condition holds
+    pub fn is_skip(&self) -> bool {
+        matches!(
+            self.kind(),
+            InstructionKind::SkipIfValueEqual
+                | InstructionKind::SkipIfValueNotEqual
+                | InstructionKind::SkipIfRegisterEqual
+                | InstructionKind::SkipIfRegisterNotEqual
+                | InstructionKind::SkipIfKeyPressed
+                | InstructionKind::SkipIfKeyNotPressed
+        )
+    }
+}
+
+/// Prints the mnemonic, like the Display implementation
+#[cfg(feature = "defmt")]
+impl defmt::Format for Instruction {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        let mnemonic = crate::logging::Message::format(format_args!("{}", self));
+        defmt::write!(f, "{=str}", mnemonic.as_str());
+    }
+}
+
+impl core::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The kind of an instruction, without its operands, to break on e.g. any draw
+/// with [`crate::emulator::Emulator::add_kind_breakpoint`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstructionKind {
+    ClearScreen,
+    ReturnFromSubroutine,
+    #[cfg(feature = "schip")]
+    SetDisplayMode,
+    #[cfg(feature = "schip")]
+    ScrollDown,
+    #[cfg(feature = "xochip")]
+    ScrollUp,
+    #[cfg(feature = "schip")]
+    ScrollRight,
+    #[cfg(feature = "schip")]
+    ScrollLeft,
+    #[cfg(feature = "schip")]
+    Exit,
+    Jump,
+    JumpOffset,
+    Call,
+    SkipIfValueEqual,
+    SkipIfValueNotEqual,
+    SkipIfRegisterEqual,
+    SkipIfRegisterNotEqual,
+    Load,
+    LoadI,
+    LoadSpriteDigitIntoI,
+    #[cfg(feature = "schip")]
+    LoadLargeSpriteDigitIntoI,
+    LoadBcd,
+    Add,
+    AddRegisters,
+    AddI,
+    CopyRegister,
+    Or,
+    And,
+    Xor,
+    Sub,
+    SubInverse,
+    ShiftRight,
+    ShiftLeft,
+    RandomAnd,
+    DrawSprite,
+    SkipIfKeyPressed,
+    SkipIfKeyNotPressed,
+    LoadDelay,
+    SetDelay,
+    SetSound,
+    WaitKeyPress,
+    DumpAll,
+    LoadAll,
+    #[cfg(feature = "schip")]
+    SaveFlags,
+    #[cfg(feature = "schip")]
+    LoadFlags,
+    #[cfg(feature = "xochip")]
+    SelectPlanes,
+    #[cfg(feature = "xochip")]
+    SaveRegisterRange,
+    #[cfg(feature = "xochip")]
+    LoadRegisterRange,
+    #[cfg(feature = "xochip")]
+    LoadLongI,
+    #[cfg(feature = "xochip")]
+    LoadAudioPattern,
+    #[cfg(feature = "xochip")]
+    SetPitch,
+    Invalid,
+}
+
+#[cfg(feature = "alloc")]
+enum Condition {
+    Always,
+    /// With the register value seen last for [`BreakCondition::RegisterChanged`]
+    When(BreakCondition, Option<u8>),
+    #[cfg(feature = "std")]
+    Predicate(Box<dyn Fn(&CpuSnapshot) -> bool + Send>),
+}
+
+#[cfg(feature = "alloc")]
+impl Condition {
+    fn is_met(&mut self, cpu: &CpuSnapshot, memory: &impl Fn(u16) -> Option<u8>) -> bool {
+        match self {
+            Condition::Always => true,
+            Condition::When(BreakCondition::RegisterEquals(register, value), _) => {
+                cpu.v[register.index() as usize] == *value
+            }
+            Condition::When(BreakCondition::RegisterChanged(register), last) => {
+                let value = cpu.v[register.index() as usize];
+                let changed = last.is_some_and(|last| last != value);
+                *last = Some(value);
+                changed
+            }
+            Condition::When(BreakCondition::MemoryEquals { address, value }, _) => {
+                memory(*address) == Some(*value)
+            }
+            #[cfg(feature = "std")]
+            Condition::Predicate(predicate) => predicate(cpu),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+struct Breakpoint {
+    id: BreakpointId,
+    address: u16,
+    condition: Condition,
+}
+
+/// The breakpoints of an emulator, sorted by address
+#[cfg(feature = "alloc")]
+#[derive(Default)]
+pub(crate) struct Breakpoints {
+    breakpoints: Vec<Breakpoint>,
+    /// Breakpoints on every instruction of a kind, wherever it is
+    kinds: Vec<(BreakpointId, InstructionKind)>,
+    watches: Vec<Watch>,
+    next_id: u32,
+}
+
+#[cfg(feature = "alloc")]
+impl Breakpoints {
+    pub(crate) fn add(&mut self, address: u16) -> BreakpointId {
+        self.insert(address, Condition::Always)
+    }
+
+    pub(crate) fn add_when(&mut self, address: u16, condition: BreakCondition) -> BreakpointId {
+        self.insert(address, Condition::When(condition, None))
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn add_if(
+        &mut self,
+        address: u16,
+        predicate: impl Fn(&CpuSnapshot) -> bool + Send + 'static,
+    ) -> BreakpointId {
+        self.insert(address, Condition::Predicate(Box::new(predicate)))
+    }
+
+    pub(crate) fn add_kind(&mut self, kind: InstructionKind) -> BreakpointId {
+        let id = self.next_id();
+        self.kinds.push((id, kind));
+        id
+    }
+
+    pub(crate) fn add_watch(&mut self, register: Register, mode: WatchMode) -> BreakpointId {
+        let id = self.next_id();
+        self.watches.push(Watch { id, register, mode });
+        id
+    }
+
+    fn next_id(&mut self) -> BreakpointId {
+        let id = BreakpointId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        id
+    }
+
+    fn insert(&mut self, address: u16, condition: Condition) -> BreakpointId {
+        let id = self.next_id();
+        let index = self
+            .breakpoints
+            .partition_point(|breakpoint| breakpoint.address <= address);
+        let breakpoint = Breakpoint {
+            id,
+            address,
+            condition,
+        };
+        self.breakpoints.insert(index, breakpoint);
+        id
+    }
+
+    /// Returns whether the breakpoint existed
+    pub(crate) fn remove(&mut self, id: BreakpointId) -> bool {
+        let len = self.len();
+        self.breakpoints.retain(|breakpoint| breakpoint.id != id);
+        self.kinds.retain(|(kind_id, _)| *kind_id != id);
+        self.watches.retain(|watch| watch.id != id);
+        self.len() != len
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.kinds.clear();
+        self.watches.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.breakpoints.len() + self.kinds.len() + self.watches.len()
+    }
+
+    /// Whether any register is watched
+    pub(crate) fn has_watches(&self) -> bool {
+        !self.watches.is_empty()
+    }
+
+    /// The first watch the instruction between the snapshots fired
+    pub(crate) fn watch_hit(&self, before: &CpuSnapshot, after: &CpuSnapshot) -> Option<WatchHit> {
+        self.watches
+            .iter()
+            .find_map(|watch| watch.hit(before, after))
+    }
+
+    /// Whether any breakpoint is set on instruction kinds
+    pub(crate) fn has_kinds(&self) -> bool {
+        !self.kinds.is_empty()
+    }
+
+    /// Whether a breakpoint is set on the kind of instruction
+    pub(crate) fn hits_kind(&self, kind: InstructionKind) -> bool {
+        self.kinds.iter().any(|(_, breakpoint)| *breakpoint == kind)
+    }
+
+    /// Whether any breakpoint is set at the address, whatever its condition
+    pub(crate) fn is_set(&self, address: u16) -> bool {
+        !self.range(address).is_empty()
+    }
+
+    /// Whether a breakpoint at the program counter of the snapshot stops,
+    /// reading memory through the function. All conditions at the address
+    /// are checked, so each of them sees every time it is reached.
+    pub(crate) fn hits(&mut self, cpu: &CpuSnapshot, memory: impl Fn(u16) -> Option<u8>) -> bool {
+        let mut hit = false;
+        let range = self.range(cpu.pc);
+        for breakpoint in &mut self.breakpoints[range] {
+            hit |= breakpoint.condition.is_met(cpu, &memory);
+        }
+        hit
+    }
+
+    /// The indices of the breakpoints at the address
+    fn range(&self, address: u16) -> Range<usize> {
+        let start = self
+            .breakpoints
+            .partition_point(|breakpoint| breakpoint.address < address);
+        let end = self
+            .breakpoints
+            .partition_point(|breakpoint| breakpoint.address <= address);
+        start..end
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn breakpoints_hit_until_removed() {
+        let mut breakpoints = Breakpoints::default();
+        let first = breakpoints.add(0x300);
+        let second = breakpoints.add(0x200);
+        let third = breakpoints.add(0x300);
+        assert!(breakpoints.is_set(0x200));
+        assert!(breakpoints.is_set(0x300));
+        assert!(!breakpoints.is_set(0x202));
+
+        assert!(breakpoints.remove(first));
+        assert!(!breakpoints.remove(first));
+        assert!(breakpoints.is_set(0x300));
+        assert!(breakpoints.remove(third));
+        assert!(!breakpoints.is_set(0x300));
+
+        breakpoints.clear();
+        assert!(!breakpoints.is_set(0x200));
+        assert!(!breakpoints.remove(second));
+    }
+
+    #[cfg(feature = "alloc")]
+    fn cpu_at(pc: u16, v3: u8) -> CpuSnapshot {
+        let mut v = [0; 16];
+        v[3] = v3;
+        CpuSnapshot {
+            v,
+            i: 0,
+            pc,
+            sp: 0,
+            delay: 0,
+            sound: 0,
+        }
+    }
+
+    #[test]
+    fn instructions_display_as_mnemonics() {
+        let mnemonics = [
+            (0x00E0, "CLS"),
+            (0x122A, "JP 0x22A"),
+            (0x6A0C, "LD VA, 0x0C"),
+            (0xA22A, "LD I, 0x22A"),
+            (0x8016, "SHR V0, V1"),
+            (0xD01F, "DRW V0, V1, 15"),
+            (0xF30A, "LD V3, K"),
+            (0xF565, "LD V5, [I]"),
+            (0x5121, "DW 0x5121"),
+        ];
+        for (opcode, mnemonic) in mnemonics {
+            assert_eq!(mnemonic, Instruction::decode(opcode).to_string());
+        }
+        assert_eq!(
+            InstructionKind::DrawSprite,
+            Instruction::decode(0xD01F).kind()
+        );
+        assert!(Instruction::decode(0xE19E).is_skip());
+        assert!(!Instruction::decode(0x1200).is_skip());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn kinds_hit_until_removed() {
+        let mut breakpoints = Breakpoints::default();
+        assert!(!breakpoints.has_kinds());
+        let draw = breakpoints.add_kind(InstructionKind::DrawSprite);
+        breakpoints.add_kind(InstructionKind::Call);
+        assert!(breakpoints.hits_kind(InstructionKind::DrawSprite));
+        assert!(!breakpoints.hits_kind(InstructionKind::Jump));
+        assert!(breakpoints.remove(draw));
+        assert!(!breakpoints.hits_kind(InstructionKind::DrawSprite));
+        breakpoints.clear();
+        assert!(!breakpoints.has_kinds());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn watches_compare_before_and_after() {
+        let mut breakpoints = Breakpoints::default();
+        assert!(!breakpoints.has_watches());
+        let flag = breakpoints.add_watch(Register::VF, WatchMode::Changed);
+        breakpoints.add_watch(Register::V3, WatchMode::Equals(2));
+        assert!(breakpoints.has_watches());
+
+        let mut carry = cpu_at(0x202, 0);
+        carry.v[0xF] = 1;
+        let hit = breakpoints.watch_hit(&cpu_at(0x200, 0), &carry);
+        assert_eq!(
+            Some((flag, 0x200, 0, 1)),
+            hit.map(|hit| (hit.id, hit.pc, hit.before, hit.after))
+        );
+        assert_eq!(None, breakpoints.watch_hit(&carry, &carry));
+
+        assert_eq!(
+            None,
+            breakpoints.watch_hit(&cpu_at(0x200, 0), &cpu_at(0x202, 1))
+        );
+        let hit = breakpoints.watch_hit(&cpu_at(0x200, 1), &cpu_at(0x202, 2));
+        assert_eq!(Some(Register::V3), hit.map(|hit| hit.register));
+        assert_eq!(
+            None,
+            breakpoints.watch_hit(&cpu_at(0x200, 2), &cpu_at(0x202, 2))
+        );
+
+        breakpoints.clear();
+        assert!(!breakpoints.has_watches());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn conditions_are_checked_at_their_address() {
+        let memory = |address: u16| (address == 0x300).then_some(7);
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.add_when(0x200, BreakCondition::RegisterEquals(Register::V3, 2));
+        breakpoints.add_when(0x202, BreakCondition::RegisterChanged(Register::V3));
+        let memory_equals = BreakCondition::MemoryEquals {
+            address: 0x300,
+            value: 7,
+        };
+        breakpoints.add_when(0x204, memory_equals);
+        assert!(breakpoints.is_set(0x200));
+
+        assert!(!breakpoints.hits(&cpu_at(0x200, 1), memory));
+        assert!(breakpoints.hits(&cpu_at(0x200, 2), memory));
+        assert!(!breakpoints.hits(&cpu_at(0x206, 2), memory));
+
+        assert!(!breakpoints.hits(&cpu_at(0x202, 1), memory));
+        assert!(!breakpoints.hits(&cpu_at(0x202, 1), memory));
+        assert!(breakpoints.hits(&cpu_at(0x202, 2), memory));
+
+        assert!(breakpoints.hits(&cpu_at(0x204, 0), memory));
+        assert!(!breakpoints.hits(&cpu_at(0x204, 0), |_| Some(6)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn predicates_see_the_cpu() {
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.add_if(0x200, |cpu| cpu.v[3] > 4);
+        assert!(!breakpoints.hits(&cpu_at(0x200, 4), |_| None));
+        assert!(breakpoints.hits(&cpu_at(0x200, 5), |_| None));
+    }
+}
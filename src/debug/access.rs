@@ -0,0 +1,97 @@
+#[cfg(not(feature = "std"))]
+use alloc::collections::VecDeque;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::collections::VecDeque;
+
+/// The number of accesses the memory access log keeps until they are taken.
+/// Once full, the oldest access is dropped.
+pub const MEMORY_ACCESS_LOG_LEN: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// A byte an instruction read or wrote,
+/// see [`crate::emulator::Emulator::enable_memory_access_log`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+    /// The [`crate::emulator::Emulator::tick_count`] of the step
+    pub tick: u64,
+    /// The address of the instruction
+    pub pc: u16,
+    pub address: u16,
+    pub kind: AccessKind,
+    /// The byte read, or the byte written
+    pub value: u8,
+}
+
+/// The most recent accesses to a range of addresses
+pub(crate) struct AccessLog {
+    accesses: VecDeque<MemoryAccess>,
+    addresses: Range<u16>,
+    reads: bool,
+}
+
+impl AccessLog {
+    pub(crate) fn new(addresses: Range<u16>, reads: bool) -> Self {
+        Self {
+            accesses: VecDeque::new(),
+            addresses,
+            reads,
+        }
+    }
+
+    /// Keep the access, if it matches the filter
+    pub(crate) fn record(&mut self, access: MemoryAccess) {
+        if !self.addresses.contains(&access.address)
+            || (access.kind == AccessKind::Read && !self.reads)
+        {
+            return;
+        }
+        if self.accesses.len() == MEMORY_ACCESS_LOG_LEN {
+            self.accesses.pop_front();
+        }
+        self.accesses.push_back(access);
+    }
+
+    /// Take the accesses, oldest first
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = MemoryAccess> + '_ {
+        self.accesses.drain(..)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn access(address: u16, kind: AccessKind) -> MemoryAccess {
+        MemoryAccess {
+            tick: 1,
+            pc: 0x200,
+            address,
+            kind,
+            value: 0,
+        }
+    }
+
+    #[test]
+    fn keeps_the_most_recent_matching_accesses() {
+        let mut log = AccessLog::new(0x300..0x310, false);
+        log.record(access(0x2FF, AccessKind::Write));
+        log.record(access(0x300, AccessKind::Read));
+        log.record(access(0x310, AccessKind::Write));
+        assert_eq!(0, log.drain().count());
+
+        for _ in 0..=MEMORY_ACCESS_LOG_LEN {
+            log.record(access(0x30F, AccessKind::Write));
+        }
+        log.record(access(0x300, AccessKind::Write));
+        let accesses: Vec<_> = log.drain().collect();
+        assert_eq!(MEMORY_ACCESS_LOG_LEN, accesses.len());
+        assert_eq!(Some(0x300), accesses.last().map(|access| access.address));
+        assert_eq!(0, log.drain().count());
+    }
+}
@@ -0,0 +1,306 @@
+use crate::{
+    cpu::{CpuSnapshot, Register},
+    emulator::{Emulator, StepInfo},
+    io::clock::{Clock, DefaultClock},
+};
+
+use super::{
+    BreakCondition, BreakpointId, Instruction, InstructionKind, StopReason, WatchMode,
+    DEFAULT_UNDO_DEPTH,
+};
+
+/// A debugging session around an emulator, combining pausing, stepping,
+/// breakpoints and state inspection for frontends.
+///
+/// The emulator stays paused between commands, so its timers are frozen
+/// while the user looks around and stepping does not make them catch up.
+/// Every step the emulator takes during the session is saved, so the last
+/// [`DEFAULT_UNDO_DEPTH`] of them can be undone with [`Debugger::step_back`].
+pub struct Debugger<C: Clock = DefaultClock> {
+    emulator: Emulator<C>,
+}
+
+impl<C: Clock> Debugger<C> {
+    /// Take over the emulator, pausing it
+    pub fn new(mut emulator: Emulator<C>) -> Self {
+        emulator.pause();
+        emulator.save_undo_steps(Some(DEFAULT_UNDO_DEPTH));
+        Self { emulator }
+    }
+
+    pub fn emulator(&self) -> &Emulator<C> {
+        &self.emulator
+    }
+
+    pub fn emulator_mut(&mut self) -> &mut Emulator<C> {
+        &mut self.emulator
+    }
+
+    /// End the session, handing back the emulator as it is, paused or not
+    pub fn into_emulator(mut self) -> Emulator<C> {
+        self.emulator.save_undo_steps(None);
+        self.emulator
+    }
+
+    pub fn pause(&mut self) {
+        self.emulator.pause();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.emulator.is_paused()
+    }
+
+    /// Run for up to budget ticks. The emulator keeps running after the
+    /// budget is used up, e.g. to resume every frame, but pauses again on
+    /// any other stop like a breakpoint.
+    pub fn resume(&mut self, budget: usize) -> StopReason {
+        let reason = self.run(|emulator| emulator.run_until_breakpoint(budget));
+        if reason == StopReason::MaxTicks {
+            self.emulator.resume();
+        }
+        reason
+    }
+
+    /// Run until the instruction at the address is next, with a breakpoint
+    /// that only lasts for this run. Other breakpoints still stop earlier.
+    pub fn run_to(&mut self, address: u16, budget: usize) -> StopReason {
+        let cursor = self.emulator.add_breakpoint(address);
+        let reason = self.run(|emulator| emulator.run_until_breakpoint(budget));
+        self.emulator.remove_breakpoint(cursor);
+        reason
+    }
+
+    /// Run the next instruction, see [`Emulator::step`]
+    pub fn step(&mut self) -> Option<StepInfo> {
+        self.run(|emulator| emulator.step())
+    }
+
+    /// Undo the last step, restoring the machine state from before it.
+    /// Returns false once there are no more steps to undo. Loading a rom
+    /// forgets all steps. The keys and their queued events are rewound,
+    /// recorded input and the flag store are not.
+    pub fn step_back(&mut self) -> bool {
+        self.emulator.undo_step()
+    }
+
+    /// The number of steps [`Debugger::step_back`] can undo right now
+    pub fn undo_len(&self) -> usize {
+        self.emulator.undo_len()
+    }
+
+    /// Keep up to depth steps to undo, forgetting the oldest ones beyond it
+    pub fn set_undo_depth(&mut self, depth: usize) {
+        self.emulator.save_undo_steps(Some(depth));
+    }
+
+    /// See [`Emulator::step_over`]
+    pub fn step_over(&mut self, budget: usize) -> StopReason {
+        self.run(|emulator| emulator.step_over(budget))
+    }
+
+    /// See [`Emulator::step_out`]
+    pub fn step_out(&mut self, budget: usize) -> StopReason {
+        self.run(|emulator| emulator.step_out(budget))
+    }
+
+    /// Run the emulator for a command and pause it again. Resuming
+    /// resyncs the timers, so the time spent paused is not taken from them.
+    fn run<T>(&mut self, command: impl FnOnce(&mut Emulator<C>) -> T) -> T {
+        self.emulator.resume();
+        let result = command(&mut self.emulator);
+        self.emulator.pause();
+        result
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) -> BreakpointId {
+        self.emulator.add_breakpoint(address)
+    }
+
+    pub fn add_breakpoint_when(&mut self, address: u16, condition: BreakCondition) -> BreakpointId {
+        self.emulator.add_breakpoint_when(address, condition)
+    }
+
+    pub fn add_kind_breakpoint(&mut self, kind: InstructionKind) -> BreakpointId {
+        self.emulator.add_kind_breakpoint(kind)
+    }
+
+    pub fn add_register_watch(&mut self, register: Register, mode: WatchMode) -> BreakpointId {
+        self.emulator.add_register_watch(register, mode)
+    }
+
+    /// Returns whether the breakpoint or watch existed
+    pub fn remove_breakpoint(&mut self, id: BreakpointId) -> bool {
+        self.emulator.remove_breakpoint(id)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.emulator.clear_breakpoints();
+    }
+
+    pub fn cpu(&self) -> CpuSnapshot {
+        self.emulator.cpu_snapshot()
+    }
+
+    /// The return addresses of the current subroutines, oldest first
+    pub fn call_stack(&self) -> &[u16] {
+        self.emulator.call_stack()
+    }
+
+    /// The instructions up to lines words before and after the program counter.
+    /// The window is clamped at both ends of memory.
+    pub fn disassembly_around_pc(
+        &self,
+        lines: u16,
+    ) -> impl Iterator<Item = (u16, Instruction)> + '_ {
+        let pc = self.emulator.pc();
+        let start = pc.saturating_sub(lines.saturating_mul(2));
+        let count = (pc - start) as usize / 2 + lines as usize + 1;
+        self.emulator.disassemble(start, count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::{KeyInputStyle, TimerStyle},
+        emulator::SoundEvent,
+        ManualClock,
+    };
+
+    #[test]
+    fn debugs_a_rom_end_to_end() {
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x3C, // 0x200: LD V0, 0x3C
+            0xF0, 0x15, // 0x202: LD DT, V0
+            0x22, 0x0A, // 0x204: CALL 0x20A
+            0x71, 0x01, // 0x206: ADD V1, 0x01
+            0x12, 0x06, // 0x208: JP 0x206
+            0x62, 0x07, // 0x20A: LD V2, 0x07
+            0x00, 0xEE, // 0x20C: RET
+        ];
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.configuration.timers = TimerStyle::WallClock;
+        emulator.load_rom(&rom).unwrap();
+        let mut debugger = Debugger::new(emulator);
+        assert!(debugger.is_paused());
+
+        let step = debugger.step().unwrap();
+        assert_eq!("LD V0, 0x3C", step.instruction.to_string());
+        debugger.step();
+        assert!(debugger.is_paused());
+        // Time passing while paused is not taken from the timers
+        debugger.emulator_mut().advance_time_ms(1000);
+        assert_eq!(StopReason::Stepped, debugger.step_over(100));
+        assert_eq!(0x3C, debugger.cpu().delay);
+        assert_eq!(7, debugger.emulator().get_register(Register::V2));
+
+        debugger.emulator_mut().load_rom(&rom).unwrap();
+        assert_eq!(StopReason::Breakpoint(0x20C), debugger.run_to(0x20C, 100));
+        assert_eq!(&[0x206], debugger.call_stack());
+        let disassembly: Vec<_> = debugger
+            .disassembly_around_pc(1)
+            .map(|(address, instruction)| (address, instruction.to_string()))
+            .collect();
+        let expected = [(0x20A, "LD V2, 0x07"), (0x20C, "RET"), (0x20E, "DW 0x0000")];
+        assert_eq!(
+            expected.map(|(address, line)| (address, line.to_string())),
+            *disassembly
+        );
+        assert_eq!(StopReason::Stepped, debugger.step_out(100));
+        assert_eq!(0x206, debugger.cpu().pc);
+
+        let watch = debugger.add_register_watch(Register::V1, WatchMode::Equals(3));
+        assert!(matches!(debugger.resume(100), StopReason::Watch(hit) if hit.pc == 0x206));
+        assert!(debugger.is_paused());
+        debugger.remove_breakpoint(watch);
+        assert_eq!(StopReason::MaxTicks, debugger.resume(10));
+        assert!(!debugger.is_paused());
+        debugger.pause();
+        assert!(debugger.emulator().is_paused());
+    }
+
+    #[test]
+    fn steps_back_to_the_same_state() {
+        #[rustfmt::skip]
+        let mut rom = vec![
+            0xA2, 0x20, // 0x200: LD I, 0x220
+            0x60, 0x05, // 0x202: LD V0, 0x05
+            0xD0, 0x05, // 0x204: DRW V0, V0, 5
+            0xC1, 0xFF, // 0x206: RND V1, 0xFF
+            0xF1, 0x33, // 0x208: LD B, V1
+            0x22, 0x10, // 0x20A: CALL 0x210
+            0x00, 0xE0, // 0x20C: CLS
+            0x12, 0x0E, // 0x20E: JP 0x20E
+            0xF2, 0x55, // 0x210: LD [I], V2
+            0x00, 0xEE, // 0x212: RET
+        ];
+        rom.resize(0x20, 0);
+        rom.extend([0xF0, 0x90, 0x90, 0x90, 0xF0]);
+        let mut debugger = Debugger::new(Emulator::new().with_rom(&rom).unwrap());
+
+        let mut fingerprints = vec![debugger.emulator().state_fingerprint()];
+        for _ in 0..10 {
+            debugger.step().unwrap();
+            fingerprints.push(debugger.emulator().state_fingerprint());
+        }
+        assert_eq!(0x20E, debugger.cpu().pc);
+        assert_eq!(10, debugger.undo_len());
+        for fingerprint in fingerprints.iter().rev().skip(1) {
+            assert!(debugger.step_back());
+            assert_eq!(*fingerprint, debugger.emulator().state_fingerprint());
+        }
+        assert!(!debugger.step_back());
+        assert_eq!(0, debugger.emulator().tick_count());
+
+        // The random number comes out the same again
+        assert_eq!(StopReason::Breakpoint(0x20E), debugger.run_to(0x20E, 100));
+        assert_eq!(fingerprints[10], debugger.emulator().state_fingerprint());
+
+        debugger.set_undo_depth(2);
+        assert_eq!(2, debugger.undo_len());
+        debugger.emulator_mut().load_rom(&rom).unwrap();
+        assert!(!debugger.step_back());
+    }
+
+    #[test]
+    fn steps_back_over_key_events_and_the_buzzer() {
+        #[rustfmt::skip]
+        let rom = [
+            0xF0, 0x0A, // 0x200: LD V0, K
+            0xF0, 0x18, // 0x202: LD ST, V0
+            0x12, 0x04, // 0x204: JP 0x204
+        ];
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.configuration.timers = TimerStyle::WallClock;
+        emulator.configuration.key_input = KeyInputStyle::Events;
+        emulator.load_rom(&rom).unwrap();
+        let mut debugger = Debugger::new(emulator);
+
+        assert!(debugger.step().unwrap().waited_for_key);
+        assert!(debugger.emulator_mut().tap_key(0x5, 1));
+        let steps = [debugger.step().unwrap(), debugger.step().unwrap()];
+        assert!(!steps[0].waited_for_key);
+        let events: Vec<_> = debugger.emulator_mut().take_sound_events().collect();
+        assert!(matches!(events[..], [SoundEvent::Started { tick: 3, .. }]));
+        let fingerprint = debugger.emulator().state_fingerprint();
+
+        assert!(debugger.step_back());
+        assert!(debugger.step_back());
+        assert!(!debugger.emulator().is_sound_on());
+        assert_eq!(steps, [debugger.step().unwrap(), debugger.step().unwrap()]);
+        assert_eq!(5, debugger.emulator().get_register(Register::V0));
+        assert_eq!(fingerprint, debugger.emulator().state_fingerprint());
+        let replayed: Vec<_> = debugger.emulator_mut().take_sound_events().collect();
+        assert_eq!(events, replayed);
+
+        // Events not taken yet are dropped with the step that caused them,
+        // the tap that ran out in the step holds the key again
+        assert!(debugger.step_back());
+        assert_eq!(0, debugger.emulator_mut().take_sound_events().count());
+        assert_eq!(1 << 0x5, debugger.emulator().keys());
+        assert_eq!(steps[1], debugger.step().unwrap());
+        assert_eq!(0, debugger.emulator().keys());
+    }
+}
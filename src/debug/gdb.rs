@@ -0,0 +1,494 @@
+//! A stub for the GDB remote serial protocol, so gdb, lldb or other
+//! frontends can debug a rom over TCP with [`serve`].
+//!
+//! The registers are numbered
+//!
+//! | Number | Register | Bits |
+//! |--------|----------|------|
+//! | 0-15   | V0-VF    | 8    |
+//! | 16     | I        | 16   |
+//! | 17     | PC       | 16   |
+//! | 18     | DT       | 8    |
+//! | 19     | ST       | 8    |
+//!
+//! with the 16 bit registers sent little-endian. The same numbering is
+//! served as `target.xml` through `qXfer:features:read`, see [`TARGET_XML`].
+//!
+//! The stub answers `?`, `g`, `G`, `p`, `P`, `m`, `M`, `c`, `s`, `Z0`, `z0`,
+//! `qSupported`, `qXfer:features:read`, `qAttached`, `H`, `k` and `D`,
+//! and stops a continue on Ctrl-C. Other packets get the empty reply.
+
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+use crate::{
+    debug::{BreakpointId, StopReason},
+    emulator::{Emulator, HaltReason},
+    io::clock::Clock,
+};
+
+/// The register description for gdb
+pub const TARGET_XML: &str = concat!(
+    r#"<?xml version="1.0"?>"#,
+    r#"<!DOCTYPE target SYSTEM "gdb-target.dtd">"#,
+    r#"<target version="1.0"><feature name="org.chip8.core">"#,
+    r#"<reg name="v0" bitsize="8" type="uint8" regnum="0"/>"#,
+    r#"<reg name="v1" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="v2" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="v3" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="v4" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="v5" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="v6" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="v7" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="v8" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="v9" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="va" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="vb" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="vc" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="vd" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="ve" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="vf" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="i" bitsize="16" type="data_ptr"/>"#,
+    r#"<reg name="pc" bitsize="16" type="code_ptr"/>"#,
+    r#"<reg name="dt" bitsize="8" type="uint8"/>"#,
+    r#"<reg name="st" bitsize="8" type="uint8"/>"#,
+    r#"</feature></target>"#,
+);
+
+/// The number of registers, see the module documentation
+const REGISTERS: usize = 20;
+/// The ticks run between checks for Ctrl-C while continuing
+const CONTINUE_TICKS: usize = 1000;
+const INTERRUPT: u8 = 0x03;
+
+/// Serve a single debugger connecting to the listener, until it detaches,
+/// kills the target or disconnects. The emulator only runs while the
+/// debugger continues or steps and is paused otherwise. The breakpoints
+/// the debugger set are removed again at the end.
+pub fn serve<C: Clock>(emulator: &mut Emulator<C>, listener: &TcpListener) -> io::Result<()> {
+    let (stream, _) = listener.accept()?;
+    emulator.pause();
+    let mut session = Session {
+        emulator,
+        stream,
+        received: Vec::new(),
+        breakpoints: HashMap::new(),
+    };
+    let result = session.run();
+    for (_, id) in session.breakpoints.drain() {
+        session.emulator.remove_breakpoint(id);
+    }
+    result
+}
+
+enum Action {
+    Reply(String),
+    /// Reply and end the session
+    ReplyAndClose(String),
+    Close,
+}
+
+struct Session<'a, C: Clock> {
+    emulator: &'a mut Emulator<C>,
+    stream: TcpStream,
+    /// Bytes read while checking for Ctrl-C, to be handled next
+    received: Vec<u8>,
+    breakpoints: HashMap<u16, BreakpointId>,
+}
+
+impl<C: Clock> Session<'_, C> {
+    fn run(&mut self) -> io::Result<()> {
+        while let Some(packet) = self.read_packet()? {
+            match self.handle(&packet)? {
+                Action::Reply(reply) => self.send(&reply)?,
+                Action::ReplyAndClose(reply) => return self.send(&reply),
+                Action::Close => return Ok(()),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, packet: &str) -> io::Result<Action> {
+        let reply = match packet.as_bytes().first() {
+            Some(b'?') => String::from("S05"),
+            Some(b'g') => self.read_registers(),
+            Some(b'G') => ok_or_error(self.write_registers(&packet[1..])),
+            Some(b'p') => self.read_register(&packet[1..]).unwrap_or_else(error),
+            Some(b'P') => ok_or_error(self.write_register(&packet[1..])),
+            Some(b'm') => self.read_memory(&packet[1..]).unwrap_or_else(error),
+            Some(b'M') => ok_or_error(self.write_memory(&packet[1..])),
+            Some(b'c') => self.resume()?,
+            Some(b's') => self.step(),
+            Some(b'Z') if packet.starts_with("Z0,") => ok_or_error(self.add_breakpoint(packet)),
+            Some(b'z') if packet.starts_with("z0,") => ok_or_error(self.remove_breakpoint(packet)),
+            Some(b'H') => String::from("OK"),
+            Some(b'k') => return Ok(Action::Close),
+            Some(b'D') => return Ok(Action::ReplyAndClose(String::from("OK"))),
+            _ if packet.starts_with("qSupported") => {
+                String::from("PacketSize=4000;qXfer:features:read+")
+            }
+            _ if packet.starts_with("qXfer:features:read:target.xml:") => {
+                target_xml(&packet["qXfer:features:read:target.xml:".len()..]).unwrap_or_else(error)
+            }
+            _ if packet == "qAttached" => String::from("1"),
+            _ => String::new(),
+        };
+        Ok(Action::Reply(reply))
+    }
+
+    fn register_bytes(&self, register: usize) -> Vec<u8> {
+        match register {
+            0..=15 => vec![self.emulator.cpu_snapshot().v[register]],
+            16 => self.emulator.i().to_le_bytes().to_vec(),
+            17 => self.emulator.pc().to_le_bytes().to_vec(),
+            18 => vec![self.emulator.delay()],
+            _ => vec![self.emulator.sound()],
+        }
+    }
+
+    fn set_register_bytes(&mut self, register: usize, bytes: &[u8]) -> Option<()> {
+        match (register, bytes) {
+            (0..=15, [value]) => *self.emulator.cpu.register_mut(register as u8) = *value,
+            (16, [low, high]) => self
+                .emulator
+                .set_i(u16::from_le_bytes([*low, *high]))
+                .ok()?,
+            (17, [low, high]) => self
+                .emulator
+                .set_pc(u16::from_le_bytes([*low, *high]))
+                .ok()?,
+            (18, [value]) => *self.emulator.cpu.delay_mut() = *value,
+            (19, [value]) => *self.emulator.cpu.sound_mut() = *value,
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn read_registers(&self) -> String {
+        let bytes: Vec<u8> = (0..REGISTERS)
+            .flat_map(|register| self.register_bytes(register))
+            .collect();
+        encode_hex(&bytes)
+    }
+
+    fn write_registers(&mut self, hex: &str) -> Option<()> {
+        let mut bytes = decode_hex(hex)?.into_iter();
+        for register in 0..REGISTERS {
+            let len = self.register_bytes(register).len();
+            let value: Vec<u8> = bytes.by_ref().take(len).collect();
+            self.set_register_bytes(register, &value)?;
+        }
+        Some(())
+    }
+
+    fn read_register(&self, number: &str) -> Option<String> {
+        let register = parse_hex(number)? as usize;
+        (register < REGISTERS).then(|| encode_hex(&self.register_bytes(register)))
+    }
+
+    fn write_register(&mut self, assignment: &str) -> Option<()> {
+        let (number, value) = assignment.split_once('=')?;
+        self.set_register_bytes(parse_hex(number)? as usize, &decode_hex(value)?)
+    }
+
+    fn read_memory(&self, range: &str) -> Option<String> {
+        let (address, len) = parse_range(range)?;
+        let bytes: Vec<u8> = (0..len)
+            .map_while(|offset| {
+                let address = u16::try_from(address.checked_add(offset)?).ok()?;
+                self.emulator.memory.get_u8(address)
+            })
+            .collect();
+        (!bytes.is_empty() || len == 0).then(|| encode_hex(&bytes))
+    }
+
+    fn write_memory(&mut self, write: &str) -> Option<()> {
+        let (range, hex) = write.split_once(':')?;
+        let (address, len) = parse_range(range)?;
+        let bytes = decode_hex(hex)?;
+        let end = address.checked_add(len)?;
+        if bytes.len() != len as usize || end > self.emulator.memory.len() as u32 {
+            return None;
+        }
+        for (offset, byte) in bytes.into_iter().enumerate() {
+            self.emulator
+                .memory
+                .write_u8(address as u16 + offset as u16, byte);
+        }
+        Some(())
+    }
+
+    fn add_breakpoint(&mut self, packet: &str) -> Option<()> {
+        let address = breakpoint_address(packet)?;
+        if !self.breakpoints.contains_key(&address) {
+            let id = self.emulator.add_breakpoint(address);
+            self.breakpoints.insert(address, id);
+        }
+        Some(())
+    }
+
+    fn remove_breakpoint(&mut self, packet: &str) -> Option<()> {
+        let id = self.breakpoints.remove(&breakpoint_address(packet)?)?;
+        self.emulator.remove_breakpoint(id);
+        Some(())
+    }
+
+    /// Run until a breakpoint, the emulator halting or Ctrl-C
+    fn resume(&mut self) -> io::Result<String> {
+        self.emulator.resume();
+        let reply = loop {
+            match self.emulator.run_until_breakpoint(CONTINUE_TICKS) {
+                StopReason::MaxTicks => (),
+                StopReason::WaitingForKey => std::thread::sleep(Duration::from_millis(1)),
+                StopReason::Halted => break self.halt_reply(),
+                _ => break String::from("S05"),
+            }
+            if self.is_interrupted()? {
+                break String::from("S02");
+            }
+        };
+        self.emulator.pause();
+        Ok(reply)
+    }
+
+    fn step(&mut self) -> String {
+        self.emulator.resume();
+        self.emulator.step();
+        self.emulator.pause();
+        match self.emulator.is_halted() {
+            true => self.halt_reply(),
+            false => String::from("S05"),
+        }
+    }
+
+    /// The rom exiting ends the process, faults stop it with SIGSEGV
+    /// so the state can still be inspected
+    fn halt_reply(&self) -> String {
+        match self.emulator.halt_reason() {
+            Some(HaltReason::Exited) => String::from("W00"),
+            _ => String::from("S0B"),
+        }
+    }
+
+    /// Whether Ctrl-C arrived, without waiting for it. Other bytes are
+    /// kept for reading the next packet. A closed connection counts as
+    /// an interrupt, so the next read notices it.
+    fn is_interrupted(&mut self) -> io::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+        let mut buffer = [0; 64];
+        let result = self.stream.read(&mut buffer);
+        self.stream.set_nonblocking(false)?;
+        match result {
+            Ok(0) => Ok(true),
+            Ok(len) => {
+                let bytes = &buffer[..len];
+                self.received
+                    .extend(bytes.iter().filter(|byte| **byte != INTERRUPT));
+                Ok(bytes.contains(&INTERRUPT))
+            }
+            Err(error) if error.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        if !self.received.is_empty() {
+            return Ok(Some(self.received.remove(0)));
+        }
+        let mut byte = [0];
+        match self.stream.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    /// Read the next packet and acknowledge it, skipping acknowledgements
+    /// and stray bytes. Returns `None` once the connection closed.
+    fn read_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(b'$') => (),
+                Some(_) => continue,
+            }
+            let mut data = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                }
+            }
+            let (Some(high), Some(low)) = (self.read_byte()?, self.read_byte()?) else {
+                return Ok(None);
+            };
+            let checksum = core::str::from_utf8(&[high, low])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            match (
+                checksum == Some(checksum_of(&data)),
+                String::from_utf8(data),
+            ) {
+                (true, Ok(packet)) => {
+                    self.stream.write_all(b"+")?;
+                    return Ok(Some(packet));
+                }
+                _ => self.stream.write_all(b"-")?,
+            }
+        }
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+        self.stream.write_all(packet.as_bytes())
+    }
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+fn ok_or_error(result: Option<()>) -> String {
+    match result {
+        Some(()) => String::from("OK"),
+        None => error(),
+    }
+}
+
+fn error() -> String {
+    String::from("E01")
+}
+
+fn parse_hex(hex: &str) -> Option<u32> {
+    u32::from_str_radix(hex, 16).ok()
+}
+
+/// Parse `address,length`
+fn parse_range(range: &str) -> Option<(u32, u32)> {
+    let (address, len) = range.split_once(',')?;
+    Some((parse_hex(address)?, parse_hex(len)?))
+}
+
+/// The address of `Z0,address,kind` or `z0,address,kind`
+fn breakpoint_address(packet: &str) -> Option<u16> {
+    let (address, _kind) = packet.get(3..)?.split_once(',')?;
+    u16::try_from(parse_hex(address)?).ok()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+/// The part of [`TARGET_XML`] at `offset,length`, prefixed with `l` if it is the last
+fn target_xml(range: &str) -> Option<String> {
+    let (offset, len) = parse_range(range)?;
+    let rest = TARGET_XML.get(offset as usize..)?;
+    Some(match rest.get(..len as usize) {
+        Some(part) if part.len() < rest.len() => format!("m{}", part),
+        _ => format!("l{}", rest),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Client(TcpStream);
+
+    impl Client {
+        /// Send a packet and return the reply
+        fn send(&mut self, data: &str) -> String {
+            let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+            self.0.write_all(packet.as_bytes()).unwrap();
+            self.reply()
+        }
+
+        fn reply(&mut self) -> String {
+            let mut bytes = Vec::new();
+            let mut byte = [0];
+            while bytes.len() < 3 || bytes[bytes.len() - 3] != b'#' {
+                self.0.read_exact(&mut byte).unwrap();
+                if bytes.is_empty() && byte[0] != b'$' {
+                    continue;
+                }
+                bytes.push(byte[0]);
+            }
+            let reply = String::from_utf8(bytes).unwrap();
+            reply[1..reply.len() - 3].to_string()
+        }
+    }
+
+    #[test]
+    fn speaks_the_remote_serial_protocol() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let mut emulator = Emulator::new();
+            // Count V3 up from 0 in a loop at 0x202
+            emulator
+                .load_rom(&[0x63, 0x00, 0x73, 0x01, 0x12, 0x02])
+                .unwrap();
+            serve(&mut emulator, &listener).unwrap();
+            emulator.resume();
+            emulator.run_until_breakpoint(100)
+        });
+        let stream = TcpStream::connect(address).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut client = Client(stream);
+
+        assert!(client
+            .send("qSupported:xmlRegisters=i386")
+            .contains("qXfer:features:read+"));
+        assert_eq!("S05", client.send("?"));
+        // V0-VF, I, PC at 0x200 and both timers
+        let registers = format!("{}000000020000", "00".repeat(16));
+        assert_eq!(registers, client.send("g"));
+        assert!(client
+            .send("qXfer:features:read:target.xml:0,fff")
+            .starts_with("l<?xml"));
+        assert!(client
+            .send("qXfer:features:read:target.xml:0,10")
+            .starts_with('m'));
+
+        assert_eq!("OK", client.send("Z0,204,2"));
+        assert_eq!("S05", client.send("c"));
+        assert_eq!("0402", client.send("p11"));
+        assert_eq!("01", client.send("p3"));
+        assert_eq!("OK", client.send("z0,204,2"));
+        assert_eq!("E01", client.send("z0,204,2"));
+        assert_eq!("S05", client.send("s"));
+        assert_eq!("0202", client.send("p11"));
+
+        assert_eq!("63007301", client.send("m200,4"));
+        assert_eq!("OK", client.send("M300,2:abcd"));
+        assert_eq!("abcd", client.send("m300,2"));
+        assert_eq!("OK", client.send("P3=2a"));
+        assert_eq!("2a", client.send("p3"));
+        assert_eq!("E01", client.send("p20"));
+
+        // The loop runs forever, until Ctrl-C
+        client.0.write_all(b"$c#63").unwrap();
+        client.0.write_all(&[INTERRUPT]).unwrap();
+        assert_eq!("S02", client.reply());
+        // Breakpoints left over are removed when the debugger detaches
+        assert_eq!("OK", client.send("Z0,204,2"));
+        assert_eq!("OK", client.send("D"));
+        assert_eq!(StopReason::MaxTicks, server.join().unwrap());
+    }
+}
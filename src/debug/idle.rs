@@ -0,0 +1,105 @@
+use super::InstructionKind;
+
+/// The most addresses a loop can span and still count as idle
+const MAX_LOOP_LEN: usize = 4;
+/// How often a loop runs without changing anything before it counts as idle
+pub(crate) const IDLE_ITERATIONS: u32 = 8;
+
+/// Notices the program looping over a few addresses without changing anything
+#[derive(Debug, Default)]
+pub(crate) struct IdleTracker {
+    addresses: [u16; MAX_LOOP_LEN],
+    len: usize,
+    quiet_steps: u32,
+}
+
+impl IdleTracker {
+    /// Note a step of the instruction at pc, quiet if it changed nothing
+    pub(crate) fn record(&mut self, pc: u16, quiet: bool) {
+        if !quiet {
+            self.reset();
+            return;
+        }
+        if !self.addresses[..self.len].contains(&pc) {
+            if self.len == MAX_LOOP_LEN {
+                self.reset();
+            }
+            self.addresses[self.len] = pc;
+            self.len += 1;
+        }
+        self.quiet_steps = self.quiet_steps.saturating_add(1);
+    }
+
+    /// Whether the last steps went around the same few addresses
+    /// at least [`IDLE_ITERATIONS`] times
+    pub(crate) fn is_looping(&self) -> bool {
+        self.len > 0 && self.quiet_steps >= self.len as u32 * IDLE_ITERATIONS
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.len = 0;
+        self.quiet_steps = 0;
+    }
+}
+
+/// Whether the instruction can only change registers, I and the program counter,
+/// so it changes nothing if the registers and I stay the same.
+/// Reading the delay timer, keys or random numbers waits on something else
+/// and never counts.
+pub(crate) fn can_be_quiet(kind: InstructionKind) -> bool {
+    matches!(
+        kind,
+        InstructionKind::Jump
+            | InstructionKind::JumpOffset
+            | InstructionKind::SkipIfValueEqual
+            | InstructionKind::SkipIfValueNotEqual
+            | InstructionKind::SkipIfRegisterEqual
+            | InstructionKind::SkipIfRegisterNotEqual
+            | InstructionKind::Load
+            | InstructionKind::LoadI
+            | InstructionKind::LoadSpriteDigitIntoI
+            | InstructionKind::Add
+            | InstructionKind::AddRegisters
+            | InstructionKind::AddI
+            | InstructionKind::CopyRegister
+            | InstructionKind::Or
+            | InstructionKind::And
+            | InstructionKind::Xor
+            | InstructionKind::Sub
+            | InstructionKind::SubInverse
+            | InstructionKind::ShiftRight
+            | InstructionKind::ShiftLeft
+            | InstructionKind::LoadAll
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn loops_count_once_they_repeat() {
+        let mut tracker = IdleTracker::default();
+        for _ in 0..IDLE_ITERATIONS - 1 {
+            for pc in [0x200, 0x202, 0x204] {
+                tracker.record(pc, true);
+            }
+        }
+        assert!(!tracker.is_looping());
+        for pc in [0x200, 0x202, 0x204] {
+            tracker.record(pc, true);
+        }
+        assert!(tracker.is_looping());
+
+        tracker.record(0x200, false);
+        assert!(!tracker.is_looping());
+
+        // Five addresses are too many
+        for _ in 0..IDLE_ITERATIONS * 2 {
+            for pc in [0x200, 0x202, 0x204, 0x206, 0x208] {
+                tracker.record(pc, true);
+            }
+        }
+        assert!(!tracker.is_looping());
+    }
+}
@@ -0,0 +1,123 @@
+use std::io::{self, Write};
+
+use crate::{cpu::CpuSnapshot, emulator::StepInfo};
+
+/// Which registers a trace line lists, see
+/// [`crate::emulator::Emulator::set_trace_writer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraceDetail {
+    /// Only the registers the instruction changed, to keep traces small
+    #[default]
+    Changes,
+    /// Every register after every instruction
+    Registers,
+}
+
+/// Write the line of a step, with the address, opcode and mnemonic of the
+/// instruction and then the registers after it ran, e.g.
+/// `0202 A22A LD I, 0x22A  I=022A`
+pub(crate) fn write_trace_line(
+    writer: &mut dyn Write,
+    step: &StepInfo,
+    before: &CpuSnapshot,
+    after: &CpuSnapshot,
+    detail: TraceDetail,
+) -> io::Result<()> {
+    write!(
+        writer,
+        "{:04X} {:04X} {}",
+        step.pc, step.opcode, step.instruction
+    )?;
+    let all = detail == TraceDetail::Registers;
+    let mut separator = "  ";
+    let mut field = |writer: &mut dyn Write, changed: bool, args: core::fmt::Arguments| {
+        if !all && !changed {
+            return Ok(());
+        }
+        write!(writer, "{}{}", separator, args)?;
+        separator = " ";
+        Ok::<(), io::Error>(())
+    };
+    field(
+        writer,
+        before.i != after.i,
+        format_args!("I={:04X}", after.i),
+    )?;
+    for (index, (old, new)) in before.v.iter().zip(after.v).enumerate() {
+        field(
+            writer,
+            *old != new,
+            format_args!("V{:X}={:02X}", index, new),
+        )?;
+    }
+    field(
+        writer,
+        before.delay != after.delay,
+        format_args!("DT={:02X}", after.delay),
+    )?;
+    field(
+        writer,
+        before.sound != after.sound,
+        format_args!("ST={:02X}", after.sound),
+    )?;
+    writeln!(writer)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::emulator::Emulator;
+
+    /// Collects the trace where the test can still read it
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn traces_the_ibm_logo() {
+        let mut emulator = Emulator::new();
+        emulator
+            .load_rom(include_bytes!("../../roms/IBM_Logo.ch8"))
+            .unwrap();
+        let trace = SharedBuffer::default();
+        emulator.set_trace_writer(trace.clone(), TraceDetail::Changes);
+        for _ in 0..10 {
+            emulator.tick();
+        }
+        assert_eq!(include_str!("../../roms/IBM_Logo.trace"), trace.text());
+
+        emulator.clear_trace_writer();
+        emulator.tick();
+        assert_eq!(10, trace.text().lines().count());
+    }
+
+    #[test]
+    fn can_list_every_register() {
+        let mut emulator = Emulator::new();
+        emulator.load_rom(&[0x6A, 0x0C]).unwrap();
+        let trace = SharedBuffer::default();
+        emulator.set_trace_writer(trace.clone(), TraceDetail::Registers);
+        emulator.tick();
+        let registers: String = (0..16).map(|index| format!(" V{:X}=00", index)).collect();
+        let line = format!("0200 6A0C LD VA, 0x0C  I=0000{} DT=00 ST=00\n", registers)
+            .replace("VA=00", "VA=0C");
+        assert_eq!(line, trace.text());
+    }
+}
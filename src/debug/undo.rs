@@ -0,0 +1,75 @@
+#[cfg(not(feature = "std"))]
+use alloc::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::collections::VecDeque;
+
+/// The number of steps [`super::Debugger::step_back`] can undo by default
+pub const DEFAULT_UNDO_DEPTH: usize = 256;
+
+/// The most recent steps, up to a depth. Older steps are forgotten.
+pub(crate) struct UndoLog<T> {
+    steps: VecDeque<T>,
+    depth: usize,
+}
+
+impl<T> UndoLog<T> {
+    pub(crate) fn new(depth: usize) -> Self {
+        Self {
+            steps: VecDeque::new(),
+            depth,
+        }
+    }
+
+    pub(crate) fn push(&mut self, step: T) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.steps.len() == self.depth {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(step);
+    }
+
+    /// Take the most recent step
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        self.steps.pop_back()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.steps.clear();
+    }
+
+    /// Change the depth, forgetting the oldest steps beyond it
+    pub(crate) fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        let excess = self.steps.len().saturating_sub(depth);
+        self.steps.drain(..excess);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.steps.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keeps_the_most_recent_steps() {
+        let mut log = UndoLog::new(3);
+        for step in 0..5 {
+            log.push(step);
+        }
+        assert_eq!(3, log.len());
+        assert_eq!(Some(4), log.pop());
+
+        log.set_depth(1);
+        assert_eq!(Some(3), log.pop());
+        assert_eq!(None, log.pop());
+
+        log.set_depth(0);
+        log.push(5);
+        assert_eq!(0, log.len());
+    }
+}
@@ -1,8 +1,31 @@
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use core::ops::Range;
 #[cfg(feature = "std")]
 use std::fmt::Display;
 
-const DISPLAY_WIDTH: usize = 64;
-const DISPLAY_HEIGHT: usize = 32;
+#[cfg(feature = "schip")]
+use crate::config::ScrollStyle;
+use crate::memory::Fnv1a;
+
+/// The buffer always holds the pixels of the high resolution
+#[cfg(feature = "schip")]
+const BUFFER_WIDTH: usize = 128;
+#[cfg(feature = "schip")]
+const BUFFER_HEIGHT: usize = 64;
+/// Without SUPER-CHIP only the low resolution is needed
+#[cfg(not(feature = "schip"))]
+const BUFFER_WIDTH: usize = 64;
+#[cfg(not(feature = "schip"))]
+const BUFFER_HEIGHT: usize = 32;
+const ROW_LEN: usize = BUFFER_WIDTH / 8;
+const PLANE_LEN: usize = ROW_LEN * BUFFER_HEIGHT;
+/// The number of bit planes of XO-CHIP
+#[cfg(feature = "xochip")]
+const PLANES: usize = 2;
+#[cfg(not(feature = "xochip"))]
+const PLANES: usize = 1;
 const BIT_MASKS: [u8; 8] = [
     0b1000_0000,
     0b0100_0000,
@@ -14,60 +37,344 @@ const BIT_MASKS: [u8; 8] = [
     0b0000_0001,
 ];
 
+/// The resolution the display runs at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DisplayMode {
+    /// The original 64x32 pixels
+    LowRes,
+    /// The 128x64 pixels of SUPER-CHIP, entered with 00FF
+    HighRes,
+}
+
+impl DisplayMode {
+    pub const fn width(self) -> u8 {
+        match self {
+            DisplayMode::LowRes => 64,
+            DisplayMode::HighRes => 128,
+        }
+    }
+
+    pub const fn height(self) -> u8 {
+        match self {
+            DisplayMode::LowRes => 32,
+            DisplayMode::HighRes => 64,
+        }
+    }
+
+    /// The number of buffer pixels along each side of a pixel
+    const fn scale(self) -> u8 {
+        match self {
+            DisplayMode::LowRes => (BUFFER_WIDTH / 64) as u8,
+            DisplayMode::HighRes => 1,
+        }
+    }
+}
+
+/// An RGBA color
+pub type Rgba = [u8; 4];
+
+/// The host colors of the four pixel colors, indexed by
+/// [`crate::emulator::Emulator::pixel_color`]. With a single plane
+/// only the first two are used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Palette(pub [Rgba; 4]);
+
+impl Palette {
+    /// The default colors of Octo
+    pub const fn octo() -> Self {
+        Self([
+            [0x99, 0x66, 0x00, 0xFF],
+            [0xFF, 0xCC, 0x00, 0xFF],
+            [0xFF, 0x66, 0x00, 0xFF],
+            [0x66, 0x22, 0x00, 0xFF],
+        ])
+    }
+
+    /// The host color of a pixel color, only its lowest two bits count
+    pub const fn color(&self, index: u8) -> Rgba {
+        self.0[(index & 0b11) as usize]
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::octo()
+    }
+}
+
+/// Rows of the buffer on every plane and the display settings,
+/// saved to undo an instruction
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DisplayRows {
+    rows: Range<usize>,
+    bytes: Vec<u8>,
+    selected: u8,
+    mode: DisplayMode,
+}
+
 pub(crate) struct DisplayBuffer {
-    /// Display is 64x32 pixels
+    /// The display is 128x64 pixels in high resolution. In low resolution
+    /// every pixel covers 2x2 of them, so the picture survives switching
+    /// modes, like on the HP-48.
     /// A pixel is either on or off,
-    /// meaning we can store 8 pixels in 1 byte
-    buffer: [u8; 256],
+    /// meaning we can store 8 pixels in 1 byte.
+    /// XO-CHIP draws on two planes, which combine into four colors.
+    planes: [[u8; PLANE_LEN]; PLANES],
+    /// The bitmask of the planes drawing, clearing and scrolling act on
+    selected: u8,
+    mode: DisplayMode,
 }
 
 impl DisplayBuffer {
     pub fn new() -> Self {
-        Self { buffer: [0; 256] }
+        Self {
+            planes: [[0; PLANE_LEN]; PLANES],
+            selected: 1,
+            mode: DisplayMode::LowRes,
+        }
+    }
+
+    pub fn mode(&self) -> DisplayMode {
+        self.mode
+    }
+
+    /// Switch the resolution, keeping the pixels
+    #[cfg(feature = "schip")]
+    pub(crate) fn set_mode(&mut self, mode: DisplayMode) {
+        self.mode = mode;
     }
 
-    fn pos_to_index(x: u8, y: u8) -> Option<usize> {
-        if y >= DISPLAY_HEIGHT as u8 || x >= DISPLAY_WIDTH as u8 {
-            None
-        } else {
-            Some(y as usize * DISPLAY_WIDTH / 8 + x as usize / 8)
+    /// The bitmask of the selected planes, with the first plane as the lowest bit
+    pub(crate) fn selected_planes(&self) -> u8 {
+        self.selected
+    }
+
+    /// Select the planes by a bitmask. Only the lowest two bits are used.
+    #[cfg(feature = "xochip")]
+    pub(crate) fn select_planes(&mut self, mask: u8) {
+        self.selected = mask & ((1 << PLANES) - 1);
+    }
+
+    /// The indices of the planes in the bitmask
+    pub(crate) fn planes_in(mask: u8) -> impl Iterator<Item = usize> {
+        (0..PLANES).filter(move |plane| mask & (1 << plane) != 0)
+    }
+
+    /// The index of the byte and the mask of the bit of a buffer pixel
+    fn buffer_index(x: usize, y: usize) -> (usize, u8) {
+        (y * BUFFER_WIDTH / 8 + x / 8, BIT_MASKS[x % 8])
+    }
+
+    /// The buffer pixels covered by the pixel at the given x and y positions
+    /// of the active mode, or `None` if the position is off screen
+    fn covered(&self, x: u8, y: u8) -> Option<impl Iterator<Item = (usize, u8)>> {
+        if x >= self.mode.width() || y >= self.mode.height() {
+            return None;
         }
+        let scale = self.mode.scale() as usize;
+        let (x, y) = (x as usize * scale, y as usize * scale);
+        Some(
+            (y..y + scale).flat_map(move |y| (x..x + scale).map(move |x| Self::buffer_index(x, y))),
+        )
     }
 
-    /// Flip the value of the pixel at the given x and y positions.
+    /// Flip the value of the pixel at the given x and y positions on a plane.
     /// If the pixel is turned off in the process, this function will return true.
-    pub(crate) fn flip_pixel(&mut self, x: u8, y: u8) -> bool {
-        if let Some(index) = Self::pos_to_index(x, y) {
-            let sub_index = (x % 8) as usize;
-            let pixel_byte = &mut self.buffer[index];
-            let is_turned_off = *pixel_byte & BIT_MASKS[sub_index] != 0;
-            *pixel_byte ^= BIT_MASKS[sub_index];
-            is_turned_off
-        } else {
-            false
+    pub(crate) fn flip_pixel(&mut self, plane: usize, x: u8, y: u8) -> bool {
+        let Some(covered) = self.covered(x, y) else {
+            return false;
+        };
+        let buffer = &mut self.planes[plane];
+        let mut is_turned_off = false;
+        for (index, mask) in covered {
+            is_turned_off |= buffer[index] & mask != 0;
+            buffer[index] ^= mask;
         }
+        is_turned_off
     }
 
+    /// Whether the pixel is on in any plane
     pub fn is_pixel_on(&self, x: u8, y: u8) -> bool {
-        if let Some(index) = Self::pos_to_index(x, y) {
-            let sub_index = (x % 8) as usize;
-            let pixel_byte = self.buffer[index];
-            pixel_byte & BIT_MASKS[sub_index] != 0
-        } else {
-            false
+        self.pixel_color(x, y) != 0
+    }
+
+    /// The color of the pixel from 0 to 3, with one bit per plane
+    pub(crate) fn pixel_color(&self, x: u8, y: u8) -> u8 {
+        let Some(covered) = self.covered(x, y) else {
+            return 0;
+        };
+        let mut color = 0;
+        for (index, mask) in covered {
+            for (plane, buffer) in self.planes.iter().enumerate() {
+                if buffer[index] & mask != 0 {
+                    color |= 1 << plane;
+                }
+            }
         }
+        color
     }
 
+    /// Write the pixels of the active mode as RGBA, scaled up by repeating them,
+    /// looking up their colors in the palette.
+    /// Returns the number of bytes written, which stops at the end of the buffer.
+    pub(crate) fn render_rgba_into(
+        &self,
+        buffer: &mut [u8],
+        scale: usize,
+        palette: &Palette,
+    ) -> usize {
+        let width = self.mode.width() as usize * scale;
+        let height = self.mode.height() as usize * scale;
+        let colors = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x / scale, y / scale)))
+            .map(|(x, y)| palette.color(self.pixel_color(x as u8, y as u8)));
+        let mut written = 0;
+        for (pixel, color) in buffer.chunks_exact_mut(4).zip(colors) {
+            pixel.copy_from_slice(&color);
+            written += 4;
+        }
+        written
+    }
+
+    /// Save the rows of pixels in the current resolution, clamped to the
+    /// screen, together with the mode and the selected planes
+    #[cfg(feature = "alloc")]
+    pub(crate) fn save_rows(&self, rows: Range<u8>) -> DisplayRows {
+        let scale = self.mode.scale() as usize;
+        let start = (rows.start as usize * scale).min(BUFFER_HEIGHT);
+        let end = (rows.end as usize * scale).clamp(start, BUFFER_HEIGHT);
+        let bytes = self
+            .planes
+            .iter()
+            .flat_map(|plane| &plane[start * ROW_LEN..end * ROW_LEN])
+            .copied()
+            .collect();
+        DisplayRows {
+            rows: start..end,
+            bytes,
+            selected: self.selected,
+            mode: self.mode,
+        }
+    }
+
+    /// Save every row, see [`DisplayBuffer::save_rows`]
+    #[cfg(feature = "alloc")]
+    pub(crate) fn save_all(&self) -> DisplayRows {
+        self.save_rows(0..self.mode.height())
+    }
+
+    #[cfg(feature = "alloc")]
+    pub(crate) fn restore_rows(&mut self, saved: &DisplayRows) {
+        self.selected = saved.selected;
+        self.mode = saved.mode;
+        let range = saved.rows.start * ROW_LEN..saved.rows.end * ROW_LEN;
+        if range.is_empty() {
+            return;
+        }
+        for (plane, bytes) in self.planes.iter_mut().zip(saved.bytes.chunks(range.len())) {
+            plane[range.clone()].copy_from_slice(bytes);
+        }
+    }
+
+    /// Clear the selected planes
     pub(crate) fn clear(&mut self) {
-        self.buffer.fill(0);
+        for plane in Self::planes_in(self.selected) {
+            self.planes[plane].fill(0);
+        }
+    }
+
+    /// Clear every plane, whichever are selected
+    #[cfg(feature = "schip")]
+    pub(crate) fn clear_all(&mut self) {
+        for buffer in &mut self.planes {
+            buffer.fill(0);
+        }
+    }
+
+    /// How many pixels of the buffer a scroll by one pixel moves
+    #[cfg(feature = "schip")]
+    fn scroll_scale(&self, style: ScrollStyle) -> u8 {
+        match style {
+            ScrollStyle::HalfPixel => 1,
+            ScrollStyle::FullPixel => self.mode.scale(),
+        }
+    }
+
+    /// Move the picture of the selected planes down by n pixels of the
+    /// active mode. The rows at the top are cleared.
+    #[cfg(feature = "schip")]
+    pub(crate) fn scroll_down(&mut self, n: u8, style: ScrollStyle) {
+        let rows = (n as usize * self.scroll_scale(style) as usize).min(BUFFER_HEIGHT);
+        let row_bytes = BUFFER_WIDTH / 8;
+        for plane in Self::planes_in(self.selected) {
+            let buffer = &mut self.planes[plane];
+            buffer.copy_within(..(BUFFER_HEIGHT - rows) * row_bytes, rows * row_bytes);
+            buffer[..rows * row_bytes].fill(0);
+        }
+    }
+
+    /// Move the picture of the selected planes up by n pixels of the
+    /// active mode. The rows at the bottom are cleared.
+    #[cfg(feature = "xochip")]
+    pub(crate) fn scroll_up(&mut self, n: u8, style: ScrollStyle) {
+        let rows = (n as usize * self.scroll_scale(style) as usize).min(BUFFER_HEIGHT);
+        let row_bytes = BUFFER_WIDTH / 8;
+        for plane in Self::planes_in(self.selected) {
+            let buffer = &mut self.planes[plane];
+            buffer.copy_within(rows * row_bytes.., 0);
+            buffer[(BUFFER_HEIGHT - rows) * row_bytes..].fill(0);
+        }
+    }
+
+    /// Move the picture of the selected planes left by 4 pixels of the
+    /// active mode. The columns at the right are cleared.
+    #[cfg(feature = "schip")]
+    pub(crate) fn scroll_left4(&mut self, style: ScrollStyle) {
+        let pixels = 4 * self.scroll_scale(style) as u32;
+        self.map_rows(|row| row << pixels);
+    }
+
+    /// Move the picture of the selected planes right by 4 pixels of the
+    /// active mode. The columns at the left are cleared.
+    #[cfg(feature = "schip")]
+    pub(crate) fn scroll_right4(&mut self, style: ScrollStyle) {
+        let pixels = 4 * self.scroll_scale(style) as u32;
+        self.map_rows(|row| row >> pixels);
+    }
+
+    /// Replace every row of the selected planes, with its leftmost pixel as the highest bit
+    #[cfg(feature = "schip")]
+    fn map_rows(&mut self, f: impl Fn(u128) -> u128) {
+        for plane in Self::planes_in(self.selected) {
+            for chunk in self.planes[plane].chunks_exact_mut(BUFFER_WIDTH / 8) {
+                let mut row = [0; BUFFER_WIDTH / 8];
+                row.copy_from_slice(chunk);
+                chunk.copy_from_slice(&f(u128::from_be_bytes(row)).to_be_bytes());
+            }
+        }
+    }
+
+    /// The 64-bit FNV-1a hash over all pixels and the mode
+    pub(crate) fn checksum(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        for buffer in &self.planes {
+            hasher.write(buffer);
+        }
+        // Without XO-CHIP the second plane is always empty
+        #[cfg(not(feature = "xochip"))]
+        hasher.write(&[0; PLANE_LEN]);
+        hasher.write(&[self.mode as u8]);
+        hasher.finish()
     }
 }
 
+/// Prints the pixels of the active mode
 #[cfg(feature = "std")]
 impl Display for DisplayBuffer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for row in 0..DISPLAY_HEIGHT as u8 {
-            for col in 0..DISPLAY_WIDTH as u8 {
+        for row in 0..self.mode.height() {
+            for col in 0..self.mode.width() {
                 let symbol = if self.is_pixel_on(col, row) {
                     '◼'
                 } else {
@@ -75,7 +382,28 @@ impl Display for DisplayBuffer {
                 };
                 write!(f, "{}", symbol)?;
             }
-            write!(f, "\n");
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl DisplayBuffer {
+    /// Write the pixels of the active mode with one half block character
+    /// for every two rows, so the picture keeps its proportions in a terminal
+    #[cfg(feature = "std")]
+    pub(crate) fn write_half_blocks(&self, f: &mut impl core::fmt::Write) -> core::fmt::Result {
+        for row in (0..self.mode.height()).step_by(2) {
+            for col in 0..self.mode.width() {
+                let symbol = match (self.is_pixel_on(col, row), self.is_pixel_on(col, row + 1)) {
+                    (false, false) => ' ',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (true, true) => '█',
+                };
+                f.write_char(symbol)?;
+            }
+            writeln!(f)?;
         }
         Ok(())
     }
@@ -90,7 +418,7 @@ mod test {
         let mut display = DisplayBuffer::new();
         for x in 0..8 {
             assert!(!display.is_pixel_on(x, 0));
-            assert!(!display.flip_pixel(x, 0));
+            assert!(!display.flip_pixel(0, x, 0));
             assert!(display.is_pixel_on(x, 0));
         }
     }
@@ -100,9 +428,9 @@ mod test {
         let mut display = DisplayBuffer::new();
         for x in 0..8 {
             assert!(!display.is_pixel_on(x, 0));
-            assert!(!display.flip_pixel(x, 0));
+            assert!(!display.flip_pixel(0, x, 0));
             assert!(display.is_pixel_on(x, 0));
-            assert!(display.flip_pixel(x, 0));
+            assert!(display.flip_pixel(0, x, 0));
             assert!(!display.is_pixel_on(x, 0));
         }
     }
@@ -110,6 +438,114 @@ mod test {
     #[test]
     fn cannot_access_oob() {
         let mut display = DisplayBuffer::new();
-        display.flip_pixel(255, 255);
+        display.flip_pixel(0, 255, 255);
+        #[cfg(feature = "schip")]
+        display.set_mode(DisplayMode::HighRes);
+        display.flip_pixel(0, 255, 255);
+    }
+
+    /// The positions of all pixels that are on, in the active mode
+    #[cfg(feature = "schip")]
+    fn pixels_on(display: &DisplayBuffer) -> Vec<(u8, u8)> {
+        let mode = display.mode();
+        (0..mode.height())
+            .flat_map(|y| (0..mode.width()).map(move |x| (x, y)))
+            .filter(|(x, y)| display.is_pixel_on(*x, *y))
+            .collect()
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn can_scroll_in_each_direction() {
+        for mode in [DisplayMode::LowRes, DisplayMode::HighRes] {
+            let right = mode.width() - 1;
+            let bottom = mode.height() - 1;
+            let mut display = DisplayBuffer::new();
+            display.set_mode(mode);
+            for (x, y) in [(0, 0), (5, 1), (right, 2), (2, bottom)] {
+                display.flip_pixel(0, x, y);
+            }
+
+            display.scroll_down(2, ScrollStyle::FullPixel);
+            assert_eq!(vec![(0, 2), (5, 3), (right, 4)], pixels_on(&display));
+
+            display.scroll_right4(ScrollStyle::FullPixel);
+            assert_eq!(vec![(4, 2), (9, 3)], pixels_on(&display));
+
+            display.scroll_left4(ScrollStyle::FullPixel);
+            display.scroll_left4(ScrollStyle::FullPixel);
+            assert_eq!(vec![(1, 3)], pixels_on(&display));
+
+            display.scroll_down(bottom - 3, ScrollStyle::FullPixel);
+            assert_eq!(vec![(1, bottom)], pixels_on(&display));
+            display.scroll_up(bottom, ScrollStyle::FullPixel);
+            assert_eq!(vec![(1, 0)], pixels_on(&display));
+            display.scroll_up(1, ScrollStyle::FullPixel);
+            assert!(pixels_on(&display).is_empty());
+        }
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn scrolling_past_the_edge_clears() {
+        let mut display = DisplayBuffer::new();
+        display.flip_pixel(0, 10, 10);
+        display.scroll_down(0xF, ScrollStyle::FullPixel);
+        display.scroll_down(0xF, ScrollStyle::FullPixel);
+        display.scroll_down(0xF, ScrollStyle::FullPixel);
+        assert!(pixels_on(&display).is_empty());
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn lores_can_scroll_by_half_pixels() {
+        let mut display = DisplayBuffer::new();
+        display.flip_pixel(0, 0, 0);
+        display.scroll_down(1, ScrollStyle::HalfPixel);
+        assert_eq!(vec![(0, 0), (0, 1)], pixels_on(&display));
+
+        display.set_mode(DisplayMode::HighRes);
+        assert_eq!(vec![(0, 1), (1, 1), (0, 2), (1, 2)], pixels_on(&display));
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn modes_share_the_picture() {
+        let mut display = DisplayBuffer::new();
+        display.flip_pixel(0, 63, 31);
+        assert!(!display.flip_pixel(0, 64, 0));
+        assert!(!display.is_pixel_on(64, 0));
+
+        display.set_mode(DisplayMode::HighRes);
+        for (x, y) in [(126, 62), (127, 62), (126, 63), (127, 63)] {
+            assert!(display.is_pixel_on(x, y));
+        }
+        assert!(!display.is_pixel_on(125, 62));
+        assert!(!display.flip_pixel(0, 100, 40));
+
+        display.set_mode(DisplayMode::LowRes);
+        assert!(display.is_pixel_on(50, 20));
+        assert!(display.flip_pixel(0, 50, 20));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn saved_rows_can_be_restored() {
+        let mut display = DisplayBuffer::new();
+        display.flip_pixel(0, 3, 4);
+        let saved = display.save_rows(4..6);
+        display.flip_pixel(0, 3, 4);
+        display.flip_pixel(0, 7, 5);
+        display.flip_pixel(0, 7, 10);
+        display.restore_rows(&saved);
+        assert!(display.is_pixel_on(3, 4));
+        assert!(!display.is_pixel_on(7, 5));
+        assert!(display.is_pixel_on(7, 10));
+
+        let saved = display.save_all();
+        display.clear();
+        display.restore_rows(&saved);
+        assert!(display.is_pixel_on(3, 4));
+        assert!(display.is_pixel_on(7, 10));
     }
 }
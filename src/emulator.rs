@@ -1,89 +1,472 @@
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{ops::Range, time::Duration};
+
+#[cfg(feature = "std")]
+use crate::debug::{write_trace_line, TraceDetail};
+#[cfg(feature = "alloc")]
+use crate::{
+    audio::SoundSink,
+    debug::{
+        AccessKind, AccessLog, BreakCondition, BreakpointId, Breakpoints, MemoryAccess, StopReason,
+        UndoLog, WatchMode,
+    },
+    display::DisplayRows,
+    io::{
+        flags::FlagStore,
+        recording::{InputEntry, InputRecording},
+    },
+    memory::{MemorySnapshot, MemoryWindow},
+};
 use crate::{
+    audio::{self, PatternWave, SquareWave, DEFAULT_PITCH},
     command::Command,
-    config::{DumpLoadStyle, EmulatorConfiguration, JumpOffsetStyle, ShiftStyle},
-    cpu::Cpu,
-    display::DisplayBuffer,
-    io::{keyboard::Keyboard, timer::Timer},
-    memory::{Memory, Stack, CHIP8_START, MEMORY_SIZE},
-    opcode::OpCode,
+    config::{
+        CollisionStyle, DumpLoadStyle, EmulatorConfiguration, InvalidOpcodeStyle, JumpOffsetStyle,
+        KeyInputStyle, LoresSpriteStyle, PcOverflowStyle, Platform, ShiftStyle, TimerStyle,
+        WaitKeyStyle, LARGE_FONT_OFFSET,
+    },
+    cpu::{Cpu, CpuSnapshot, Register},
+    debug::{can_be_quiet, IdleTracker, Instruction, InstructionKind},
+    display::{DisplayBuffer, DisplayMode, Palette},
+    error::{Chip8Error, ConfigError, DecodeError, InvalidAddress, RomError},
+    io::{
+        clock::{Clock, DefaultClock, ManualClock},
+        flags::MAX_FLAGS,
+        keyboard::{KeyEvent, KeySource, Keyboard},
+        keymap::KeyMap,
+        timer::{self, Timer},
+    },
+    memory::{Fnv1a, Memory, Stack},
 };
 
-/// The main emulator
-pub struct Emulator {
+/// The number of key taps that can be held at the same time
+const MAX_KEY_TAPS: usize = 16;
+/// The number of sound events kept until they are taken.
+/// Once full, the oldest event is dropped.
+#[cfg(feature = "alloc")]
+const MAX_SOUND_EVENTS: usize = 32;
+
+#[rustfmt::skip]
+const FONT_SPRITES: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/// The SUPER-CHIP digits, 8x10 pixels each,
+/// with the hexadecimal letters of XO-CHIP added
+#[rustfmt::skip]
+const LARGE_FONT_SPRITES: [u8; 160] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+/// What happened during a successful [`Emulator::try_tick`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickOutcome {
+    /// An instruction was executed
+    Executed,
+    /// The emulator is waiting for a key press (FX0A)
+    /// and will retry the instruction on the next tick
+    WaitingForKey,
+    /// The emulator is paused and did nothing
+    Paused,
+    /// The rom exited with 00FD, now or on an earlier tick
+    Exited,
+}
+
+/// Why the emulator stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HaltReason {
+    /// An error stopped the emulator
+    Fault(Chip8Error),
+    /// The rom exited with the SUPER-CHIP instruction 00FD
+    Exited,
+}
+
+/// The buzzer turning on or off, with the [`Emulator::tick_count`]
+/// of the tick that caused it. The time on the emulator's clock is exact
+/// even if the sound timer ran out in between two ticks, so audio
+/// backends can cut off the beep at the right sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundEvent {
+    Started { tick: u64, millis: u64 },
+    Stopped { tick: u64, millis: u64 },
+}
+
+impl SoundEvent {
+    /// The [`Emulator::tick_count`] of the tick that caused the event
+    #[cfg(feature = "alloc")]
+    pub(crate) fn tick(&self) -> u64 {
+        match self {
+            SoundEvent::Started { tick, .. } | SoundEvent::Stopped { tick, .. } => *tick,
+        }
+    }
+}
+
+/// What the last DXYN drew, for tools that want more than VF tells.
+/// Plane masks have bit 0 for the first plane and bit 1 for the second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrawInfo {
+    /// The position the sprite was drawn at, after wrapping
+    pub x: u8,
+    pub y: u8,
+    /// The planes that were drawn on
+    pub planes: u8,
+    /// The planes on which a pixel was turned off
+    pub collisions: u8,
+}
+
+/// The instruction a [`Emulator::step`] ran and what it did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepInfo {
+    /// The address of the instruction
+    pub pc: u16,
+    pub opcode: u16,
+    pub instruction: Instruction,
+    /// The program counter after the instruction ran
+    pub next_pc: u16,
+    /// A sprite was drawn
+    pub drew: bool,
+    /// A FX0A instruction has no key yet and runs again on the next step
+    pub waited_for_key: bool,
+    /// A skip instruction skipped the next instruction
+    pub skipped: bool,
+}
+
+/// What a step can change, saved before it runs so it can be undone.
+/// Memory, display, rng, flags and audio are only saved
+/// for the instructions that change them. The keyboard is saved
+/// whole, so the key events FX0A takes go back to the front of the queue.
+#[cfg(feature = "alloc")]
+pub(crate) struct UndoStep {
+    cpu: Cpu,
+    stack: (usize, u16),
+    /// The address and the bytes FX33, FX55 or 5XY2 write there
+    memory: Option<(u16, u8, [u8; 16])>,
+    display: Option<DisplayRows>,
+    rng: Option<oorandom::Rand32>,
+    flags: Option<[u8; MAX_FLAGS]>,
+    audio: Option<(Option<[u8; 16]>, u8)>,
+    tick_count: u64,
+    instructions_since_timer_step: u32,
+    mode_switches: u64,
+    state: RunState,
+    last_fault: Option<Chip8Error>,
+    last_draw: Option<DrawInfo>,
+    keyboard: Keyboard,
+    key_taps: [Option<(u8, u32)>; MAX_KEY_TAPS],
+    source_keys: u16,
+    timer: Timer,
+    is_sound_on: bool,
+}
+
+/// The lifecycle of the emulator. It starts out running, waits while
+/// a FX0A instruction has no key to read, and halts on errors until
+/// a new rom gets loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RunState {
+    Running,
+    /// With [`WaitKeyStyle::OnRelease`] the key that went down
+    /// is remembered until it gets released
+    WaitingForKey {
+        latched: Option<u8>,
+    },
+    Halted(HaltReason),
+}
+
+/// The main emulator. The clock drives the delay and sound timers.
+pub struct Emulator<C: Clock = DefaultClock> {
     pub configuration: EmulatorConfiguration,
     pub(crate) cpu: Cpu,
     pub(crate) memory: Memory,
+    font: [u8; 80],
     pub(crate) stack: Stack,
     pub(crate) display: DisplayBuffer,
+    /// The number of resolution switches, for hosts to notice them
+    mode_switches: u64,
     pub(crate) keyboard: Keyboard,
-    pub(crate) delay_timer: Timer,
-    pub(crate) sound_timer: Timer,
+    key_map: KeyMap,
+    /// Replaces the keyboard for reading key states, if set
+    #[cfg(feature = "alloc")]
+    key_source: Option<Box<dyn KeySource + Send>>,
+    /// The keys the key source reported at its last poll, to queue its changes as events
+    #[cfg(feature = "alloc")]
+    source_keys: u16,
+    /// Keys pressed by [`Emulator::tap_key`], with the ticks left until release
+    key_taps: [Option<(u8, u32)>; MAX_KEY_TAPS],
+    /// The recorded input, with the tick count the recording started at
+    #[cfg(feature = "alloc")]
+    recording: Option<(u64, InputRecording)>,
+    /// The number of ticks run, not counting ticks while halted or paused
+    tick_count: u64,
+    /// Whether the buzzer was on when last checked, to detect changes
+    is_sound_on: bool,
+    #[cfg(feature = "alloc")]
+    sound_events: Vec<SoundEvent>,
+    /// Notified about every change of the buzzer, if set
+    #[cfg(feature = "alloc")]
+    sound_sink: Option<Box<dyn SoundSink + Send>>,
+    /// The RPL user flags of FX75 and FX85, which survive loading a rom
+    flags: [u8; MAX_FLAGS],
+    /// Persists the flags, if set
+    #[cfg(feature = "alloc")]
+    flag_store: Option<Box<dyn FlagStore + Send>>,
+    /// Silences the buzzer without touching the sound register
+    muted: bool,
+    buzzer: SquareWave,
+    /// The XO-CHIP audio pattern, which replaces the buzzer once loaded
+    audio_pattern: Option<[u8; 16]>,
+    pitch: u8,
+    pattern_wave: PatternWave,
+    clock: C,
+    /// The 60 Hz timer shared by the delay and sound registers
+    pub(crate) timer: Timer,
+    instructions_since_timer_step: u32,
     rng: oorandom::Rand32,
-    register_awaiting_input: Option<u8>,
+    state: RunState,
+    paused: bool,
+    last_fault: Option<Chip8Error>,
+    last_draw: Option<DrawInfo>,
+    /// Kept when loading a rom, so they can be set up before
+    #[cfg(feature = "alloc")]
+    breakpoints: Breakpoints,
+    /// The steps to undo, if they are saved
+    #[cfg(feature = "alloc")]
+    undo: Option<UndoLog<UndoStep>>,
+    /// Gets a line for every step, if set
+    #[cfg(feature = "std")]
+    trace: Option<(Box<dyn std::io::Write + Send>, TraceDetail)>,
+    /// The first profiled address and how often every word from it on was executed
+    #[cfg(feature = "alloc")]
+    profile: Option<(u16, Vec<u32>)>,
+    /// Gets the memory accesses of instructions, if set
+    #[cfg(feature = "alloc")]
+    access_log: Option<AccessLog>,
+    /// Notices loops that change nothing, for [`Emulator::is_spinning`]
+    idle: IdleTracker,
 }
 
 impl Emulator {
+    /// Create an emulator running on the platform's default clock
     pub fn new() -> Self {
-        let mut memory = Memory::new();
-        Self::load_font_sprites(&mut memory);
-        Self {
-            configuration: EmulatorConfiguration::default(),
-            cpu: Cpu::new(),
-            memory,
-            stack: Stack::new(),
+        Self::with_clock(DefaultClock::new())
+    }
+}
+
+impl<C: Clock> Emulator<C> {
+    /// Create an emulator whose timers run on the given clock
+    pub fn with_clock(clock: C) -> Self {
+        let configuration = EmulatorConfiguration::default();
+        debug_assert!(configuration.validate().is_ok());
+        let mut emulator = Self {
+            cpu: Cpu::new(configuration.start_address),
+            stack: Stack::new(configuration.stack_depth),
+            configuration,
+            memory: Memory::with_size(configuration.memory_size),
+            font: FONT_SPRITES,
             display: DisplayBuffer::new(),
+            mode_switches: 0,
             keyboard: Keyboard::new(),
-            delay_timer: Timer::new(),
-            sound_timer: Timer::new(),
+            key_map: KeyMap::default(),
+            #[cfg(feature = "alloc")]
+            key_source: None,
+            #[cfg(feature = "alloc")]
+            source_keys: 0,
+            key_taps: [None; MAX_KEY_TAPS],
+            #[cfg(feature = "alloc")]
+            recording: None,
+            tick_count: 0,
+            is_sound_on: false,
+            #[cfg(feature = "alloc")]
+            sound_events: Vec::new(),
+            #[cfg(feature = "alloc")]
+            sound_sink: None,
+            flags: [0; MAX_FLAGS],
+            #[cfg(feature = "alloc")]
+            flag_store: None,
+            muted: false,
+            buzzer: SquareWave::default(),
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            pattern_wave: PatternWave::default(),
+            timer: Timer::new(&clock),
+            clock,
+            instructions_since_timer_step: 0,
             rng: oorandom::Rand32::new(42),
-            register_awaiting_input: None,
-        }
+            state: RunState::Running,
+            paused: false,
+            last_fault: None,
+            last_draw: None,
+            #[cfg(feature = "alloc")]
+            breakpoints: Breakpoints::default(),
+            #[cfg(feature = "alloc")]
+            undo: None,
+            #[cfg(feature = "std")]
+            trace: None,
+            #[cfg(feature = "alloc")]
+            profile: None,
+            #[cfg(feature = "alloc")]
+            access_log: None,
+            idle: IdleTracker::default(),
+        };
+        emulator.load_font_sprites();
+        emulator
     }
 
-    pub fn with_rom(mut self, rom: &[u8]) -> Self {
-        self.memory.copy_from_slice(CHIP8_START as u16, rom);
-        self
+    pub fn clock(&self) -> &C {
+        &self.clock
     }
 
-    pub fn load_rom(&mut self, rom: &[u8]) {
-        self.cpu = Cpu::new();
-        self.memory.clear_public();
-        self.stack = Stack::new();
-        self.display.clear();
-        self.register_awaiting_input = None;
-        self.memory.copy_from_slice(CHIP8_START as u16, rom);
+    /// Mutable access to the clock, e.g. to advance a [`crate::ManualClock`]
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
     }
 
-    pub fn load_test_rom(&mut self) {
-        self.load_rom(include_bytes!("../roms/test_opcode.ch8"))
+    /// Replace the configuration, if it is valid. The stack, program counter
+    /// and font are set up for it, as they would be by loading a rom.
+    pub fn with_configuration(
+        mut self,
+        configuration: EmulatorConfiguration,
+    ) -> Result<Self, ConfigError> {
+        configuration.validate()?;
+        self.configuration = configuration;
+        self.cpu = Cpu::new(configuration.start_address);
+        self.stack = Stack::new(configuration.stack_depth);
+        self.memory.resize(configuration.memory_size);
+        self.load_font_sprites();
+        Ok(self)
     }
 
-    fn load_font_sprites(memory: &mut Memory) {
-        memory.copy_from_slice(
-            0x050,
-            &[
-                0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-                0x20, 0x60, 0x20, 0x20, 0x70, // 1
-                0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-                0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-                0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-                0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-                0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-                0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-                0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-                0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-                0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-                0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-                0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-                0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-                0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-                0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-            ],
+    pub fn with_rom(mut self, rom: &[u8]) -> Result<Self, RomError> {
+        self.load_rom(rom)?;
+        Ok(self)
+    }
+
+    /// Reset the emulator and load the given rom at the
+    /// configured [`EmulatorConfiguration::start_address`].
+    /// If the rom is empty or does not fit into memory,
+    /// the emulator is left untouched.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), RomError> {
+        let start_address = self.configuration.start_address;
+        let max = self
+            .configuration
+            .memory_size
+            .saturating_sub(start_address as usize);
+        if rom.is_empty() {
+            return Err(RomError::Empty);
+        }
+        if rom.len() > max {
+            return Err(RomError::TooLarge {
+                len: rom.len(),
+                max,
+            });
+        }
+
+        self.cpu = Cpu::new(start_address);
+        self.memory.resize(self.configuration.memory_size);
+        self.memory.clear_public(start_address);
+        self.load_font_sprites();
+        self.stack = Stack::new(self.configuration.stack_depth);
+        if self.display.mode() != DisplayMode::LowRes {
+            self.mode_switches += 1;
+        }
+        self.display = DisplayBuffer::new();
+        self.state = RunState::Running;
+        self.last_fault = None;
+        self.last_draw = None;
+        self.instructions_since_timer_step = 0;
+        self.update_sound_state();
+        self.audio_pattern = None;
+        self.pitch = DEFAULT_PITCH;
+        #[cfg(feature = "alloc")]
+        if let Some(undo) = &mut self.undo {
+            undo.clear();
+        }
+        #[cfg(feature = "alloc")]
+        if let Some((_, counters)) = &mut self.profile {
+            counters.fill(0);
+        }
+        self.idle.reset();
+        self.keyboard.clear_events();
+        let copied = self.memory.try_copy_from_slice(start_address, rom);
+        debug_assert!(
+            copied,
+            "Rom size was validated against the available memory"
         );
+        Ok(())
+    }
+
+    pub fn load_test_rom(&mut self) -> Result<(), RomError> {
+        self.load_rom(include_bytes!("../roms/test_opcode.ch8"))
+    }
+
+    /// Replace the font sprites used by FX29 with a custom font,
+    /// e.g. one of the alternative fonts used by other interpreters.
+    /// The font consists of 16 glyphs of 5 bytes each and is stored
+    /// at the configured [`EmulatorConfiguration::font_base`].
+    pub fn load_font(&mut self, font: &[u8; 80]) {
+        self.font = *font;
+        self.load_font_sprites();
+    }
+
+    fn load_font_sprites(&mut self) {
+        let font_base = self.configuration.font_base;
+        if !self.memory.try_copy_from_slice(font_base, &self.font) {
+            log_warn!(
+                "Font base {:#05X} leaves no room for the font sprites",
+                font_base
+            );
+        }
+        if !self.configuration.platform.has_super_chip() {
+            return;
+        }
+        let large_font_base = font_base.saturating_add(LARGE_FONT_OFFSET);
+        if !self
+            .memory
+            .try_copy_from_slice(large_font_base, &LARGE_FONT_SPRITES)
+        {
+            log_warn!(
+                "Font base {:#05X} leaves no room for the large font sprites",
+                font_base
+            );
+        }
     }
 
-    fn font_sprite_address(character: u8) -> u16 {
-        0x050 + character as u16 * 5
+    /// The address of the glyph for the given character.
+    /// Only the lower nibble of the character is used.
+    fn font_sprite_address(&self, character: u8) -> u16 {
+        self.configuration
+            .font_base
+            .wrapping_add((character & 0x0F) as u16 * 5)
     }
 
     /// Perform a single, atomic tick of the emulator.
@@ -91,57 +474,299 @@ impl Emulator {
     /// - Load
     /// - Decode
     /// - Execute
+    ///
+    /// Errors are logged and stop the emulator, after which ticking does nothing.
+    /// Use [`Emulator::try_tick`] to handle them directly.
+    ///
+    /// Ticking never panics, whatever rom is loaded. All memory, stack and
+    /// register accesses are checked, so a misbehaving rom can only halt the emulator.
     pub fn tick(&mut self) {
-        self.update_delay_register();
-        self.update_sound_register();
+        let _ = self.step();
+    }
+
+    /// Run one 60 Hz frame of [`EmulatorConfiguration::instructions_per_frame`]
+    /// ticks. With [`TimerStyle::HostDriven`] the timers step once at the
+    /// end of the frame, other timer styles run the timers while ticking.
+    /// Stops early with the error once the emulator halts.
+    pub fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        for _ in 0..self.configuration.instructions_per_frame {
+            self.try_tick()?;
+        }
+        self.end_frame();
+        Ok(())
+    }
+
+    /// Run one frame like [`Emulator::run_frame`] and render the buzzer for
+    /// exactly that frame, usually `sample_rate / 60` samples. The samples are
+    /// split evenly between the ticks, so a beep starting or stopping in the
+    /// middle of the frame does so in the samples, too.
+    /// Once the emulator halts, the rest of the frame is silent.
+    pub fn run_frame_with_audio(
+        &mut self,
+        samples: &mut [f32],
+        sample_rate: u32,
+    ) -> Result<(), Chip8Error> {
+        self.update_buzzer();
+        let ticks = self.configuration.instructions_per_frame.max(1) as usize;
+        for index in 0..ticks {
+            let segment = index * samples.len() / ticks..(index + 1) * samples.len() / ticks;
+            if let Err(error) = self.try_tick() {
+                samples[segment.start..].fill(0.0);
+                return Err(error);
+            }
+            let on_samples = match self.is_audible() {
+                true => segment.len(),
+                false => 0,
+            };
+            self.render_audio(&mut samples[segment], sample_rate, on_samples);
+        }
+        self.end_frame();
+        Ok(())
+    }
+
+    fn end_frame(&mut self) {
+        if let TimerStyle::HostDriven = self.configuration.timers {
+            if !self.paused {
+                self.tick_timers();
+            }
+        }
+    }
+
+    /// Perform a single tick like [`Emulator::tick`], but report
+    /// what happened. Once an error occured, the emulator is halted and
+    /// the error is returned again for every following call, without doing anything.
+    /// The same goes for [`TickOutcome::Exited`] once the rom exited.
+    ///
+    /// Like [`Emulator::tick`], this never panics on arbitrary rom input.
+    pub fn try_tick(&mut self) -> Result<TickOutcome, Chip8Error> {
+        if let RunState::Halted(_) = &self.state {
+            return self.outcome();
+        }
+        if self.paused {
+            return Ok(TickOutcome::Paused);
+        }
+        self.step();
+        self.outcome()
+    }
+
+    /// Perform a single tick like [`Emulator::tick`] and report the
+    /// instruction it ran. Returns `None` when nothing ran, because the
+    /// emulator is halted or paused, or the program counter ran past the
+    /// end of memory. Instructions that halt the emulator are still reported.
+    pub fn step(&mut self) -> Option<StepInfo> {
+        if matches!(self.state, RunState::Halted(_)) || self.paused {
+            return None;
+        }
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("tick", pc = self.pc()).entered();
+        #[cfg(feature = "std")]
+        let before = self.trace.is_some().then(|| self.cpu_snapshot());
+        #[cfg(feature = "alloc")]
+        if self.undo.is_some() {
+            let step = self.save_undo_step();
+            if let Some(undo) = &mut self.undo {
+                undo.push(step);
+            }
+        }
+        self.tick_count += 1;
+        self.update_key_taps();
+        #[cfg(feature = "alloc")]
+        if let Some(source) = &mut self.key_source {
+            source.poll();
+        }
+        #[cfg(feature = "alloc")]
+        self.queue_source_events();
 
-        if *self.cpu.pc() >= MEMORY_SIZE as u16 - 2 {
-            *self.cpu.pc_mut() = CHIP8_START as u16;
+        if let TimerStyle::WallClock = self.configuration.timers {
+            self.update_timer_registers();
         }
 
-        // Load
-        let opcode = self.load_op();
+        // Load, which may wrap the program counter before advancing it
+        let opcode = self.load_op()?;
+        let pc = self.pc().wrapping_sub(2);
+        #[cfg(feature = "alloc")]
+        self.profile_address(pc);
 
         // Decode
-        let opcode: OpCode = opcode.into();
-        let command = opcode.into();
+        let instruction = Instruction::decode(opcode);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(target: "chip8::step", pc, opcode, mnemonic = %instruction);
+        #[cfg(not(feature = "tracing"))]
+        log_trace!(target: "chip8::step", "{:04X} {:04X} {}", pc, opcode, instruction);
 
         // Execute
-        self.execute(command);
+        let quiet_before = can_be_quiet(instruction.kind()).then(|| self.cpu_snapshot());
+        self.execute(instruction.0);
+        self.track_idle(pc, quiet_before);
+
+        if let TimerStyle::PerInstructions(instructions) = self.configuration.timers {
+            self.count_timer_instruction(instructions);
+        }
+
+        let next_pc = self.pc();
+        let step = StepInfo {
+            pc,
+            opcode,
+            instruction,
+            next_pc,
+            drew: instruction.kind() == InstructionKind::DrawSprite
+                && !matches!(self.state, RunState::Halted(_)),
+            waited_for_key: matches!(self.state, RunState::WaitingForKey { .. }),
+            skipped: instruction.is_skip() && next_pc != pc.wrapping_add(2),
+        };
+        #[cfg(feature = "std")]
+        if let Some(before) = before {
+            self.trace_step(&step, &before);
+        }
+        Some(step)
     }
 
-    fn update_delay_register(&mut self) {
-        if *self.cpu.delay() > 0 {
-            let steps = self.delay_timer.tick();
-            if steps > *self.cpu.delay() {
-                *self.cpu.delay_mut() = 0;
-            } else {
-                *self.cpu.delay_mut() -= steps;
+    #[cfg(feature = "std")]
+    fn trace_step(&mut self, step: &StepInfo, before: &CpuSnapshot) {
+        let after = self.cpu_snapshot();
+        if let Some((writer, detail)) = &mut self.trace {
+            if let Err(error) = write_trace_line(writer, step, before, &after, *detail) {
+                log_warn!("Could not write the trace, tracing stops: {}", error);
+                self.trace = None;
             }
         }
     }
 
-    fn update_sound_register(&mut self) {
-        if *self.cpu.sound() > 0 {
-            let steps = self.sound_timer.tick();
-            if steps > *self.cpu.sound() {
-                *self.cpu.sound_mut() = 0;
-            } else {
-                *self.cpu.sound_mut() -= steps;
-            }
+    fn outcome(&self) -> Result<TickOutcome, Chip8Error> {
+        match &self.state {
+            RunState::Running => Ok(TickOutcome::Executed),
+            RunState::WaitingForKey { .. } => Ok(TickOutcome::WaitingForKey),
+            RunState::Halted(HaltReason::Fault(error)) => Err(error.clone()),
+            RunState::Halted(HaltReason::Exited) => Ok(TickOutcome::Exited),
+        }
+    }
+
+    /// Step the timers once every given number of executed instructions
+    fn count_timer_instruction(&mut self, instructions: u32) {
+        self.instructions_since_timer_step += 1;
+        if self.instructions_since_timer_step >= instructions {
+            self.instructions_since_timer_step = 0;
+            self.tick_timers();
+        }
+    }
+
+    /// Run the delay and sound registers down by the steps passed on the clock.
+    /// The timer keeps its phase whatever the register values are, so
+    /// loading a register does not shift when its next step happens.
+    fn update_timer_registers(&mut self) {
+        let sound = *self.cpu.sound();
+        let sound_end = self.timer.step_millis(sound as u32);
+        let steps = self.timer.tick(&self.clock);
+        *self.cpu.delay_mut() = Self::drain(*self.cpu.delay(), steps);
+        *self.cpu.sound_mut() = Self::drain(sound, steps);
+        self.update_sound_state_at(sound_end);
+    }
+
+    /// Record a [`SoundEvent`] if the buzzer turned on or off just now
+    fn update_sound_state(&mut self) {
+        self.update_sound_state_at(self.clock.now_millis());
+    }
+
+    /// Record a [`SoundEvent`] if the buzzer turned on or off at the given time
+    #[cfg(feature = "alloc")]
+    fn update_sound_state_at(&mut self, millis: u64) {
+        let is_sound_on = self.is_sound_on();
+        if is_sound_on == self.is_sound_on {
+            return;
+        }
+        self.is_sound_on = is_sound_on;
+        if !self.muted {
+            self.notify_sound_sink(is_sound_on);
         }
+        let tick = self.tick_count;
+        if self.sound_events.len() == MAX_SOUND_EVENTS {
+            self.sound_events.remove(0);
+        }
+        self.sound_events.push(match is_sound_on {
+            true => SoundEvent::Started { tick, millis },
+            false => SoundEvent::Stopped { tick, millis },
+        });
+    }
+
+    /// Without sound events or a sink, only the buzzer state is kept
+    #[cfg(not(feature = "alloc"))]
+    fn update_sound_state_at(&mut self, _millis: u64) {
+        self.is_sound_on = self.is_sound_on();
+    }
+
+    /// Subtract the timer steps from a register value, stopping at 0
+    fn drain(value: u8, steps: u32) -> u8 {
+        (value as u32).saturating_sub(steps) as u8
     }
 
-    fn load_op(&mut self) -> u16 {
-        let opcode = self.memory.read_u16(*self.cpu.pc());
+    /// Load the instruction at the program counter and advance it.
+    /// If the program counter ran past the end of memory, it either
+    /// wraps around to the start address or faults, depending on the configuration.
+    fn load_op(&mut self) -> Option<u16> {
+        let pc = *self.cpu.pc();
+        let memory_size = self.memory.len();
+        if pc as usize > memory_size - 2 {
+            let start_address = self.configuration.start_address;
+            match self.configuration.pc_overflow {
+                PcOverflowStyle::Wrap if start_address as usize <= memory_size - 2 => {
+                    *self.cpu.pc_mut() = start_address
+                }
+                _ => {
+                    log_warn!("Program counter ran past the end of memory: {:#05X}", pc);
+                    self.raise(Chip8Error::PcOutOfRange { pc });
+                    return None;
+                }
+            }
+        }
+
+        let pc = *self.cpu.pc();
+        let Some(opcode) = self.memory.read_u16(pc) else {
+            self.raise(Chip8Error::PcOutOfRange { pc });
+            return None;
+        };
         self.cpu.advance_pc();
-        opcode
+        Some(opcode)
     }
 
     fn execute(&mut self, command: Command) {
         match command {
             Command::ClearScreen => self.clear_screen(),
             Command::ReturnFromSubroutine => self.return_from_subroutine(),
+            #[cfg(feature = "schip")]
+            Command::SetDisplayMode { mode } => {
+                let opcode = match mode {
+                    DisplayMode::LowRes => 0x00FE,
+                    DisplayMode::HighRes => 0x00FF,
+                };
+                self.super_chip(opcode, |emulator| emulator.set_display_mode(mode))
+            }
+            #[cfg(feature = "schip")]
+            Command::ScrollDown { rows } => self.super_chip(0x00C0 | rows as u16, |emulator| {
+                let style = emulator.configuration.quirks.scroll;
+                emulator.display.scroll_down(rows, style)
+            }),
+            #[cfg(feature = "xochip")]
+            Command::ScrollUp { rows } => match self.configuration.platform {
+                Platform::XoChip => {
+                    let style = self.configuration.quirks.scroll;
+                    self.display.scroll_up(rows, style)
+                }
+                _ => self.invalid(0x00D0 | rows as u16),
+            },
+            #[cfg(feature = "schip")]
+            Command::ScrollRight => self.super_chip(0x00FB, |emulator| {
+                let style = emulator.configuration.quirks.scroll;
+                emulator.display.scroll_right4(style)
+            }),
+            #[cfg(feature = "schip")]
+            Command::ScrollLeft => self.super_chip(0x00FC, |emulator| {
+                let style = emulator.configuration.quirks.scroll;
+                emulator.display.scroll_left4(style)
+            }),
+            #[cfg(feature = "schip")]
+            Command::Exit => self.super_chip(0x00FD, |emulator| {
+                emulator.state = RunState::Halted(HaltReason::Exited)
+            }),
             Command::Jump { address } => self.jump(address),
             Command::SkipIfValueEqual { register, value } => self.skip_if_value_eq(register, value),
             Command::SkipIfValueNotEqual { register, value } => {
@@ -161,7 +786,7 @@ impl Emulator {
             Command::Add { register, value } => self.add(register, value),
             Command::AddRegisters { write, read } => self.add_registers(write, read),
             Command::AddI { read } => self.add_i(read),
-            Command::JumpOffset { address, register } => match self.configuration.jump {
+            Command::JumpOffset { address, register } => match self.configuration.quirks.jump {
                 JumpOffsetStyle::OffsetFromV0 => self.jump_offset(address),
                 JumpOffsetStyle::OffsetVariable => self.jump_offset_variable(address, register),
             },
@@ -169,17 +794,22 @@ impl Emulator {
             Command::LoadSpriteDigitIntoI { read_register } => {
                 self.load_sprite_key_into_i(read_register)
             }
+            #[cfg(feature = "schip")]
+            Command::LoadLargeSpriteDigitIntoI { read_register } => self
+                .super_chip(0xF030 | (read_register as u16) << 8, |emulator| {
+                    emulator.load_large_sprite_key_into_i(read_register)
+                }),
             Command::LoadBcd { read_register } => self.load_bcd(read_register),
             Command::Or { write, read } => self.or(write, read),
             Command::And { write, read } => self.and(write, read),
             Command::Xor { write, read } => self.xor(write, read),
             Command::Sub { write, read } => self.sub(write, read),
             Command::SubInverse { write, read } => self.sub_inverse(write, read),
-            Command::ShiftRight { write, read } => match self.configuration.shift {
+            Command::ShiftRight { write, read } => match self.configuration.quirks.shift {
                 ShiftStyle::CopyThenShift => self.shift_right(write, read),
                 ShiftStyle::ShiftInPlace => self.shift_right_in_place(write),
             },
-            Command::ShiftLeft { write, read } => match self.configuration.shift {
+            Command::ShiftLeft { write, read } => match self.configuration.quirks.shift {
                 ShiftStyle::CopyThenShift => self.shift_left(write, read),
                 ShiftStyle::ShiftInPlace => self.shift_left_in_place(write),
             },
@@ -197,522 +827,4974 @@ impl Emulator {
             Command::SetDelay { register } => self.set_delay(register),
             Command::SetSound { register } => self.set_sound(register),
             Command::WaitKeyPress { register } => self.wait_key(register),
-            Command::DumpAll { until_register } => match self.configuration.r_register {
-                DumpLoadStyle::AffectIRegister => self.dump_all_variable(until_register),
-                DumpLoadStyle::StaticIRegister => self.dump_all_static(until_register),
+            Command::DumpAll { until_register } => match self.configuration.quirks.dump_load {
+                DumpLoadStyle::IncrementI => self.dump_all(until_register, until_register + 1),
+                DumpLoadStyle::IncrementIByX => self.dump_all(until_register, until_register),
+                DumpLoadStyle::StaticIRegister => self.dump_all(until_register, 0),
+            },
+            Command::LoadAll { until_register } => match self.configuration.quirks.dump_load {
+                DumpLoadStyle::IncrementI => self.load_all(until_register, until_register + 1),
+                DumpLoadStyle::IncrementIByX => self.load_all(until_register, until_register),
+                DumpLoadStyle::StaticIRegister => self.load_all(until_register, 0),
             },
-            Command::LoadAll { until_register } => match self.configuration.r_register {
-                DumpLoadStyle::AffectIRegister => self.load_all_variable(until_register),
-                DumpLoadStyle::StaticIRegister => self.load_all_static(until_register),
+            #[cfg(feature = "schip")]
+            Command::SaveFlags { until_register } => self
+                .super_chip(0xF075 | (until_register as u16) << 8, |emulator| {
+                    emulator.save_flags(until_register)
+                }),
+            #[cfg(feature = "schip")]
+            Command::LoadFlags { until_register } => self
+                .super_chip(0xF085 | (until_register as u16) << 8, |emulator| {
+                    emulator.load_flags(until_register)
+                }),
+            #[cfg(feature = "xochip")]
+            Command::SelectPlanes { planes } => match self.configuration.platform {
+                Platform::XoChip => self.display.select_planes(planes),
+                _ => self.invalid(0xF001 | (planes as u16) << 8),
             },
-            Command::NoOp => log::warn!("Invalid instruction!"),
+            #[cfg(feature = "xochip")]
+            Command::SaveRegisterRange { first, last } => match self.configuration.platform {
+                Platform::XoChip => self.save_register_range(first, last),
+                _ => self.invalid(0x5002 | (first as u16) << 8 | (last as u16) << 4),
+            },
+            #[cfg(feature = "xochip")]
+            Command::LoadRegisterRange { first, last } => match self.configuration.platform {
+                Platform::XoChip => self.load_register_range(first, last),
+                _ => self.invalid(0x5003 | (first as u16) << 8 | (last as u16) << 4),
+            },
+            #[cfg(feature = "xochip")]
+            Command::LoadLongI => match self.configuration.platform {
+                Platform::XoChip => self.load_long_i(),
+                _ => self.invalid(0xF000),
+            },
+            #[cfg(feature = "xochip")]
+            Command::LoadAudioPattern => match self.configuration.platform {
+                Platform::XoChip => self.load_audio_pattern(),
+                _ => self.invalid(0xF002),
+            },
+            #[cfg(feature = "xochip")]
+            Command::SetPitch { register } => match self.configuration.platform {
+                Platform::XoChip => self.set_pitch(register),
+                _ => self.invalid(0xF03A | (register as u16) << 8),
+            },
+            Command::Invalid { opcode } => self.invalid(opcode),
         }
     }
 }
 
+impl Emulator<ManualClock> {
+    /// Move the clock of the timers forward, for hosts without a clock
+    /// of their own. Call this with the time passed since the last call,
+    /// e.g. once per frame before ticking:
+    ///
+    /// ```
+    /// use chip8::{emulator::Emulator, Clock, ManualClock};
+    ///
+    /// let mut emulator = Emulator::with_clock(ManualClock::new());
+    /// emulator.load_test_rom().unwrap();
+    /// for _frame in 0..60 {
+    ///     emulator.advance_time_ms(16);
+    ///     for _ in 0..10 {
+    ///         emulator.tick();
+    ///     }
+    /// }
+    /// assert_eq!(960, emulator.clock().now_millis());
+    /// ```
+    pub fn advance_time_ms(&mut self, millis: u64) {
+        self.clock.advance(millis);
+    }
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Peripherals implementations
-impl Emulator {
+impl<C: Clock> Emulator<C> {
+    /// Press the given key. Only the lower nibble is used,
+    /// so keys above 0xF wrap around instead of being rejected.
     pub fn press_key(&mut self, key: u8) {
-        self.keyboard.press(key);
-        if self.register_awaiting_input.is_some() {
-            self.resume_from_wait_key(key);
-        }
+        self.push_key_event(KeyEvent::Down(key));
     }
 
+    /// Release the given key. Only the lower nibble is used.
     pub fn release_key(&mut self, key: u8) {
-        self.keyboard.release(key);
+        self.push_key_event(KeyEvent::Up(key));
     }
 
-    pub fn is_sound_on(&self) -> bool {
-        *self.cpu.sound() > 0
+    /// Press or release a key. Next to updating the key state,
+    /// the event is queued if the input style is [`KeyInputStyle::Events`].
+    pub fn push_key_event(&mut self, event: KeyEvent) {
+        self.keyboard.push_event(event);
+        self.drop_unused_key_events();
+        #[cfg(feature = "alloc")]
+        self.record_key_event(event);
     }
 
-    pub fn is_pixel_on(&self, x: u8, y: u8) -> bool {
-        self.display.is_pixel_on(x, y)
+    /// Only [`KeyInputStyle::Events`] reads the queued events,
+    /// otherwise they would pile up and satisfy a later FX0A
+    fn drop_unused_key_events(&mut self) {
+        if self.configuration.key_input != KeyInputStyle::Events {
+            self.keyboard.clear_events();
+        }
     }
 
-    pub fn dump_registers(&self) -> [u8; 16] {
-        [
-            *self.cpu.register(0),
-            *self.cpu.register(1),
-            *self.cpu.register(2),
-            *self.cpu.register(3),
-            *self.cpu.register(4),
-            *self.cpu.register(5),
-            *self.cpu.register(6),
-            *self.cpu.register(7),
-            *self.cpu.register(8),
-            *self.cpu.register(9),
-            *self.cpu.register(10),
-            *self.cpu.register(11),
-            *self.cpu.register(12),
-            *self.cpu.register(13),
-            *self.cpu.register(14),
-            *self.cpu.register(15),
-        ]
+    /// The number of ticks run so far. Ticks that did nothing,
+    /// because the emulator was halted or paused, are not counted.
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
     }
 
-    pub fn pc(&self) -> u16 {
-        *self.cpu.pc()
-    }
-    pub fn i(&self) -> u16 {
-        *self.cpu.i()
-    }
-    pub fn delay(&self) -> u8 {
-        *self.cpu.delay()
-    }
-    pub fn dump_raw_memory_around_pc(&self) -> [u8; 11] {
-        [
-            self.memory.read_u8(self.pc() - 5),
-            self.memory.read_u8(self.pc() - 4),
-            self.memory.read_u8(self.pc() - 3),
-            self.memory.read_u8(self.pc() - 2),
-            self.memory.read_u8(self.pc() - 1),
-            self.memory.read_u8(self.pc() - 0),
-            self.memory.read_u8(self.pc() + 1),
-            self.memory.read_u8(self.pc() + 2),
-            self.memory.read_u8(self.pc() + 3),
-            self.memory.read_u8(self.pc() + 4),
-            self.memory.read_u8(self.pc() + 5),
-        ]
-    }
-    pub fn dump_double_memory_around_pc(&self) -> [u16; 11] {
-        [
-            self.memory.read_u16(self.pc() - 10),
-            self.memory.read_u16(self.pc() - 8),
-            self.memory.read_u16(self.pc() - 6),
-            self.memory.read_u16(self.pc() - 4),
-            self.memory.read_u16(self.pc() - 2),
-            self.memory.read_u16(self.pc() - 0),
-            self.memory.read_u16(self.pc() + 2),
-            self.memory.read_u16(self.pc() + 4),
-            self.memory.read_u16(self.pc() + 6),
-            self.memory.read_u16(self.pc() + 8),
-            self.memory.read_u16(self.pc() + 10),
-        ]
+    /// Record all key input from now on, until the
+    /// recording is taken with [`Emulator::take_input_recording`]
+    #[cfg(feature = "alloc")]
+    pub fn start_input_recording(&mut self) {
+        self.recording = Some((self.tick_count, InputRecording::new()));
     }
-}
 
-/// Interpreter
-impl Emulator {
-    fn clear_screen(&mut self) {
-        self.display.clear()
-    }
-    fn return_from_subroutine(&mut self) {
-        *self.cpu.pc_mut() = self.stack.pop();
+    /// Stop recording and return the input recorded so far
+    #[cfg(feature = "alloc")]
+    pub fn take_input_recording(&mut self) -> InputRecording {
+        self.recording
+            .take()
+            .map(|(_, recording)| recording)
+            .unwrap_or_default()
     }
 
-    fn call_subroutine(&mut self, address: u16) {
-        self.stack.push(*self.cpu.pc());
-        *self.cpu.pc_mut() = address;
+    #[cfg(feature = "alloc")]
+    fn record_key_event(&mut self, event: KeyEvent) {
+        if let Some((start, recording)) = &mut self.recording {
+            let event = match event {
+                KeyEvent::Down(key) => KeyEvent::Down(key & 0x0F),
+                KeyEvent::Up(key) => KeyEvent::Up(key & 0x0F),
+            };
+            recording.push(InputEntry {
+                tick: self.tick_count - *start,
+                event,
+            });
+        }
     }
 
-    fn jump(&mut self, address: u16) {
-        *self.cpu.pc_mut() = address;
+    /// Run the given number of ticks, feeding in the recorded input
+    /// at the same ticks it was recorded at
+    #[cfg(feature = "alloc")]
+    pub fn replay_input(&mut self, recording: &InputRecording, ticks: u64) {
+        let mut cursor = recording.cursor();
+        for tick in 0..ticks {
+            for event in cursor.events_until(tick) {
+                self.push_key_event(event);
+            }
+            self.tick();
+        }
     }
 
-    fn jump_offset(&mut self, address: u16) {
-        self.jump(address + *self.cpu.register(0) as u16);
+    /// The map used by [`Emulator::press_char`] and [`Emulator::release_char`],
+    /// [`KeyMap::qwerty`] by default
+    pub fn key_map(&self) -> &KeyMap {
+        &self.key_map
     }
 
-    fn jump_offset_variable(&mut self, address: u16, register: u8) {
-        self.jump(address + *self.cpu.register(register) as u16);
+    pub fn set_key_map(&mut self, key_map: KeyMap) {
+        self.key_map = key_map;
     }
 
-    fn skip_if_value_eq(&mut self, register: u8, value: u8) {
-        if *self.cpu.register(register) == value {
-            self.cpu.advance_pc();
+    /// Press the key the character is mapped to.
+    /// Returns false if the character is not mapped to any key.
+    pub fn press_char(&mut self, c: char) -> bool {
+        match self.key_map.map_char(c) {
+            Some(key) => {
+                self.press_key(key);
+                true
+            }
+            None => false,
         }
     }
-    fn skip_if_value_neq(&mut self, register: u8, value: u8) {
-        if *self.cpu.register(register) != value {
-            self.cpu.advance_pc();
+
+    /// Release the key the character is mapped to.
+    /// Returns false if the character is not mapped to any key.
+    pub fn release_char(&mut self, c: char) -> bool {
+        match self.key_map.map_char(c) {
+            Some(key) => {
+                self.release_key(key);
+                true
+            }
+            None => false,
         }
     }
-    fn skip_if_registers_eq(&mut self, register_a: u8, register_b: u8) {
-        if *self.cpu.register(register_a) == *self.cpu.register(register_b) {
-            self.cpu.advance_pc();
-        }
+
+    /// Press the key now and release it again after it was held
+    /// for the given number of ticks. Taps can overlap, a key tapped
+    /// several times is released once the last tap runs out.
+    /// Returns false if too many taps are held already.
+    pub fn tap_key(&mut self, key: u8, hold_ticks: u32) -> bool {
+        let Some(slot) = self.key_taps.iter_mut().find(|tap| tap.is_none()) else {
+            return false;
+        };
+        *slot = Some((key & 0x0F, hold_ticks));
+        self.press_key(key);
+        true
     }
-    fn skip_if_registers_neq(&mut self, register_a: u8, register_b: u8) {
-        if *self.cpu.register(register_a) != *self.cpu.register(register_b) {
-            self.cpu.advance_pc();
+
+    /// Count down the held taps, releasing the keys that ran out
+    fn update_key_taps(&mut self) {
+        for index in 0..MAX_KEY_TAPS {
+            let Some((key, remaining)) = self.key_taps[index] else {
+                continue;
+            };
+            if remaining > 0 {
+                self.key_taps[index] = Some((key, remaining - 1));
+                continue;
+            }
+            self.key_taps[index] = None;
+            let is_held = self
+                .key_taps
+                .iter()
+                .flatten()
+                .any(|(tapped, _)| *tapped == key);
+            if !is_held {
+                self.release_key(key);
+            }
         }
     }
 
-    fn skip_if_key_pressed(&mut self, key_register: u8) {
-        if self.keyboard.is_pressed(*self.cpu.register(key_register)) {
-            self.cpu.advance_pc();
+    /// Replace the state of all keys at once, with bit n set if key n is pressed
+    pub fn set_keys(&mut self, mask: u16) {
+        #[cfg(feature = "alloc")]
+        let changed = mask ^ self.keyboard.mask();
+        self.keyboard.set_mask(mask);
+        self.drop_unused_key_events();
+        #[cfg(feature = "alloc")]
+        for key in (0..16).filter(|key| changed >> key & 1 == 1) {
+            if mask >> key & 1 == 1 {
+                self.record_key_event(KeyEvent::Down(key));
+            } else {
+                self.record_key_event(KeyEvent::Up(key));
+            }
         }
     }
 
-    fn skip_if_key_not_pressed(&mut self, key_register: u8) {
-        if !self.keyboard.is_pressed(*self.cpu.register(key_register)) {
-            self.cpu.advance_pc();
+    /// Read keys from the given source instead of the keys pressed
+    /// on the emulator, e.g. to poll a hardware keypad
+    #[cfg(feature = "alloc")]
+    pub fn set_key_source(&mut self, source: impl KeySource + Send + 'static) {
+        self.key_source = Some(Box::new(source));
+        self.source_keys = 0;
+    }
+
+    /// A key source only reports which keys are held, so for
+    /// [`KeyInputStyle::Events`] the changes since its last poll are queued
+    #[cfg(feature = "alloc")]
+    fn queue_source_events(&mut self) {
+        if self.key_source.is_none() {
+            return;
+        }
+        let keys = self.keys();
+        let changed = keys ^ self.source_keys;
+        self.source_keys = keys;
+        if self.configuration.key_input != KeyInputStyle::Events {
+            return;
+        }
+        for key in (0..16).filter(|key| changed >> key & 1 == 1) {
+            if keys >> key & 1 == 1 {
+                self.keyboard.queue_event(KeyEvent::Down(key));
+            } else {
+                self.keyboard.queue_event(KeyEvent::Up(key));
+            }
         }
     }
 
-    fn load(&mut self, register: u8, value: u8) {
-        *self.cpu.register_mut(register) = value;
+    /// Persist the user flags of FX75 and FX85 in the given store.
+    /// The flags saved in the store before are loaded right away.
+    #[cfg(feature = "alloc")]
+    pub fn set_flag_store(&mut self, mut store: impl FlagStore + Send + 'static) {
+        self.flags = [0; MAX_FLAGS];
+        store.load(&mut self.flags);
+        self.flag_store = Some(Box::new(store));
     }
-    fn copy_register(&mut self, write: u8, read: u8) {
-        *self.cpu.register_mut(write) = *self.cpu.register(read);
+
+    /// Keep the user flags in memory only
+    #[cfg(feature = "alloc")]
+    pub fn clear_flag_store(&mut self) {
+        self.flag_store = None;
     }
-    fn load_i(&mut self, value: u16) {
-        *self.cpu.i_mut() = value;
+
+    /// The user flags saved by FX75
+    pub fn flags(&self) -> &[u8; MAX_FLAGS] {
+        &self.flags
     }
-    fn load_sprite_key_into_i(&mut self, key_register: u8) {
-        *self.cpu.i_mut() = Self::font_sprite_address(*self.cpu.register(key_register));
+
+    /// Go back to reading the keys pressed on the emulator
+    #[cfg(feature = "alloc")]
+    pub fn clear_key_source(&mut self) {
+        self.key_source = None;
     }
-    fn load_bcd(&mut self, read: u8) {
-        let value = *self.cpu.register(read);
-        let address = *self.cpu.i();
-        self.memory.write_u8(address + 0, value / 100);
-        self.memory.write_u8(address + 1, (value / 10) % 10);
-        self.memory.write_u8(address + 2, value % 10);
+
+    fn key_source(&self) -> &dyn KeySource {
+        #[cfg(feature = "alloc")]
+        if let Some(source) = &self.key_source {
+            return source.as_ref();
+        }
+        &self.keyboard
     }
-    fn add(&mut self, register: u8, value: u8) {
-        *self.cpu.register_mut(register) = self.cpu.register(register).wrapping_add(value);
+
+    fn is_key_pressed(&self, key: u8) -> bool {
+        self.key_source().is_pressed(key & 0x0F)
     }
-    fn add_registers(&mut self, write: u8, read: u8) {
-        let a = self.cpu.register(write);
-        let b = self.cpu.register(read);
-        if (*a as u16) + (*b as u16) > 255 {
-            *self.cpu.register_mut(write) = a.wrapping_add(*b);
-            self.cpu.carry_on();
-        } else {
-            *self.cpu.register_mut(write) = a + b;
-            self.cpu.carry_off();
-        }
+
+    pub fn any_key_pressed(&self) -> bool {
+        self.key_source().any_pressed()
     }
-    fn add_i(&mut self, register: u8) {
-        *self.cpu.i_mut() += *self.cpu.register(register) as u16;
+
+    /// The lowest pressed key, which is also the one FX0A picks
+    /// when several keys are held
+    pub fn first_pressed_key(&self) -> Option<u8> {
+        self.key_source().first_pressed()
     }
 
-    fn or(&mut self, write: u8, read: u8) {
-        *self.cpu.register_mut(write) |= *self.cpu.register(read);
+    /// The state of all keys, with bit n set if key n is pressed.
+    /// Reads the key source, if one is set.
+    pub fn keys(&self) -> u16 {
+        let source = self.key_source();
+        (0..16)
+            .filter(|key| source.is_pressed(*key))
+            .fold(0, |mask, key| mask | 1 << key)
     }
-    fn and(&mut self, write: u8, read: u8) {
-        *self.cpu.register_mut(write) &= *self.cpu.register(read);
+
+    /// Stop executing instructions and freeze the timers,
+    /// e.g. while the host shows a menu. Ticking does nothing until resumed.
+    pub fn pause(&mut self) {
+        self.paused = true;
     }
-    fn random_and(&mut self, register: u8, value: u8) {
-        *self.cpu.register_mut(register) = value & (self.rng.rand_u32() >> 24) as u8;
+
+    /// Continue after [`Emulator::pause`]. The time spent paused
+    /// is not taken from the timers.
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.timer.resync(&self.clock);
+            self.paused = false;
+        }
     }
-    fn xor(&mut self, write: u8, read: u8) {
-        *self.cpu.register_mut(write) ^= *self.cpu.register(read);
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
     }
-    fn sub(&mut self, write: u8, read: u8) {
-        let a = *self.cpu.register(write);
-        let b = *self.cpu.register(read);
-        *self.cpu.register_mut(write) = self.sub_with_borrow(a, b);
+
+    /// Whether an error or the rom exiting stopped the emulator. A halted
+    /// emulator does nothing when ticked, until a new rom gets loaded.
+    pub fn is_halted(&self) -> bool {
+        matches!(self.state, RunState::Halted(_))
     }
-    fn sub_inverse(&mut self, write: u8, read: u8) {
-        let a = *self.cpu.register(write);
-        let b = *self.cpu.register(read);
-        *self.cpu.register_mut(write) = self.sub_with_borrow(b, a);
+
+    /// Whether a FX0A instruction is waiting for a key.
+    /// Hosts can wait for input instead of ticking in the meantime.
+    pub fn is_waiting_for_key(&self) -> bool {
+        matches!(self.state, RunState::WaitingForKey { .. })
     }
-    fn sub_with_borrow(&mut self, a: u8, b: u8) -> u8 {
-        if a < b {
-            self.cpu.carry_off();
-            a.wrapping_sub(b)
-        } else {
-            self.cpu.carry_on();
-            a - b
+
+    /// Whether the rom is busy waiting for the delay timer to run out,
+    /// in the usual `FX07; 3X00; 1NNN` loop jumping back to the FX07.
+    /// Until the timer runs out, ticking does nothing but burn time.
+    pub fn is_idle_on_delay(&self) -> bool {
+        if self.delay() == 0 || self.is_halted() {
+            return false;
         }
+        let pc = self.pc();
+        (0..3).any(|instruction| {
+            let start = pc.wrapping_sub(instruction * 2);
+            self.is_delay_loop_at(start)
+        })
     }
 
-    /// Shifting is ambiguous, older versions copied over the value
-    /// from the read register to the write register, while newer
-    /// versions shift in-place the given register.
-    fn shift_right(&mut self, write: u8, read: u8) {
-        self.copy_register(write, read);
-        self.shift_right_in_place(write);
+    /// Whether the rom loops without changing anything, so ticking it only
+    /// burns time: the instruction at the program counter jumps to itself,
+    /// or the last instructions went around at most 4 addresses without
+    /// changing registers, memory or the display. Loops reading the delay
+    /// timer or keys do not count, see [`Emulator::is_idle_on_delay`].
+    pub fn is_spinning(&self) -> bool {
+        if self.is_halted() {
+            return false;
+        }
+        let pc = self.pc();
+        let jumps_to_self = pc < 0x1000 && self.memory.read_u16(pc) == Some(0x1000 | pc);
+        jumps_to_self || self.idle.is_looping()
     }
 
-    fn shift_right_in_place(&mut self, register: u8) {
-        let rightmost = *self.cpu.register(register) & 1;
-        *self.cpu.register_mut(register) >>= 1;
-        if rightmost == 1 {
-            self.cpu.carry_on();
+    /// Note whether the instruction at pc changed anything, given the cpu
+    /// before it if it is one that can only change registers
+    fn track_idle(&mut self, pc: u16, before: Option<CpuSnapshot>) {
+        let quiet = before.is_some_and(|before| {
+            let after = self.cpu_snapshot();
+            before.v == after.v && before.i == after.i && before.sp == after.sp
+        }) && matches!(self.state, RunState::Running);
+        self.idle.record(pc, quiet);
+    }
+
+    fn is_delay_loop_at(&self, start: u16) -> bool {
+        let word = |offset: u16| self.memory.read_u16(start.wrapping_add(offset));
+        let (Some(load), Some(skip), Some(jump)) = (word(0), word(2), word(4)) else {
+            return false;
+        };
+        let register = (load >> 8) & 0x0F;
+        load & 0xF0FF == 0xF007 && skip == 0x3000 | register << 8 && jump == 0x1000 | start
+    }
+
+    /// Why the emulator halted, if it did
+    pub fn halt_reason(&self) -> Option<&HaltReason> {
+        match &self.state {
+            RunState::Halted(reason) => Some(reason),
+            _ => None,
+        }
+    }
+
+    /// The most recent error encountered, even if it did not stop the emulator.
+    /// With [`InvalidOpcodeStyle::Skip`] this holds the last skipped invalid opcode.
+    pub fn last_fault(&self) -> Option<&Chip8Error> {
+        self.last_fault.as_ref()
+    }
+
+    /// What the last sprite drawn since loading the rom collided with
+    pub fn last_draw_info(&self) -> Option<&DrawInfo> {
+        self.last_draw.as_ref()
+    }
+
+    /// Stop [`Emulator::run_until_breakpoint`] before the instruction at the address runs
+    #[cfg(feature = "alloc")]
+    pub fn add_breakpoint(&mut self, address: u16) -> BreakpointId {
+        self.breakpoints.add(address)
+    }
+
+    /// Stop [`Emulator::run_until_breakpoint`] before the instruction
+    /// at the address runs, if the condition holds
+    #[cfg(feature = "alloc")]
+    pub fn add_breakpoint_when(&mut self, address: u16, condition: BreakCondition) -> BreakpointId {
+        self.breakpoints.add_when(address, condition)
+    }
+
+    /// Stop [`Emulator::run_until_breakpoint`] before the instruction
+    /// at the address runs, if the predicate returns true for the cpu state
+    #[cfg(feature = "std")]
+    pub fn add_breakpoint_if(
+        &mut self,
+        address: u16,
+        predicate: impl Fn(&CpuSnapshot) -> bool + Send + 'static,
+    ) -> BreakpointId {
+        self.breakpoints.add_if(address, predicate)
+    }
+
+    /// Stop [`Emulator::run_until_breakpoint`] before any instruction
+    /// of the kind runs, wherever it is
+    #[cfg(feature = "alloc")]
+    pub fn add_kind_breakpoint(&mut self, kind: InstructionKind) -> BreakpointId {
+        self.breakpoints.add_kind(kind)
+    }
+
+    /// Stop [`Emulator::run_until_breakpoint`] after an instruction that
+    /// changes the register the way the mode asks for, with
+    /// [`StopReason::Watch`]. VF is watched like any other register.
+    #[cfg(feature = "alloc")]
+    pub fn add_register_watch(&mut self, register: Register, mode: WatchMode) -> BreakpointId {
+        self.breakpoints.add_watch(register, mode)
+    }
+
+    /// Returns whether the breakpoint existed
+    #[cfg(feature = "alloc")]
+    pub fn remove_breakpoint(&mut self, id: BreakpointId) -> bool {
+        self.breakpoints.remove(id)
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Write a line for every instruction from now on, with its address,
+    /// opcode and mnemonic and the registers after it, e.g.
+    /// `0202 A22A LD I, 0x22A  I=022A`. If writing fails, tracing stops.
+    #[cfg(feature = "std")]
+    pub fn set_trace_writer(
+        &mut self,
+        writer: impl std::io::Write + Send + 'static,
+        detail: TraceDetail,
+    ) {
+        self.trace = Some((Box::new(writer), detail));
+    }
+
+    #[cfg(feature = "std")]
+    pub fn clear_trace_writer(&mut self) {
+        self.trace = None;
+    }
+
+    /// Count how often every address from the start address on is
+    /// executed from now on, see [`Emulator::address_profile`].
+    /// Loading a rom resets the counts.
+    #[cfg(feature = "alloc")]
+    pub fn enable_address_profiling(&mut self) {
+        let start = self.configuration.start_address;
+        let words = self.memory.len().saturating_sub(start as usize).div_ceil(2);
+        self.profile = Some((start, vec![0; words]));
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn disable_address_profiling(&mut self) {
+        self.profile = None;
+    }
+
+    /// How often every word was executed, indexed by (address - start address) / 2.
+    /// The counts saturate instead of overflowing. Empty if profiling is off.
+    #[cfg(feature = "alloc")]
+    pub fn address_profile(&self) -> &[u32] {
+        self.profile
+            .as_ref()
+            .map_or(&[], |(_, counters)| counters.as_slice())
+    }
+
+    /// The up to n most executed addresses with their counts and
+    /// instructions, most executed first
+    #[cfg(feature = "alloc")]
+    pub fn top_n(&self, n: usize) -> Vec<(u16, u32, Instruction)> {
+        let Some((start, counters)) = &self.profile else {
+            return Vec::new();
+        };
+        let mut hottest: Vec<(u16, u32)> = counters
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .filter_map(|(index, count)| {
+                let address = u16::try_from(*start as usize + index * 2).ok()?;
+                Some((address, *count))
+            })
+            .collect();
+        hottest.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hottest
+            .into_iter()
+            .take(n)
+            .map(|(address, count)| {
+                let word = self.memory.read_u16(address).unwrap_or(0);
+                (address, count, Instruction::decode(word))
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "alloc")]
+    fn profile_address(&mut self, pc: u16) {
+        let Some((start, counters)) = &mut self.profile else {
+            return;
+        };
+        let counter = pc
+            .checked_sub(*start)
+            .and_then(|offset| counters.get_mut(offset as usize / 2));
+        if let Some(counter) = counter {
+            *counter = counter.saturating_add(1);
+        }
+    }
+
+    /// Log the bytes instructions write to the addresses from now on, and
+    /// the bytes they read too if reads is set. Fetching instructions is
+    /// not logged. The log keeps the last [`crate::debug::MEMORY_ACCESS_LOG_LEN`]
+    /// accesses until they are taken with [`Emulator::memory_access_log`].
+    #[cfg(feature = "alloc")]
+    pub fn enable_memory_access_log(&mut self, addresses: Range<u16>, reads: bool) {
+        self.access_log = Some(AccessLog::new(addresses, reads));
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn disable_memory_access_log(&mut self) {
+        self.access_log = None;
+    }
+
+    /// Take the logged memory accesses, oldest first
+    #[cfg(feature = "alloc")]
+    pub fn memory_access_log(&mut self) -> impl Iterator<Item = MemoryAccess> + '_ {
+        self.access_log.iter_mut().flat_map(AccessLog::drain)
+    }
+
+    /// Save every step from now on, so the last depth of them can be
+    /// undone, or stop saving them with `None`
+    #[cfg(feature = "alloc")]
+    pub(crate) fn save_undo_steps(&mut self, depth: Option<usize>) {
+        match (depth, &mut self.undo) {
+            (None, _) => self.undo = None,
+            (Some(depth), Some(undo)) => undo.set_depth(depth),
+            (Some(depth), None) => self.undo = Some(UndoLog::new(depth)),
+        }
+    }
+
+    /// The number of saved steps that can be undone
+    #[cfg(feature = "alloc")]
+    pub(crate) fn undo_len(&self) -> usize {
+        self.undo.as_ref().map_or(0, UndoLog::len)
+    }
+
+    /// Undo the most recently saved step. Returns whether there was one.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn undo_step(&mut self) -> bool {
+        match self.undo.as_mut().and_then(UndoLog::pop) {
+            Some(step) => {
+                self.restore_undo_step(step);
+                self.idle.reset();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Tick until the instruction at the program counter has a breakpoint,
+    /// at most max_ticks times. The first instruction always runs, so
+    /// calling this again continues from the breakpoint it stopped at.
+    #[cfg(feature = "alloc")]
+    pub fn run_until_breakpoint(&mut self, max_ticks: usize) -> StopReason {
+        self.run_until(max_ticks, |_| false)
+    }
+
+    /// Run the next instruction, or the whole subroutine if it is a call,
+    /// until the program counter is back behind the call.
+    /// Stops early at breakpoints and after max_ticks.
+    #[cfg(feature = "alloc")]
+    pub fn step_over(&mut self, max_ticks: usize) -> StopReason {
+        let pc = self.pc();
+        let is_call = self
+            .memory
+            .read_u16(pc)
+            .is_some_and(|word| word & 0xF000 == 0x2000);
+        if !is_call {
+            return self.run_until(max_ticks, |_| true);
+        }
+        let depth = self.call_depth();
+        let return_address = pc.wrapping_add(2);
+        self.run_until(max_ticks, |emulator| {
+            emulator.call_depth() <= depth && emulator.pc() == return_address
+        })
+    }
+
+    /// Run until the current subroutine returns.
+    /// Stops early at breakpoints and after max_ticks.
+    #[cfg(feature = "alloc")]
+    pub fn step_out(&mut self, max_ticks: usize) -> StopReason {
+        let depth = self.call_depth();
+        self.run_until(max_ticks, |emulator| emulator.call_depth() < depth)
+    }
+
+    /// Tick until the step is done, a breakpoint is reached, the rom spins
+    /// or max_ticks ran. Breakpoints are only checked after the first tick.
+    #[cfg(feature = "alloc")]
+    fn run_until(&mut self, max_ticks: usize, is_done: impl Fn(&Self) -> bool) -> StopReason {
+        for ticks in 0..=max_ticks {
+            if ticks > 0 {
+                if is_done(self) {
+                    return StopReason::Stepped;
+                }
+                if self.is_at_breakpoint() {
+                    return StopReason::Breakpoint(self.pc());
+                }
+                if self.is_spinning() {
+                    return StopReason::IdleLoop { pc: self.pc() };
+                }
+            }
+            if ticks == max_ticks {
+                break;
+            }
+            let before = self.breakpoints.has_watches().then(|| self.cpu_snapshot());
+            match self.try_tick() {
+                Ok(TickOutcome::Executed) => (),
+                Ok(TickOutcome::WaitingForKey) => return StopReason::WaitingForKey,
+                Ok(TickOutcome::Paused) => return StopReason::Paused,
+                Ok(TickOutcome::Exited) | Err(_) => return StopReason::Halted,
+            }
+            if let Some(before) = before {
+                if let Some(hit) = self.breakpoints.watch_hit(&before, &self.cpu_snapshot()) {
+                    return StopReason::Watch(hit);
+                }
+            }
+        }
+        StopReason::MaxTicks
+    }
+
+    /// Whether a breakpoint stops before the instruction at the program counter
+    #[cfg(feature = "alloc")]
+    fn is_at_breakpoint(&mut self) -> bool {
+        let mut hit = false;
+        if self.breakpoints.is_set(self.pc()) {
+            let cpu = self.cpu_snapshot();
+            let memory = &self.memory;
+            hit = self
+                .breakpoints
+                .hits(&cpu, |address| memory.get_u8(address));
+        }
+        if !hit && self.breakpoints.has_kinds() {
+            if let Some(word) = self.memory.read_u16(self.pc()) {
+                hit = self.breakpoints.hits_kind(Instruction::decode(word).kind());
+            }
+        }
+        hit
+    }
+
+    /// Whether the sound register is running, muted or not
+    pub fn is_sound_on(&self) -> bool {
+        *self.cpu.sound() > 0
+    }
+
+    /// Whether the buzzer can be heard, i.e. is on and not muted.
+    /// Feed this to an [`crate::audio::BuzzerHandle`] once per frame.
+    pub fn is_audible(&self) -> bool {
+        self.is_sound_on() && !self.muted
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Silence the buzzer on the host side. The rom still sees the sound
+    /// register run as usual, only the rendered audio and the
+    /// [`SoundSink`] follow the mute. The sink is told about the buzzer
+    /// falling silent or coming back when muting a running beep.
+    pub fn set_muted(&mut self, muted: bool) {
+        #[cfg(feature = "alloc")]
+        if muted != self.muted && self.is_sound_on() {
+            self.notify_sound_sink(!muted);
+        }
+        self.muted = muted;
+    }
+
+    #[cfg(feature = "alloc")]
+    fn notify_sound_sink(&mut self, on: bool) {
+        if let Some(sink) = &mut self.sound_sink {
+            sink.sound_changed(on);
+        }
+    }
+
+    /// Whether the pixel is on, in the coordinates of the active [`DisplayMode`]
+    pub fn is_pixel_on(&self, x: u8, y: u8) -> bool {
+        self.display.is_pixel_on(x, y)
+    }
+
+    /// The resolution the display currently runs at
+    pub fn display_mode(&self) -> DisplayMode {
+        self.display.mode()
+    }
+
+    /// The width and height of the display in the active mode
+    pub fn resolution(&self) -> (u16, u16) {
+        let mode = self.display.mode();
+        (mode.width() as u16, mode.height() as u16)
+    }
+
+    /// Whether the display runs at the 128x64 pixels of SUPER-CHIP
+    pub fn is_hires(&self) -> bool {
+        self.display.mode() == DisplayMode::HighRes
+    }
+
+    /// Counts how often the resolution changed, including the switch back
+    /// to low resolution when loading a rom. Hosts compare it to the count
+    /// they saw last frame to notice when to resize their window or texture.
+    pub fn mode_switches(&self) -> u64 {
+        self.mode_switches
+    }
+
+    /// The color of the pixel from 0 to 3, in the coordinates of the active
+    /// [`DisplayMode`]. Bit 0 is set if the pixel is on in the first plane,
+    /// bit 1 if it is on in the second plane of XO-CHIP.
+    pub fn pixel_color(&self, x: u8, y: u8) -> u8 {
+        self.display.pixel_color(x, y)
+    }
+
+    /// Write the display as RGBA pixels row by row, each pixel repeated
+    /// `scale` times in both directions, in the colors of the palette.
+    /// A whole frame takes
+    /// width * height * scale² * 4 bytes, see [`Emulator::resolution`].
+    /// Returns the number of bytes written, which stops at the end of the buffer.
+    pub fn render_rgba_into(&self, buffer: &mut [u8], scale: usize, palette: &Palette) -> usize {
+        self.display.render_rgba_into(buffer, scale, palette)
+    }
+
+    pub fn dump_registers(&self) -> [u8; 16] {
+        [
+            *self.cpu.register(0),
+            *self.cpu.register(1),
+            *self.cpu.register(2),
+            *self.cpu.register(3),
+            *self.cpu.register(4),
+            *self.cpu.register(5),
+            *self.cpu.register(6),
+            *self.cpu.register(7),
+            *self.cpu.register(8),
+            *self.cpu.register(9),
+            *self.cpu.register(10),
+            *self.cpu.register(11),
+            *self.cpu.register(12),
+            *self.cpu.register(13),
+            *self.cpu.register(14),
+            *self.cpu.register(15),
+        ]
+    }
+
+    /// The return addresses currently pushed on the stack, oldest first
+    pub fn call_stack(&self) -> &[u16] {
+        self.stack.contents()
+    }
+
+    /// The number of subroutines currently entered
+    pub fn call_depth(&self) -> usize {
+        self.stack.contents().len()
+    }
+
+    /// The addresses of the call instructions that entered
+    /// the current subroutines, oldest first
+    pub fn call_sites(&self) -> impl Iterator<Item = u16> + '_ {
+        self.stack
+            .contents()
+            .iter()
+            .map(|address| address.wrapping_sub(2))
+    }
+
+    /// The complete cpu state, including I, PC, the stack pointer and timers
+    pub fn cpu_snapshot(&self) -> CpuSnapshot {
+        self.cpu.snapshot(self.call_depth() as u8)
+    }
+
+    pub fn get_register(&self, register: Register) -> u8 {
+        *self.cpu.register(register.index())
+    }
+
+    pub fn set_register(&mut self, register: Register, value: u8) {
+        *self.cpu.register_mut(register.index()) = value;
+    }
+
+    pub fn pc(&self) -> u16 {
+        *self.cpu.pc()
+    }
+    /// Move execution to the given address. The address has to leave room
+    /// for a full instruction before the end of memory.
+    /// If the emulator is waiting for a key press (FX0A),
+    /// the wait is abandoned and execution simply continues at the new address.
+    pub fn set_pc(&mut self, address: u16) -> Result<(), InvalidAddress> {
+        if address as usize > self.memory.len() - 2 {
+            return Err(InvalidAddress(address));
+        }
+        if address & 1 == 1 {
+            log_warn!("Setting PC to odd address {:#05X}", address);
+        }
+        if !self.is_halted() {
+            self.state = RunState::Running;
+        }
+        *self.cpu.pc_mut() = address;
+        Ok(())
+    }
+    pub fn i(&self) -> u16 {
+        *self.cpu.i()
+    }
+    /// Set the I register to the given address inside of memory
+    pub fn set_i(&mut self, address: u16) -> Result<(), InvalidAddress> {
+        if address as usize >= self.memory.len() {
+            return Err(InvalidAddress(address));
+        }
+        *self.cpu.i_mut() = address;
+        Ok(())
+    }
+    pub fn delay(&self) -> u8 {
+        *self.cpu.delay()
+    }
+    /// How long until the delay register reaches 0
+    pub fn delay_remaining(&self) -> Duration {
+        timer::remaining(self.delay())
+    }
+    pub fn sound(&self) -> u8 {
+        *self.cpu.sound()
+    }
+    /// How long the current beep keeps playing
+    pub fn sound_remaining(&self) -> Duration {
+        timer::remaining(self.sound())
+    }
+    /// Decrement the delay and sound timers by exactly one step.
+    /// With [`TimerStyle::HostDriven`] the host calls this at 60 Hz,
+    /// independent of how fast or whether instructions get executed.
+    pub fn tick_timers(&mut self) {
+        *self.cpu.delay_mut() = self.cpu.delay().saturating_sub(1);
+        *self.cpu.sound_mut() = self.cpu.sound().saturating_sub(1);
+        self.update_sound_state();
+    }
+
+    /// Notify the given sink whenever the buzzer turns on or off
+    #[cfg(feature = "alloc")]
+    pub fn set_sound_sink(&mut self, sink: impl SoundSink + Send + 'static) {
+        self.sound_sink = Some(Box::new(sink));
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn clear_sound_sink(&mut self) {
+        self.sound_sink = None;
+    }
+
+    /// Take the buzzer changes since the last call, oldest first.
+    /// Only the most recent events are kept, so call this regularly.
+    #[cfg(feature = "alloc")]
+    pub fn take_sound_events(&mut self) -> impl Iterator<Item = SoundEvent> + '_ {
+        self.sound_events.drain(..)
+    }
+
+    /// Render the buzzer for the time the buffer covers from now on:
+    /// a square wave while the sound timer runs, silence once it ran out.
+    /// Call this between ticks, as the timer keeps running while ticking.
+    /// Once an XO-CHIP rom loaded an audio pattern, the pattern is played instead.
+    /// Changes to the buzzer configuration apply from the next buffer on.
+    pub fn fill_audio(&mut self, buf: &mut [f32], sample_rate: u32) {
+        self.update_buzzer();
+        let on_samples = self.audible_samples(sample_rate);
+        self.render_audio(buf, sample_rate, on_samples);
+    }
+
+    /// The samples the buzzer keeps playing for, none while muted.
+    /// On the wall clock the part of the current step that already
+    /// passed is taken off, as it was played by the previous buffer.
+    fn audible_samples(&self, sample_rate: u32) -> usize {
+        if self.muted {
+            return 0;
+        }
+        match self.configuration.timers {
+            TimerStyle::WallClock => {
+                let units = self
+                    .timer
+                    .units_left(self.sound() as u32, self.clock.now_millis());
+                audio::unit_samples(units, sample_rate)
+            }
+            _ => audio::sound_samples(self.sound(), sample_rate),
+        }
+    }
+
+    fn render_audio(&mut self, buf: &mut [f32], sample_rate: u32, on_samples: usize) {
+        match &self.audio_pattern {
+            Some(pattern) => {
+                self.pattern_wave
+                    .fill(pattern, self.pitch, buf, sample_rate, on_samples)
+            }
+            None => self.buzzer.fill(buf, sample_rate, on_samples),
+        }
+    }
+
+    /// Like [`Emulator::fill_audio`], for signed 16 bit samples
+    pub fn fill_audio_i16(&mut self, buf: &mut [i16], sample_rate: u32) {
+        self.update_buzzer();
+        let on_samples = self.audible_samples(sample_rate);
+        match &self.audio_pattern {
+            Some(pattern) => {
+                self.pattern_wave
+                    .fill_i16(pattern, self.pitch, buf, sample_rate, on_samples)
+            }
+            None => self.buzzer.fill_i16(buf, sample_rate, on_samples),
+        }
+    }
+
+    /// Apply the configured buzzer pitch and volume. The waves keep
+    /// their phase, so a change does not click.
+    fn update_buzzer(&mut self) {
+        self.buzzer.set_frequency(self.configuration.buzzer_hz);
+        self.buzzer.set_volume(self.configuration.buzzer_volume);
+        self.pattern_wave
+            .set_volume(self.configuration.buzzer_volume);
+    }
+
+    /// Hand the buzzer state to an [`audio::AudioExport`], once per frame
+    #[cfg(feature = "js")]
+    pub fn export_audio(&mut self, export: &mut audio::AudioExport) {
+        self.update_buzzer();
+        let remaining = match self.muted {
+            true => Duration::ZERO,
+            false => self.sound_remaining(),
+        };
+        export.update(remaining, &self.buzzer);
+    }
+
+    /// The XO-CHIP audio pattern loaded with F002, if any.
+    /// Hosts rendering audio on their own play it at [`Emulator::pitch`].
+    pub fn audio_pattern(&self) -> Option<&[u8; 16]> {
+        self.audio_pattern.as_ref()
+    }
+
+    /// The XO-CHIP pitch set with FX3A, see [`audio::pattern_rate`]
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+    /// Copy a region of memory out of the emulator, e.g. to
+    /// implement save points for the variables of a game.
+    /// The range is clamped to the memory bounds.
+    #[cfg(feature = "alloc")]
+    pub fn snapshot_memory(&self, range: Range<u16>) -> MemorySnapshot {
+        self.memory.snapshot(range)
+    }
+
+    /// Copy a region of memory into the given buffer, without allocating.
+    /// Returns the number of bytes copied, which is limited by
+    /// both the memory bounds and the length of the buffer.
+    pub fn snapshot_memory_into(&self, range: Range<u16>, buffer: &mut [u8]) -> usize {
+        self.memory.snapshot_into(range, buffer)
+    }
+
+    /// Write a [`MemorySnapshot`] back to the region it was taken from
+    #[cfg(feature = "alloc")]
+    pub fn restore_memory(&mut self, snapshot: &MemorySnapshot) {
+        self.memory.restore(snapshot);
+    }
+
+    /// A hash over the complete observable machine state: memory,
+    /// registers, I, PC, the call stack and the display.
+    /// Two emulators in the same state always produce the same fingerprint,
+    /// which makes this useful to detect desyncs between instances.
+    pub fn state_fingerprint(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        hasher.write_u64(self.memory.checksum());
+        hasher.write(&self.dump_registers());
+        hasher.write_u16(self.i());
+        hasher.write_u16(self.pc());
+        for address in self.stack.contents() {
+            hasher.write_u16(*address);
+        }
+        hasher.write_u64(self.display.checksum());
+        hasher.finish()
+    }
+
+    /// Copy the memory up to radius bytes around the program counter.
+    /// The window is clamped at both ends of memory.
+    #[cfg(feature = "alloc")]
+    pub fn memory_window(&self, radius: u16) -> MemoryWindow {
+        self.memory.window(self.pc(), radius)
+    }
+
+    /// Decode up to count instructions from the address on,
+    /// stopping at the end of memory
+    pub fn disassemble(
+        &self,
+        start: u16,
+        count: usize,
+    ) -> impl Iterator<Item = (u16, Instruction)> + '_ {
+        (0..count).map_while(move |index| {
+            let address = u16::try_from(start as usize + index * 2).ok()?;
+            let word = self.memory.read_u16(address)?;
+            Some((address, Instruction::decode(word)))
+        })
+    }
+
+    /// The 11 bytes centered on the program counter.
+    /// Bytes outside of memory are reported as 0.
+    pub fn dump_raw_memory_around_pc(&self) -> [u8; 11] {
+        let mut bytes = [0; 11];
+        for (byte, offset) in bytes.iter_mut().zip(-5i16..=5) {
+            if let Some(address) = self.pc().checked_add_signed(offset) {
+                *byte = self.memory.get_u8(address).unwrap_or(0);
+            }
+        }
+        bytes
+    }
+
+    /// The 11 words centered on the program counter.
+    /// Words outside of memory are reported as 0.
+    pub fn dump_double_memory_around_pc(&self) -> [u16; 11] {
+        let mut words = [0; 11];
+        for (word, offset) in words.iter_mut().zip(-5i16..=5) {
+            if let Some(address) = self.pc().checked_add_signed(offset * 2) {
+                *word = self.memory.read_u16(address).unwrap_or(0);
+            }
+        }
+        words
+    }
+
+    /// Everything about the current state for a bug report: the cpu, the call
+    /// stack, the instructions around the program counter, the configuration,
+    /// the timers, the pressed keys and the display. Nothing in it depends on
+    /// the host, so the same state always gives the same dump.
+    #[cfg(feature = "std")]
+    pub fn dump_state(&self) -> String {
+        let mut dump = String::new();
+        // Writing to a string cannot fail
+        let _ = self.write_state(&mut dump);
+        dump
+    }
+
+    #[cfg(feature = "std")]
+    fn write_state(&self, f: &mut impl core::fmt::Write) -> core::fmt::Result {
+        writeln!(f, "== cpu ==")?;
+        write!(f, "{}", self.cpu_snapshot())?;
+        match (&self.state, self.paused) {
+            (RunState::Halted(reason), _) => writeln!(f, "halted: {:?}", reason)?,
+            (_, true) => writeln!(f, "paused")?,
+            (RunState::WaitingForKey { .. }, _) => writeln!(f, "waiting for a key")?,
+            (RunState::Running, _) => writeln!(f, "running")?,
+        }
+        writeln!(f, "ticks: {}", self.tick_count)?;
+
+        writeln!(f, "== call stack ==")?;
+        for return_address in self.call_stack() {
+            let site = return_address.wrapping_sub(2);
+            match self.memory.read_u16(site) {
+                Some(word) => writeln!(f, "{:04X} {}", site, Instruction::decode(word))?,
+                None => writeln!(f, "{:04X} ?", site)?,
+            }
+        }
+
+        writeln!(f, "== disassembly ==")?;
+        let pc = self.pc();
+        let start = pc.saturating_sub(16);
+        let count = (pc - start) as usize / 2 + 9;
+        for (address, instruction) in self.disassemble(start, count) {
+            let arrow = if address == pc { ">" } else { " " };
+            writeln!(f, "{} {:04X} {}", arrow, address, instruction)?;
+        }
+
+        writeln!(f, "== configuration ==")?;
+        write!(f, "{}", self.configuration)?;
+
+        writeln!(f, "== timers ==")?;
+        writeln!(
+            f,
+            "delay: {} ({} ms)",
+            self.delay(),
+            self.delay_remaining().as_millis()
+        )?;
+        writeln!(
+            f,
+            "sound: {} ({} ms)",
+            self.sound(),
+            self.sound_remaining().as_millis()
+        )?;
+
+        writeln!(f, "== keys ==")?;
+        let keys = self.keys();
+        if keys == 0 {
+            writeln!(f, "none pressed")?;
         } else {
-            self.cpu.carry_off();
+            let pressed: Vec<_> = (0..16)
+                .filter(|key| keys & (1 << key) != 0)
+                .map(|key| format!("{:X}", key))
+                .collect();
+            writeln!(f, "pressed: {}", pressed.join(" "))?;
+        }
+
+        writeln!(f, "== display ==")?;
+        self.display.write_half_blocks(f)
+    }
+}
+
+/// A summary of the state, see [`Emulator::dump_state`] for all of it
+impl<C: Clock> core::fmt::Debug for Emulator<C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Emulator")
+            .field("cpu", &self.cpu_snapshot())
+            .field("state", &self.state)
+            .field("paused", &self.paused)
+            .field("tick_count", &self.tick_count)
+            .field("call_stack", &self.call_stack())
+            .field("display_mode", &self.display.mode())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Interpreter
+impl<C: Clock> Emulator<C> {
+    /// Save what the instruction at the program counter can change
+    #[cfg(feature = "alloc")]
+    fn save_undo_step(&self) -> UndoStep {
+        let word = self
+            .memory
+            .read_u16(self.pc())
+            .or_else(|| self.memory.read_u16(self.configuration.start_address));
+        let mut step = UndoStep {
+            cpu: self.cpu,
+            stack: self.stack.top(),
+            memory: None,
+            display: None,
+            rng: None,
+            flags: None,
+            audio: None,
+            tick_count: self.tick_count,
+            instructions_since_timer_step: self.instructions_since_timer_step,
+            mode_switches: self.mode_switches,
+            state: self.state.clone(),
+            last_fault: self.last_fault.clone(),
+            last_draw: self.last_draw,
+            keyboard: self.keyboard.clone(),
+            key_taps: self.key_taps,
+            source_keys: self.source_keys,
+            timer: self.timer.clone(),
+            is_sound_on: self.is_sound_on,
+        };
+        let Some(Instruction(command)) = word.map(Instruction::decode) else {
+            return step;
+        };
+        match command {
+            Command::LoadBcd { .. } => step.memory = Some(self.save_memory_at_i(3)),
+            Command::DumpAll { until_register } => {
+                step.memory = Some(self.save_memory_at_i(until_register as usize + 1))
+            }
+            #[cfg(feature = "xochip")]
+            Command::SaveRegisterRange { first, last } => {
+                step.memory = Some(self.save_memory_at_i(first.abs_diff(last) as usize + 1))
+            }
+            Command::DrawSprite {
+                register_y, value, ..
+            } => {
+                let y = *self.cpu.register(register_y) % self.display.mode().height();
+                let (height, _) = self.sprite_size(value);
+                step.display = Some(self.display.save_rows(y..y.saturating_add(height)));
+            }
+            Command::ClearScreen => step.display = Some(self.display.save_all()),
+            #[cfg(feature = "schip")]
+            Command::SetDisplayMode { .. }
+            | Command::ScrollDown { .. }
+            | Command::ScrollRight
+            | Command::ScrollLeft => step.display = Some(self.display.save_all()),
+            #[cfg(feature = "xochip")]
+            Command::ScrollUp { .. } => step.display = Some(self.display.save_all()),
+            #[cfg(feature = "xochip")]
+            Command::SelectPlanes { .. } => step.display = Some(self.display.save_rows(0..0)),
+            Command::RandomAnd { .. } => step.rng = Some(self.rng),
+            #[cfg(feature = "schip")]
+            Command::SaveFlags { .. } => step.flags = Some(self.flags),
+            #[cfg(feature = "xochip")]
+            Command::LoadAudioPattern | Command::SetPitch { .. } => {
+                step.audio = Some((self.audio_pattern, self.pitch))
+            }
+            _ => (),
+        }
+        step
+    }
+
+    /// The address I points at and up to len of the bytes from there on
+    #[cfg(feature = "alloc")]
+    fn save_memory_at_i(&self, len: usize) -> (u16, u8, [u8; 16]) {
+        let address = self.i_address();
+        let mut bytes = [0; 16];
+        let mut saved = 0;
+        for (offset, byte) in bytes.iter_mut().take(len).enumerate() {
+            match self.memory.get_u8(address.wrapping_add(offset as u16)) {
+                Some(value) => *byte = value,
+                None => break,
+            }
+            saved += 1;
+        }
+        (address, saved, bytes)
+    }
+
+    /// Put back everything the step saved. A flag store keeps
+    /// the flags it was given. The sound events of the step are dropped
+    /// unless they were taken already, and the sink hears about the buzzer.
+    #[cfg(feature = "alloc")]
+    fn restore_undo_step(&mut self, step: UndoStep) {
+        self.cpu = step.cpu;
+        self.stack.restore_top(step.stack);
+        if let Some((address, len, bytes)) = step.memory {
+            for (offset, byte) in bytes[..len as usize].iter().enumerate() {
+                self.memory.write_u8(address + offset as u16, *byte);
+            }
+        }
+        if let Some(display) = &step.display {
+            self.display.restore_rows(display);
+        }
+        if let Some(rng) = step.rng {
+            self.rng = rng;
+        }
+        if let Some(flags) = step.flags {
+            self.flags = flags;
+        }
+        if let Some((audio_pattern, pitch)) = step.audio {
+            self.audio_pattern = audio_pattern;
+            self.pitch = pitch;
+        }
+        self.tick_count = step.tick_count;
+        self.instructions_since_timer_step = step.instructions_since_timer_step;
+        self.mode_switches = step.mode_switches;
+        self.state = step.state;
+        self.last_fault = step.last_fault;
+        self.last_draw = step.last_draw;
+        self.keyboard = step.keyboard;
+        self.key_taps = step.key_taps;
+        self.source_keys = step.source_keys;
+        self.timer = step.timer;
+        while let Some(event) = self.sound_events.last() {
+            if event.tick() <= step.tick_count {
+                break;
+            }
+            self.sound_events.pop();
+        }
+        if step.is_sound_on != self.is_sound_on {
+            self.is_sound_on = step.is_sound_on;
+            if !self.muted {
+                self.notify_sound_sink(step.is_sound_on);
+            }
+        }
+    }
+
+    /// Stop the emulator with the given error
+    fn raise(&mut self, error: Chip8Error) {
+        self.last_fault = Some(error.clone());
+        self.state = RunState::Halted(HaltReason::Fault(error));
+    }
+
+    fn invalid(&mut self, opcode: u16) {
+        let error = DecodeError {
+            pc: self.cpu.pc().wrapping_sub(2),
+            opcode,
+        };
+        #[cfg(feature = "tracing")]
+        tracing::warn!(pc = error.pc, opcode = error.opcode, "invalid opcode");
+        #[cfg(not(feature = "tracing"))]
+        log_warn!("{}", error);
+        match self.configuration.invalid_opcode {
+            InvalidOpcodeStyle::Skip => self.last_fault = Some(error.into()),
+            InvalidOpcodeStyle::Halt => self.raise(error.into()),
+        }
+    }
+
+    /// Run a SUPER-CHIP instruction, if the platform supports it
+    #[cfg(feature = "schip")]
+    fn super_chip(&mut self, opcode: u16, instruction: impl FnOnce(&mut Self)) {
+        if self.configuration.platform.has_super_chip() {
+            instruction(self)
+        } else {
+            self.invalid(opcode)
+        }
+    }
+
+    fn clear_screen(&mut self) {
+        self.display.clear()
+    }
+    #[cfg(feature = "schip")]
+    fn set_display_mode(&mut self, mode: DisplayMode) {
+        if self.display.mode() != mode {
+            self.mode_switches += 1;
+        }
+        self.display.set_mode(mode);
+        if self.configuration.quirks.mode_switch_clears {
+            self.display.clear_all();
+        }
+    }
+    fn return_from_subroutine(&mut self) {
+        if let Some(address) = self.stack.pop() {
+            *self.cpu.pc_mut() = address;
+        } else {
+            let pc = self.cpu.pc().wrapping_sub(2);
+            log_warn!("Stack underflow at {:#05X}", pc);
+            self.raise(Chip8Error::StackUnderflow { pc });
+        }
+    }
+
+    fn call_subroutine(&mut self, address: u16) {
+        if self.stack.push(*self.cpu.pc()) {
+            *self.cpu.pc_mut() = address;
+        } else {
+            let pc = self.cpu.pc().wrapping_sub(2);
+            log_warn!("Stack overflow at {:#05X}", pc);
+            self.raise(Chip8Error::StackOverflow { pc });
+        }
+    }
+
+    fn jump(&mut self, address: u16) {
+        *self.cpu.pc_mut() = address;
+    }
+
+    fn jump_offset(&mut self, address: u16) {
+        self.jump(address.wrapping_add(*self.cpu.register(0) as u16));
+    }
+
+    /// Jump to XNN + VX, where XNN is made up of the register
+    /// nibble and the low byte of the opcode
+    fn jump_offset_variable(&mut self, address: u16, register: u8) {
+        let address = (register as u16) << 8 | (address & 0x0FF);
+        self.jump(address.wrapping_add(*self.cpu.register(register) as u16));
+    }
+
+    /// Step over the next instruction. On XO-CHIP that is the
+    /// whole four bytes of F000 NNNN, so its address is not executed.
+    fn skip_next_instruction(&mut self) {
+        if let Platform::XoChip = self.configuration.platform {
+            if self.memory.read_u16(*self.cpu.pc()) == Some(0xF000) {
+                self.cpu.advance_pc();
+            }
+        }
+        self.cpu.advance_pc();
+    }
+
+    fn skip_if_value_eq(&mut self, register: u8, value: u8) {
+        if *self.cpu.register(register) == value {
+            self.skip_next_instruction();
+        }
+    }
+    fn skip_if_value_neq(&mut self, register: u8, value: u8) {
+        if *self.cpu.register(register) != value {
+            self.skip_next_instruction();
+        }
+    }
+    fn skip_if_registers_eq(&mut self, register_a: u8, register_b: u8) {
+        if *self.cpu.register(register_a) == *self.cpu.register(register_b) {
+            self.skip_next_instruction();
+        }
+    }
+    fn skip_if_registers_neq(&mut self, register_a: u8, register_b: u8) {
+        if *self.cpu.register(register_a) != *self.cpu.register(register_b) {
+            self.skip_next_instruction();
+        }
+    }
+
+    fn skip_if_key_pressed(&mut self, key_register: u8) {
+        if self.is_key_pressed(*self.cpu.register(key_register)) {
+            self.skip_next_instruction();
+        }
+    }
+
+    fn skip_if_key_not_pressed(&mut self, key_register: u8) {
+        if !self.is_key_pressed(*self.cpu.register(key_register)) {
+            self.skip_next_instruction();
+        }
+    }
+
+    fn load(&mut self, register: u8, value: u8) {
+        *self.cpu.register_mut(register) = value;
+    }
+    fn copy_register(&mut self, write: u8, read: u8) {
+        *self.cpu.register_mut(write) = *self.cpu.register(read);
+    }
+    fn load_i(&mut self, value: u16) {
+        *self.cpu.i_mut() = value;
+    }
+    fn load_sprite_key_into_i(&mut self, key_register: u8) {
+        *self.cpu.i_mut() = self.font_sprite_address(*self.cpu.register(key_register));
+    }
+    /// Point I at the large glyph for the lower nibble of the register
+    #[cfg(feature = "schip")]
+    fn load_large_sprite_key_into_i(&mut self, key_register: u8) {
+        let character = *self.cpu.register(key_register) & 0x0F;
+        *self.cpu.i_mut() = self
+            .configuration
+            .font_base
+            .wrapping_add(LARGE_FONT_OFFSET + character as u16 * 10);
+    }
+    /// Wrap an address into the address space, which keeps 12 bits of it
+    /// with the classic 4 KiB and all 16 bits with the 64 KiB of XO-CHIP
+    fn wrap_address(&self, address: usize) -> u16 {
+        (address % self.memory.len()) as u16
+    }
+    /// The address I points at, wrapped into the address space
+    fn i_address(&self) -> u16 {
+        self.wrap_address(*self.cpu.i() as usize)
+    }
+    /// Make sure len bytes starting at address lie inside of memory,
+    /// otherwise the emulator halts with [`Chip8Error::MemoryOutOfBounds`].
+    fn check_memory_range(&mut self, address: u16, len: usize) -> bool {
+        let end = address as usize + len;
+        if end <= self.memory.len() {
+            return true;
+        }
+        let pc = self.cpu.pc().wrapping_sub(2);
+        log_warn!(
+            "Memory access out of bounds at {:#05X}, accessing {} bytes from {:#05X}",
+            pc,
+            len,
+            address
+        );
+        self.raise(Chip8Error::MemoryOutOfBounds { pc, address });
+        false
+    }
+
+    /// Read a byte for an instruction, logging the access if asked to
+    fn read_memory(&mut self, address: u16) -> u8 {
+        let value = self.memory.read_u8(address);
+        #[cfg(feature = "alloc")]
+        if self.access_log.is_some() {
+            self.log_access(address, AccessKind::Read, value);
+        }
+        value
+    }
+
+    /// Write a byte for an instruction, logging the access if asked to
+    fn write_memory(&mut self, address: u16, value: u8) {
+        self.memory.write_u8(address, value);
+        #[cfg(feature = "alloc")]
+        if self.access_log.is_some() {
+            self.log_access(address, AccessKind::Write, value);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn log_access(&mut self, address: u16, kind: AccessKind, value: u8) {
+        let access = MemoryAccess {
+            tick: self.tick_count,
+            pc: self.cpu.pc().wrapping_sub(2),
+            address,
+            kind,
+            value,
+        };
+        if let Some(log) = &mut self.access_log {
+            log.record(access);
+        }
+    }
+
+    fn load_bcd(&mut self, read: u8) {
+        let value = *self.cpu.register(read);
+        let address = self.i_address();
+        if !self.check_memory_range(address, 3) {
+            return;
+        }
+        self.write_memory(address, value / 100);
+        self.write_memory(address + 1, (value / 10) % 10);
+        self.write_memory(address + 2, value % 10);
+    }
+    fn add(&mut self, register: u8, value: u8) {
+        *self.cpu.register_mut(register) = self.cpu.register(register).wrapping_add(value);
+    }
+    /// The arithmetic instructions write their result first and the flag last,
+    /// so when VF is the destination it ends up holding only the flag.
+    fn add_registers(&mut self, write: u8, read: u8) {
+        let a = *self.cpu.register(write);
+        let b = *self.cpu.register(read);
+        let (result, carry) = a.overflowing_add(b);
+        *self.cpu.register_mut(write) = result;
+        self.cpu.set_carry(carry);
+    }
+    /// Add to I, wrapping around inside of the address space.
+    /// Some interpreters also set VF when I leaves the address space.
+    fn add_i(&mut self, register: u8) {
+        let sum = *self.cpu.i() as usize + *self.cpu.register(register) as usize;
+        *self.cpu.i_mut() = self.wrap_address(sum);
+        if self.configuration.quirks.index_add_sets_flag {
+            if sum >= self.memory.len() {
+                self.cpu.carry_on();
+            } else {
+                self.cpu.carry_off();
+            }
+        }
+    }
+
+    fn or(&mut self, write: u8, read: u8) {
+        *self.cpu.register_mut(write) |= *self.cpu.register(read);
+        self.reset_vf_after_logic();
+    }
+    fn and(&mut self, write: u8, read: u8) {
+        *self.cpu.register_mut(write) &= *self.cpu.register(read);
+        self.reset_vf_after_logic();
+    }
+    fn reset_vf_after_logic(&mut self) {
+        if self.configuration.quirks.logic_resets_vf {
+            self.cpu.carry_off();
+        }
+    }
+    fn random_and(&mut self, register: u8, value: u8) {
+        *self.cpu.register_mut(register) = value & (self.rng.rand_u32() >> 24) as u8;
+    }
+    fn xor(&mut self, write: u8, read: u8) {
+        *self.cpu.register_mut(write) ^= *self.cpu.register(read);
+        self.reset_vf_after_logic();
+    }
+    fn sub(&mut self, write: u8, read: u8) {
+        let a = *self.cpu.register(write);
+        let b = *self.cpu.register(read);
+        self.subtract_into(write, a, b);
+    }
+    fn sub_inverse(&mut self, write: u8, read: u8) {
+        let a = *self.cpu.register(write);
+        let b = *self.cpu.register(read);
+        self.subtract_into(write, b, a);
+    }
+    /// Store a - b in the write register, wrapping around on underflow.
+    /// VF is set to 1 if there was *no* borrow (a >= b), so equal
+    /// operands also set it. The flag is written last.
+    fn subtract_into(&mut self, write: u8, a: u8, b: u8) {
+        let result = a.wrapping_sub(b);
+        let no_borrow = a >= b;
+        *self.cpu.register_mut(write) = result;
+        self.cpu.set_carry(no_borrow);
+    }
+
+    /// Shifting is ambiguous, older versions copied over the value
+    /// from the read register to the write register, while newer
+    /// versions shift in-place the given register.
+    fn shift_right(&mut self, write: u8, read: u8) {
+        self.copy_register(write, read);
+        self.shift_right_in_place(write);
+    }
+
+    fn shift_right_in_place(&mut self, register: u8) {
+        let rightmost = *self.cpu.register(register) & 1;
+        *self.cpu.register_mut(register) >>= 1;
+        if rightmost == 1 {
+            self.cpu.carry_on();
+        } else {
+            self.cpu.carry_off();
+        }
+    }
+
+    fn shift_left(&mut self, write: u8, read: u8) {
+        self.copy_register(write, read);
+        self.shift_left_in_place(write);
+    }
+
+    fn shift_left_in_place(&mut self, register: u8) {
+        let leftmost = *self.cpu.register(register) & 0b1000_0000;
+        *self.cpu.register_mut(register) <<= 1;
+        if leftmost == 0b1000_0000 {
+            self.cpu.carry_on();
+        } else {
+            self.cpu.carry_off();
+        }
+    }
+
+    /// Load V0..=VX from consecutive addresses starting at I,
+    /// then move I forward by the given increment
+    fn load_all(&mut self, until_register: u8, i_increment: u8) {
+        let start_address = self.i_address();
+        if !self.check_memory_range(start_address, until_register as usize + 1) {
+            return;
+        }
+        for i in 0..=until_register {
+            *self.cpu.register_mut(i) = self.read_memory(start_address + i as u16);
+        }
+        *self.cpu.i_mut() = self.wrap_address(start_address as usize + i_increment as usize);
+    }
+
+    /// Dump V0..=VX to consecutive addresses starting at I,
+    /// then move I forward by the given increment
+    fn dump_all(&mut self, until_register: u8, i_increment: u8) {
+        let start_address = self.i_address();
+        if !self.check_memory_range(start_address, until_register as usize + 1) {
+            return;
+        }
+        for i in 0..=until_register {
+            self.write_memory(start_address + i as u16, *self.cpu.register(i));
+        }
+        *self.cpu.i_mut() = self.wrap_address(start_address as usize + i_increment as usize);
+    }
+
+    fn draw(&mut self, register_x: u8, register_y: u8, value: u8) {
+        let mode = self.display.mode();
+        let x = *self.cpu.register(register_x) % mode.width();
+        let y = *self.cpu.register(register_y) % mode.height();
+        let (height, width) = self.sprite_size(value);
+        let bytes_per_row = width as u16 / 8;
+        let start_address = self.i_address();
+        // Every selected plane draws its own sprite, stored one after the other
+        let planes = self.display.selected_planes();
+        let sprite_len = height as usize * bytes_per_row as usize;
+        let sprites_len = sprite_len * DisplayBuffer::planes_in(planes).count();
+        if !self.check_memory_range(start_address, sprites_len) {
+            return;
+        }
+        let visible_rows = height.min(mode.height() - y);
+        let clipped_rows = height - visible_rows;
+        // One bit per sprite row, set if the row collided on any plane
+        let mut colliding = 0u16;
+        let mut colliding_planes = 0u8;
+
+        for (index, plane) in DisplayBuffer::planes_in(planes).enumerate() {
+            let sprite_address = start_address + (index * sprite_len) as u16;
+            for y_offset in 0..visible_rows {
+                let address = sprite_address + y_offset as u16 * bytes_per_row;
+                let sprite_row = match width {
+                    16 => u16::from_be_bytes([
+                        self.read_memory(address),
+                        self.read_memory(address + 1),
+                    ]),
+                    _ => (self.read_memory(address) as u16) << 8,
+                };
+                if self.draw_sprite_row(plane, x, y + y_offset, sprite_row, width) {
+                    colliding |= 1 << y_offset;
+                    colliding_planes |= 1 << plane;
+                }
+            }
+        }
+        let colliding_rows = colliding.count_ones() as u8;
+
+        let collision = match (mode, self.configuration.quirks.collision) {
+            (DisplayMode::LowRes, _) | (DisplayMode::HighRes, CollisionStyle::Flag) => {
+                (colliding_rows > 0) as u8
+            }
+            (DisplayMode::HighRes, CollisionStyle::Rows) => colliding_rows,
+            (DisplayMode::HighRes, CollisionStyle::RowsAndClipped) => colliding_rows + clipped_rows,
+        };
+        *self.cpu.register_mut(0xF) = collision;
+        self.last_draw = Some(DrawInfo {
+            x,
+            y,
+            planes,
+            collisions: colliding_planes,
+        });
+    }
+
+    /// The rows and columns of the sprite DXYN draws
+    fn sprite_size(&self, n: u8) -> (u8, u8) {
+        if n != 0 || !self.configuration.platform.has_super_chip() {
+            return (n, 8);
+        }
+        match (self.display.mode(), self.configuration.quirks.lores_dxy0) {
+            (DisplayMode::HighRes, _) | (DisplayMode::LowRes, LoresSpriteStyle::Large) => (16, 16),
+            (DisplayMode::LowRes, LoresSpriteStyle::Tall) => (16, 8),
+            (DisplayMode::LowRes, LoresSpriteStyle::Nothing) => (0, 8),
+        }
+    }
+
+    /// Flip the pixels of a sprite row that are set, starting from the highest bit.
+    /// Pixels past the right edge are clipped.
+    /// Returns whether any pixel was turned off.
+    fn draw_sprite_row(&mut self, plane: usize, x: u8, y: u8, sprite_row: u16, width: u8) -> bool {
+        let mut did_turn_off_pixel = false;
+        for x_offset in 0..width {
+            let x_pos = x as u16 + x_offset as u16;
+            if x_pos >= self.display.mode().width() as u16 {
+                break;
+            }
+            if sprite_row & (0x8000 >> x_offset) != 0 {
+                did_turn_off_pixel |= self.display.flip_pixel(plane, x_pos as u8, y);
+            }
+        }
+        did_turn_off_pixel
+    }
+
+    /// Store the lowest pressed key in the register. Until a key is pressed (or
+    /// released, depending on the [`WaitKeyStyle`]), the program counter
+    /// stays on this instruction so it is retried on the next tick.
+    fn wait_key(&mut self, key_register: u8) {
+        let key = match self.configuration.key_input {
+            KeyInputStyle::Level => self.wait_key_level(),
+            KeyInputStyle::Events => self.wait_key_event(),
+        };
+
+        match key {
+            Some(key) => {
+                *self.cpu.register_mut(key_register) = key;
+                self.state = RunState::Running;
+            }
+            None => {
+                if !matches!(self.state, RunState::WaitingForKey { .. }) {
+                    self.state = RunState::WaitingForKey { latched: None };
+                }
+                self.cpu.rollback_pc();
+            }
+        }
+    }
+
+    /// The key completing FX0A based on the keys currently pressed.
+    /// For [`WaitKeyStyle::OnRelease`] the key going down is latched first.
+    fn wait_key_level(&mut self) -> Option<u8> {
+        let pressed = self.first_pressed_key();
+        match self.configuration.quirks.wait_key {
+            WaitKeyStyle::OnPress => pressed,
+            WaitKeyStyle::OnRelease => {
+                let latched = match self.state {
+                    RunState::WaitingForKey { latched } => latched,
+                    _ => None,
+                };
+                let released = latched.filter(|key| !self.is_key_pressed(*key));
+                if released.is_none() {
+                    self.state = RunState::WaitingForKey {
+                        latched: latched.or(pressed),
+                    };
+                }
+                released
+            }
+        }
+    }
+
+    /// The key completing FX0A from the queued key events.
+    /// Events of the other direction are dropped on the way.
+    fn wait_key_event(&mut self) -> Option<u8> {
+        while let Some(event) = self.keyboard.pop_event() {
+            match (&self.configuration.quirks.wait_key, event) {
+                (WaitKeyStyle::OnPress, KeyEvent::Down(key))
+                | (WaitKeyStyle::OnRelease, KeyEvent::Up(key)) => return Some(key),
+                _ => continue,
+            }
+        }
+        None
+    }
+
+    /// The last register FX75 and FX85 can reach: SUPER-CHIP only has
+    /// 8 flags and clamps higher registers, XO-CHIP has all 16
+    #[cfg(feature = "schip")]
+    fn last_flag_register(&self, register: u8) -> u8 {
+        match self.configuration.platform {
+            Platform::XoChip => register,
+            _ => register.min(7),
+        }
+    }
+
+    #[cfg(feature = "schip")]
+    fn save_flags(&mut self, until_register: u8) {
+        for register in 0..=self.last_flag_register(until_register) {
+            self.flags[register as usize] = *self.cpu.register(register);
+        }
+        #[cfg(feature = "alloc")]
+        if let Some(store) = &mut self.flag_store {
+            store.save(&self.flags);
+        }
+    }
+
+    #[cfg(feature = "schip")]
+    fn load_flags(&mut self, until_register: u8) {
+        for register in 0..=self.last_flag_register(until_register) {
+            *self.cpu.register_mut(register) = self.flags[register as usize];
+        }
+    }
+
+    fn load_delay(&mut self, register: u8) {
+        *self.cpu.register_mut(register) = *self.cpu.delay();
+    }
+
+    fn set_delay(&mut self, register: u8) {
+        *self.cpu.delay_mut() = *self.cpu.register(register);
+    }
+
+    fn set_sound(&mut self, register: u8) {
+        *self.cpu.sound_mut() = *self.cpu.register(register);
+        self.update_sound_state();
+    }
+    /// The registers from first to last, counting down if last is lower,
+    /// with their offsets from I
+    #[cfg(feature = "xochip")]
+    fn register_range(first: u8, last: u8) -> impl Iterator<Item = (u16, u8)> {
+        let count = first.abs_diff(last) + 1;
+        (0..count).map(move |offset| {
+            let register = if first <= last {
+                first + offset
+            } else {
+                first - offset
+            };
+            (offset as u16, register)
+        })
+    }
+    /// Save the registers to consecutive addresses starting at I, leaving I unchanged
+    #[cfg(feature = "xochip")]
+    fn save_register_range(&mut self, first: u8, last: u8) {
+        let start_address = self.i_address();
+        if !self.check_memory_range(start_address, first.abs_diff(last) as usize + 1) {
+            return;
+        }
+        for (offset, register) in Self::register_range(first, last) {
+            self.write_memory(start_address + offset, *self.cpu.register(register));
+        }
+    }
+    /// Load the registers from consecutive addresses starting at I, leaving I unchanged
+    #[cfg(feature = "xochip")]
+    fn load_register_range(&mut self, first: u8, last: u8) {
+        let start_address = self.i_address();
+        if !self.check_memory_range(start_address, first.abs_diff(last) as usize + 1) {
+            return;
+        }
+        for (offset, register) in Self::register_range(first, last) {
+            *self.cpu.register_mut(register) = self.read_memory(start_address + offset);
+        }
+    }
+    /// Load I from the word after F000 and step over it
+    #[cfg(feature = "xochip")]
+    fn load_long_i(&mut self) {
+        let pc = *self.cpu.pc();
+        let Some(address) = self.memory.read_u16(pc) else {
+            log_warn!("Missing address of F000 at {:#05X}", pc.wrapping_sub(2));
+            self.raise(Chip8Error::PcOutOfRange { pc });
+            return;
+        };
+        *self.cpu.i_mut() = self.wrap_address(address as usize);
+        self.cpu.advance_pc();
+    }
+    #[cfg(feature = "xochip")]
+    fn load_audio_pattern(&mut self) {
+        let start_address = self.i_address();
+        let mut pattern = [0; 16];
+        if !self.check_memory_range(start_address, pattern.len()) {
+            return;
+        }
+        for (offset, byte) in pattern.iter_mut().enumerate() {
+            *byte = self.read_memory(start_address + offset as u16);
+        }
+        self.audio_pattern = Some(pattern);
+    }
+    #[cfg(feature = "xochip")]
+    fn set_pitch(&mut self, register: u8) {
+        self.pitch = *self.cpu.register(register);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::{QuirkSet, DEFAULT_FONT_BASE, ETI660_START_ADDRESS},
+        memory::{CHIP8_START, MEMORY_SIZE},
+    };
+
+    use super::*;
+
+    #[test]
+    fn can_jump() {
+        let mut emulator = Emulator::new();
+        emulator.memory.write_u16(CHIP8_START as u16, 0x1300);
+
+        assert_eq!(CHIP8_START as u16, *emulator.cpu.pc());
+        emulator.tick();
+        assert_eq!(0x0300, *emulator.cpu.pc());
+    }
+
+    #[test]
+    fn jump_offset_follows_style() {
+        for (style, target) in [
+            (JumpOffsetStyle::OffsetFromV0, 0x0321),
+            (JumpOffsetStyle::OffsetVariable, 0x0330),
+        ] {
+            let mut emulator = Emulator::new();
+            emulator.configuration.quirks.jump = style;
+            emulator.memory.write_u16(CHIP8_START as u16, 0xB320);
+            *emulator.cpu.register_mut(0) = 0x01;
+            *emulator.cpu.register_mut(3) = 0x10;
+            emulator.tick();
+            assert_eq!(target, *emulator.cpu.pc(), "{:?}", style);
+        }
+    }
+
+    #[test]
+    fn can_skip_instructions() {
+        let mut emulator = Emulator::new();
+        let ptr_start = CHIP8_START as u16;
+        emulator.memory.write_u16(ptr_start, 0x3012);
+        *emulator.cpu.register_mut(0) = 0x12;
+
+        // Value equals value stored in register 0
+        assert_eq!(ptr_start, *emulator.cpu.pc());
+        emulator.tick();
+        assert_eq!(ptr_start + 4, *emulator.cpu.pc());
+
+        // Value not equals value stored in register 0
+        emulator.memory.write_u16(ptr_start + 4, 0x4005);
+        emulator.tick();
+        assert_eq!(ptr_start + 8, *emulator.cpu.pc());
+
+        // Values stored in registers 0 and 1 are equal
+        emulator.memory.write_u16(ptr_start + 8, 0x5010);
+        *emulator.cpu.register_mut(1) = 0x12;
+        emulator.tick();
+        assert_eq!(ptr_start + 12, *emulator.cpu.pc());
+
+        // Values stored in registers 0 and 1 are not equal
+        emulator.memory.write_u16(ptr_start + 12, 0x9010);
+        *emulator.cpu.register_mut(0) = 0x11;
+        emulator.tick();
+        assert_eq!(ptr_start + 16, *emulator.cpu.pc());
+    }
+
+    #[test]
+    fn can_load() {
+        let mut emulator = Emulator::new();
+        let ptr = CHIP8_START as u16;
+        emulator.memory.write_u16(ptr, 0x6012);
+
+        // Load 0x12 into register 0
+        assert_ne!(*emulator.cpu.register(0), 0x12);
+        emulator.tick();
+        assert_eq!(*emulator.cpu.register(0), 0x12);
+
+        // Copy the content of register 0 into register 5
+        emulator.memory.write_u16(ptr + 2, 0x8500);
+        emulator.tick();
+        assert_eq!(*emulator.cpu.register(5), 0x12);
+
+        // Load 0x0300 into register I
+        emulator.memory.write_u16(ptr + 4, 0xA300);
+        emulator.tick();
+        assert_eq!(*emulator.cpu.i(), 0x0300);
+    }
+
+    #[test]
+    fn can_access_registers_by_name() {
+        let mut emulator = Emulator::new();
+        emulator.memory.write_u16(CHIP8_START as u16, 0x8E30);
+        emulator.set_register(Register::V3, 0x42);
+
+        emulator.tick();
+        assert_eq!(0x42, emulator.get_register(Register::VE));
+        assert_eq!(0x42, emulator.dump_registers()[0xE]);
+    }
+
+    #[test]
+    fn can_snapshot_cpu() {
+        let mut emulator = Emulator::new();
+        emulator.memory.write_u16(CHIP8_START as u16, 0x2300);
+        emulator.memory.write_u16(0x300, 0x6A12);
+        emulator.tick();
+
+        let before = emulator.cpu_snapshot();
+        assert_eq!(0x300, before.pc);
+        assert_eq!(1, before.sp);
+
+        emulator.tick();
+        let after = emulator.cpu_snapshot();
+        let mut expected = before;
+        expected.v[0xA] = 0x12;
+        expected.pc = 0x302;
+        assert_eq!(expected, after);
+    }
+
+    #[test]
+    fn can_set_pc_and_i() {
+        let mut emulator = Emulator::new();
+        emulator.memory.write_u16(0x400, 0xF01E);
+        *emulator.cpu.register_mut(0) = 0x02;
+
+        assert_eq!(Ok(()), emulator.set_pc(0x400));
+        assert_eq!(Ok(()), emulator.set_i(0x300));
+        emulator.tick();
+        assert_eq!(0x402, emulator.pc());
+        assert_eq!(0x302, emulator.i());
+
+        assert_eq!(Err(InvalidAddress(0xFFF)), emulator.set_pc(0xFFF));
+        assert_eq!(Err(InvalidAddress(0x1000)), emulator.set_i(0x1000));
+        assert_eq!(0x402, emulator.pc());
+        assert_eq!(0x302, emulator.i());
+    }
+
+    #[test]
+    fn can_set_key_mask() {
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x03, // Load 3 into v0
+            0x61, 0x0C, // Load 0xC into v1
+            0xE0, 0x9E, // Skip next instruction if key in v0 is pressed
+            0x00, 0x00,
+            0xE1, 0x9E, // Skip next instruction if key in v1 is pressed
+            0x00, 0x00,
+            0xF2, 0x0A, // Wait for a key and store it in v2
+        ];
+        let mut emulator = Emulator::new().with_rom(&rom).unwrap();
+        emulator.set_keys(1 << 0x3 | 1 << 0xC);
+        assert_eq!(1 << 0x3 | 1 << 0xC, emulator.keys());
+
+        for _ in 0..4 {
+            emulator.tick();
+        }
+        assert_eq!(0x20C, emulator.pc());
+
+        emulator.set_keys(0);
+        assert_eq!(Ok(TickOutcome::WaitingForKey), emulator.try_tick());
+        emulator.set_keys(1 << 0x5 | 1 << 0xC);
+        emulator.tick();
+        assert_eq!(0x5, *emulator.cpu.register(2));
+        assert_eq!(0x20E, emulator.pc());
+    }
+
+    /// Presses a key once it was polled a number of times
+    #[cfg(feature = "alloc")]
+    struct ScriptedKeys<F: Fn(u32, u8) -> bool> {
+        polls: u32,
+        is_pressed: F,
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<F: Fn(u32, u8) -> bool> KeySource for ScriptedKeys<F> {
+        fn is_pressed(&self, key: u8) -> bool {
+            (self.is_pressed)(self.polls, key)
+        }
+
+        fn poll(&mut self) {
+            self.polls += 1;
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn can_read_keys_from_source() {
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x09, // Load 9 into v0
+            0xE0, 0xA1, // 0x202: Skip next instruction if key in v0 is not pressed
+            0x12, 0x08, // Jump to 0x208
+            0x12, 0x02, // Jump back to 0x202
+            0xF1, 0x0A, // 0x208: Wait for a key and store it in v1
+        ];
+        let mut emulator = Emulator::new().with_rom(&rom).unwrap();
+        emulator.set_key_source(ScriptedKeys {
+            polls: 0,
+            is_pressed: |polls, key| polls >= 6 && key == 0x9,
+        });
+        emulator.press_key(0x3);
+
+        for _ in 0..5 {
+            emulator.tick();
+            assert_ne!(0x208, emulator.pc());
+        }
+        emulator.tick();
+        emulator.tick();
+        assert_eq!(0x208, emulator.pc());
+        assert_eq!(Some(0x9), emulator.first_pressed_key());
+        emulator.tick();
+        assert_eq!(0x9, *emulator.cpu.register(1));
+
+        emulator.clear_key_source();
+        assert_eq!(Some(0x3), emulator.first_pressed_key());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn key_sources_feed_key_events() {
+        // Wait for a key and store it in v1
+        let mut emulator = Emulator::new().with_rom(&[0xF1, 0x0A]).unwrap();
+        emulator.configuration.key_input = KeyInputStyle::Events;
+        emulator.configuration.quirks.wait_key = WaitKeyStyle::OnRelease;
+        emulator.set_key_source(ScriptedKeys {
+            polls: 0,
+            is_pressed: |polls, key| (3..5).contains(&polls) && key == 0xB,
+        });
+        emulator.press_key(0x3);
+
+        for _ in 0..3 {
+            emulator.tick();
+        }
+        assert_eq!(1 << 0xB, emulator.keys());
+        assert!(emulator.is_waiting_for_key());
+        emulator.tick();
+        emulator.tick();
+        assert_eq!(0, emulator.keys());
+        assert_eq!(0xB, *emulator.cpu.register(1));
+
+        emulator.clear_key_source();
+        assert_eq!(1 << 0x3, emulator.keys());
+    }
+
+    #[test]
+    fn tapped_keys_are_released() {
+        // Load 5 into v0, followed by skips if the key in v0 is pressed
+        let mut rom = vec![0x60, 0x05];
+        rom.extend([0xE0, 0x9E].repeat(16));
+        let mut emulator = Emulator::new().with_rom(&rom).unwrap();
+        emulator.tick();
+
+        assert!(emulator.tap_key(0x5, 3));
+        for _ in 0..3 {
+            let pc = emulator.pc();
+            emulator.tick();
+            assert_eq!(pc + 4, emulator.pc());
+        }
+        let pc = emulator.pc();
+        emulator.tick();
+        assert_eq!(pc + 2, emulator.pc());
+        assert!(!emulator.any_key_pressed());
+    }
+
+    #[test]
+    fn overlapping_taps_hold_the_key() {
+        let mut emulator = Emulator::new().with_rom(&[0x12, 0x00]).unwrap();
+        assert!(emulator.tap_key(0x5, 1));
+        assert!(emulator.tap_key(0x5, 3));
+        assert!(emulator.tap_key(0x6, 2));
+        for _ in 0..3 {
+            emulator.tick();
+        }
+        assert_eq!(1 << 0x5, emulator.keys());
+        emulator.tick();
+        assert_eq!(0, emulator.keys());
+
+        for _ in 0..MAX_KEY_TAPS {
+            assert!(emulator.tap_key(0x1, 1));
+        }
+        assert!(!emulator.tap_key(0x1, 1));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn recorded_input_replays_identically() {
+        #[rustfmt::skip]
+        let rom = [
+            0xF0, 0x0A, // 0x200: Wait for a key and store it in v0
+            0xF0, 0x29, // Point I to the font sprite of v0
+            0xD1, 0x15, // Draw the sprite at v1, v1
+            0x71, 0x05, // Add 5 to v1
+            0x12, 0x00, // Jump back to 0x200
+        ];
+        let mut emulator = Emulator::new().with_rom(&rom).unwrap();
+        emulator.tick();
+        emulator.start_input_recording();
+        let run = |emulator: &mut Emulator, ticks| {
+            for _ in 0..ticks {
+                emulator.tick();
+            }
+        };
+        run(&mut emulator, 5);
+        emulator.press_key(0x3);
+        run(&mut emulator, 3);
+        emulator.release_key(0x3);
+        run(&mut emulator, 4);
+        emulator.set_keys(1 << 0xA);
+        run(&mut emulator, 6);
+        emulator.set_keys(0);
+        run(&mut emulator, 5);
+        let recording = emulator.take_input_recording();
+        assert_eq!(
+            &[
+                InputEntry {
+                    tick: 5,
+                    event: KeyEvent::Down(0x3)
+                },
+                InputEntry {
+                    tick: 8,
+                    event: KeyEvent::Up(0x3)
+                },
+                InputEntry {
+                    tick: 12,
+                    event: KeyEvent::Down(0xA)
+                },
+                InputEntry {
+                    tick: 18,
+                    event: KeyEvent::Up(0xA)
+                },
+            ],
+            recording.entries()
+        );
+
+        let mut replay = Emulator::new().with_rom(&rom).unwrap();
+        replay.tick();
+        replay.replay_input(&recording, 23);
+        assert_ne!(DisplayBuffer::new().checksum(), emulator.display.checksum());
+        assert_eq!(emulator.display.checksum(), replay.display.checksum());
+        assert_eq!(emulator.state_fingerprint(), replay.state_fingerprint());
+    }
+
+    #[test]
+    fn can_press_mapped_chars() {
+        let mut emulator = Emulator::new();
+        assert!(emulator.press_char('v'));
+        assert!(emulator.press_char('Q'));
+        assert!(!emulator.press_char('p'));
+        assert_eq!(1 << 0xF | 1 << 0x4, emulator.keys());
+
+        emulator.set_key_map(KeyMap::azerty());
+        assert!(emulator.release_char('a'));
+        assert_eq!(1 << 0xF, emulator.keys());
+    }
+
+    #[test]
+    fn out_of_range_keys_are_masked() {
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x13, // Load 0x13 into v0
+            0xE0, 0x9E, // Skip next instruction if key in v0 is pressed
+            0x61, 0x01, // Load 1 into v1
+            0xF2, 0x0A, // Wait for a key and store it in v2
+        ];
+        let mut emulator = Emulator::new().with_rom(&rom).unwrap();
+        emulator.press_key(0x10);
+        emulator.release_key(0xFF);
+        emulator.press_key(0x03);
+
+        emulator.tick();
+        emulator.tick();
+        assert_eq!(0x206, *emulator.cpu.pc());
+        assert_eq!(0, *emulator.cpu.register(1));
+
+        emulator.set_keys(0);
+        emulator.tick();
+        emulator.press_key(0xFA);
+        emulator.tick();
+        assert_eq!(0xA, *emulator.cpu.register(2));
+    }
+
+    #[test]
+    fn wait_key_can_complete_on_release() {
+        for style in [WaitKeyStyle::OnPress, WaitKeyStyle::OnRelease] {
+            let is_on_release = matches!(style, WaitKeyStyle::OnRelease);
+            let mut emulator = Emulator::new();
+            emulator.configuration.quirks.wait_key = style;
+            emulator.load_rom(&[0xF3, 0x0A]).unwrap();
+            assert_eq!(Ok(TickOutcome::WaitingForKey), emulator.try_tick());
+
+            emulator.press_key(0x4);
+            if is_on_release {
+                for _ in 0..5 {
+                    assert_eq!(Ok(TickOutcome::WaitingForKey), emulator.try_tick());
+                }
+                // Other keys going down do not replace the latched key
+                emulator.press_key(0x2);
+                assert_eq!(Ok(TickOutcome::WaitingForKey), emulator.try_tick());
+                emulator.release_key(0x4);
+            }
+            assert_eq!(Ok(TickOutcome::Executed), emulator.try_tick());
+            assert_eq!(0x4, *emulator.cpu.register(3));
+            assert_eq!(0x202, emulator.pc());
+        }
+    }
+
+    #[test]
+    fn wait_key_follows_quirk_changes() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.quirks.wait_key = WaitKeyStyle::OnRelease;
+        emulator.load_rom(&[0xF3, 0x0A, 0xF3, 0x0A]).unwrap();
+        emulator.press_key(0x4);
+        assert_eq!(Ok(TickOutcome::WaitingForKey), emulator.try_tick());
+
+        // The latched key does not keep the wait going after the change
+        emulator.configuration.quirks.wait_key = WaitKeyStyle::OnPress;
+        assert_eq!(Ok(TickOutcome::Executed), emulator.try_tick());
+        assert_eq!(0x4, *emulator.cpu.register(3));
+
+        emulator.configuration.quirks.wait_key = WaitKeyStyle::OnRelease;
+        assert_eq!(Ok(TickOutcome::WaitingForKey), emulator.try_tick());
+        emulator.release_key(0x4);
+        assert_eq!(Ok(TickOutcome::Executed), emulator.try_tick());
+        assert_eq!(0x204, emulator.pc());
+    }
+
+    #[test]
+    fn key_events_satisfy_a_single_wait() {
+        #[rustfmt::skip]
+        let rom = [
+            0xF3, 0x0A, // Wait for a key and store it in v3
+            0xF4, 0x0A, // Wait for a key and store it in v4
+            0xE4, 0x9E, // Skip next instruction if key in v4 is pressed
+        ];
+        let mut emulator = Emulator::new();
+        emulator.configuration.key_input = KeyInputStyle::Events;
+        emulator.load_rom(&rom).unwrap();
+
+        emulator.push_key_event(KeyEvent::Down(0x6));
+        assert_eq!(Ok(TickOutcome::Executed), emulator.try_tick());
+        assert_eq!(0x6, *emulator.cpu.register(3));
+
+        // The key is still held, but its press was used up
+        for _ in 0..3 {
+            assert_eq!(Ok(TickOutcome::WaitingForKey), emulator.try_tick());
+            assert_eq!(0x202, emulator.pc());
+        }
+
+        emulator.release_key(0x6);
+        emulator.press_key(0x9);
+        emulator.tick();
+        assert_eq!(0x9, *emulator.cpu.register(4));
+        emulator.tick();
+        assert_eq!(0x208, emulator.pc());
+    }
+
+    #[test]
+    fn old_key_events_do_not_satisfy_a_wait() {
+        let rom = [0xF3, 0x0A]; // Wait for a key and store it in v3
+        let mut emulator = Emulator::new();
+        emulator.configuration.key_input = KeyInputStyle::Events;
+        emulator.load_rom(&[0x12, 0x00]).unwrap();
+        emulator.press_key(0x5);
+        emulator.release_key(0x5);
+        emulator.tick();
+
+        // Pressed while the previous rom ran
+        emulator.load_rom(&rom).unwrap();
+        assert_eq!(Ok(TickOutcome::WaitingForKey), emulator.try_tick());
+        assert_eq!(0, emulator.get_register(Register::V3));
+
+        // Pressed while events were not used
+        emulator.configuration.key_input = KeyInputStyle::Level;
+        emulator.load_rom(&rom).unwrap();
+        emulator.press_key(0x5);
+        emulator.set_keys(0);
+        emulator.configuration.key_input = KeyInputStyle::Events;
+        assert_eq!(Ok(TickOutcome::WaitingForKey), emulator.try_tick());
+        assert_eq!(0, emulator.get_register(Register::V3));
+    }
+
+    #[test]
+    fn reports_waiting_for_key() {
+        let mut emulator = Emulator::new().with_rom(&[0xF3, 0x0A]).unwrap();
+        assert!(!emulator.is_waiting_for_key());
+        emulator.tick();
+        assert!(emulator.is_waiting_for_key());
+
+        emulator.press_key(0x1);
+        emulator.tick();
+        assert!(!emulator.is_waiting_for_key());
+    }
+
+    #[test]
+    fn reports_idle_on_delay() {
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x02, // Load 2 into v0
+            0xF0, 0x15, // Set the delay timer to v0
+            0xF1, 0x07, // 0x204: Load the delay timer into v1
+            0x31, 0x00, // Skip the next instruction if v1 is 0
+            0x12, 0x04, // Jump back to 0x204
+            0x62, 0x01, // Load 1 into v2
+        ];
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.load_rom(&rom).unwrap();
+        emulator.tick();
+        assert!(!emulator.is_idle_on_delay());
+
+        for _ in 0..5 {
+            emulator.tick();
+            assert!(emulator.is_idle_on_delay());
+        }
+
+        emulator.advance_time_ms(100);
+        for _ in 0..4 {
+            emulator.tick();
+        }
+        assert_eq!(0x20A, emulator.pc());
+        assert!(!emulator.is_idle_on_delay());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn detects_spinning_roms() {
+        let mut emulator = Emulator::new();
+        emulator
+            .load_rom(include_bytes!("../roms/IBM_Logo.ch8"))
+            .unwrap();
+        assert!(!emulator.is_spinning());
+        assert_eq!(
+            StopReason::IdleLoop { pc: 0x228 },
+            emulator.run_until_breakpoint(1000)
+        );
+        assert!(emulator.is_spinning());
+
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x05, // 0x200: LD V0, 0x05
+            0x30, 0x05, // 0x202: SE V0, 0x05
+            0x00, 0xE0, // 0x204: CLS, skipped
+            0x12, 0x00, // 0x206: JP 0x200
+        ];
+        emulator.load_rom(&rom).unwrap();
+        let start = emulator.tick_count();
+        assert_eq!(
+            StopReason::IdleLoop { pc: 0x202 },
+            emulator.run_until_breakpoint(1000)
+        );
+        assert!(emulator.tick_count() - start < 100);
+
+        // Waiting on the delay timer is idle, but not spinning
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x3C, // 0x200: LD V0, 0x3C
+            0xF0, 0x15, // 0x202: LD DT, V0
+            0xF1, 0x07, // 0x204: LD V1, DT
+            0x31, 0x00, // 0x206: SE V1, 0x00
+            0x12, 0x04, // 0x208: JP 0x204
+            0x12, 0x0A, // 0x20A: JP 0x20A
+        ];
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.load_rom(&rom).unwrap();
+        assert_eq!(StopReason::MaxTicks, emulator.run_until_breakpoint(1000));
+        assert!(emulator.is_idle_on_delay());
+        assert!(!emulator.is_spinning());
+        emulator.advance_time_ms(1000);
+        assert_eq!(
+            StopReason::IdleLoop { pc: 0x20A },
+            emulator.run_until_breakpoint(1000)
+        );
+    }
+
+    #[test]
+    fn wait_key_scans_pressed_keys() {
+        #[rustfmt::skip]
+        let rom = [
+            0xF3, 0x0A, // Wait for a key and store it in v3
+            0x61, 0x01, // Load 1 into v1
+        ];
+        let mut emulator = Emulator::new().with_rom(&rom).unwrap();
+        for _ in 0..5 {
+            assert_eq!(Ok(TickOutcome::WaitingForKey), emulator.try_tick());
+            assert_eq!(0x200, emulator.pc());
+        }
+
+        emulator.press_key(0x7);
+        assert_eq!(Ok(TickOutcome::Executed), emulator.try_tick());
+        assert_eq!(0x7, *emulator.cpu.register(3));
+        assert_eq!(0x202, emulator.pc());
+        emulator.tick();
+        assert_eq!(1, *emulator.cpu.register(1));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn can_dump_memory_near_zero() {
+        let mut emulator = Emulator::new();
+        emulator.memory.write_u16(0x000, 0x1234);
+        emulator.set_pc(0x000).unwrap();
+
+        let window = emulator.memory_window(4);
+        assert_eq!(0x000, window.start());
+        assert_eq!(Some(0), window.center_offset());
+        assert_eq!(
+            [0, 0, 0, 0, 0, 0x12, 0x34, 0, 0, 0, 0],
+            emulator.dump_raw_memory_around_pc()
+        );
+
+        emulator.set_pc(0x002).unwrap();
+        assert_eq!(Some(2), emulator.memory_window(4).center_offset());
+        assert_eq!(
+            [0, 0, 0, 0, 0x1234, 0, 0, 0, 0, 0, 0],
+            emulator.dump_double_memory_around_pc()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn can_dump_memory_near_end() {
+        let mut emulator = Emulator::new();
+        emulator.memory.write_u16(0xFFE, 0xABCD);
+        emulator.set_pc(0xFFE).unwrap();
+
+        let window = emulator.memory_window(8);
+        assert_eq!(0xFF6, window.start());
+        assert_eq!(10, window.bytes().len());
+        assert_eq!(Some(0xABCD), window.word_at(0xFFE));
+        assert_eq!(
+            [0, 0, 0, 0, 0, 0xAB, 0xCD, 0, 0, 0, 0],
+            emulator.dump_raw_memory_around_pc()
+        );
+        assert_eq!(
+            [0, 0, 0, 0, 0, 0xABCD, 0, 0, 0, 0, 0],
+            emulator.dump_double_memory_around_pc()
+        );
+    }
+
+    #[test]
+    fn can_add() {
+        let mut emulator = Emulator::new();
+        let ptr = CHIP8_START as u16;
+        emulator.memory.write_u16(ptr, 0x7112);
+        *emulator.cpu.register_mut(1) = 0x05;
+
+        // Add 0x12 to whatever is stored in register 1
+        emulator.tick();
+        assert_eq!(0x05 + 0x12, *emulator.cpu.register(1));
+
+        // Store 0x03 in register 2 and add registers 1 and 2
+        *emulator.cpu.register_mut(2) = 0x03;
+        emulator.cpu.carry_on();
+        emulator.memory.write_u16(ptr + 2, 0x8124);
+        emulator.tick();
+        assert_eq!(0x05 + 0x12 + 0x03, *emulator.cpu.register(1));
+        assert_eq!(0, *emulator.cpu.carry());
+
+        // Add whatever is stored in register 1 to register I
+        emulator.memory.write_u16(ptr + 4, 0xF11E);
+        emulator.tick();
+        assert_eq!(0x05 + 0x12 + 0x03, *emulator.cpu.i());
+    }
+
+    #[test]
+    fn vf_holds_flag_as_destination() {
+        let mut emulator = Emulator::new();
+        let ptr = CHIP8_START as u16;
+        emulator.memory.write_u16(ptr, 0x8FE4);
+        emulator.memory.write_u16(ptr + 2, 0x8F15);
+        emulator.memory.write_u16(ptr + 4, 0x8FE7);
+
+        // 0xF0 + 0x20 overflows, so the carry flag wins over the sum
+        *emulator.cpu.register_mut(0xF) = 0xF0;
+        *emulator.cpu.register_mut(0xE) = 0x20;
+        emulator.tick();
+        assert_eq!(1, *emulator.cpu.carry());
+
+        // 0x01 - 0x05 borrows, so the flag is 0 instead of the difference
+        *emulator.cpu.register_mut(0xF) = 0x01;
+        *emulator.cpu.register_mut(0x1) = 0x05;
+        emulator.tick();
+        assert_eq!(0, *emulator.cpu.carry());
+
+        // 0x20 - 0x10 does not borrow, so the flag is 1 instead of the difference
+        *emulator.cpu.register_mut(0xF) = 0x10;
+        emulator.tick();
+        assert_eq!(1, *emulator.cpu.carry());
+    }
+
+    #[test]
+    fn sub_sets_no_borrow_flag() {
+        // (a, b, a - b, no borrow)
+        let cases = [
+            (5, 3, 2, 1),
+            (3, 5, 254, 0),
+            (7, 7, 0, 1),
+            (0, 1, 255, 0),
+            (255, 255, 0, 1),
+            (255, 0, 255, 1),
+        ];
+
+        for (a, b, difference, flag) in cases {
+            // 8XY5: x = x - y
+            let mut emulator = Emulator::new();
+            emulator.memory.write_u16(CHIP8_START as u16, 0x8125);
+            *emulator.cpu.register_mut(1) = a;
+            *emulator.cpu.register_mut(2) = b;
+            emulator.tick();
+            assert_eq!(difference, *emulator.cpu.register(1), "{a} - {b}");
+            assert_eq!(flag, *emulator.cpu.carry(), "{a} - {b}");
+
+            // 8XY7: x = y - x
+            let mut emulator = Emulator::new();
+            emulator.memory.write_u16(CHIP8_START as u16, 0x8127);
+            *emulator.cpu.register_mut(1) = b;
+            *emulator.cpu.register_mut(2) = a;
+            emulator.tick();
+            assert_eq!(difference, *emulator.cpu.register(1), "{a} - {b}");
+            assert_eq!(flag, *emulator.cpu.carry(), "{a} - {b}");
+        }
+    }
+
+    #[test]
+    fn add_i_wraps_around() {
+        let mut emulator = Emulator::new();
+        emulator.memory.write_u16(CHIP8_START as u16, 0xF01E);
+        emulator.memory.write_u16(CHIP8_START as u16 + 2, 0xF01E);
+        *emulator.cpu.register_mut(0) = 5;
+        *emulator.cpu.register_mut(0xF) = 7;
+        *emulator.cpu.i_mut() = 0xFFE;
+
+        emulator.tick();
+        assert_eq!(0x003, *emulator.cpu.i());
+        assert_eq!(7, *emulator.cpu.carry());
+
+        emulator.configuration.quirks.index_add_sets_flag = true;
+        *emulator.cpu.i_mut() = 0xFFE;
+        emulator.tick();
+        assert_eq!(0x003, *emulator.cpu.i());
+        assert_eq!(1, *emulator.cpu.carry());
+    }
+
+    #[test]
+    fn add_i_flag_is_set_past_last_address() {
+        for sets_flag in [false, true] {
+            let mut emulator = Emulator::new();
+            emulator.configuration.quirks.index_add_sets_flag = sets_flag;
+            emulator.load_rom(&[0xF0, 0x1E, 0xF0, 0x1E]).unwrap();
+            *emulator.cpu.register_mut(0) = 1;
+            *emulator.cpu.register_mut(0xF) = 7;
+
+            // Reaching the last address is no overflow yet
+            *emulator.cpu.i_mut() = 0xFFE;
+            emulator.tick();
+            assert_eq!(0xFFF, *emulator.cpu.i());
+            assert_eq!(if sets_flag { 0 } else { 7 }, *emulator.cpu.carry());
+
+            emulator.tick();
+            assert_eq!(0x000, *emulator.cpu.i());
+            assert_eq!(if sets_flag { 1 } else { 7 }, *emulator.cpu.carry());
+        }
+    }
+
+    #[test]
+    fn shift_quirk_can_change_mid_run() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.quirks = QuirkSet::cosmac_vip().with_shift(ShiftStyle::ShiftInPlace);
+        emulator
+            .load_rom(&[0x60, 0x10, 0x61, 0x05, 0x80, 0x16, 0x80, 0x16, 0x80, 0x16])
+            .unwrap();
+        for _ in 0..3 {
+            emulator.tick();
+        }
+        assert_eq!(0x08, *emulator.cpu.register(0));
+
+        emulator.configuration.quirks.shift = ShiftStyle::CopyThenShift;
+        emulator.tick();
+        assert_eq!(0x02, *emulator.cpu.register(0));
+        assert_eq!(1, *emulator.cpu.carry());
+
+        emulator.configuration.quirks.shift = ShiftStyle::ShiftInPlace;
+        emulator.tick();
+        assert_eq!(0x01, *emulator.cpu.register(0));
+        assert_eq!(0, *emulator.cpu.carry());
+    }
+
+    #[test]
+    fn logic_can_reset_vf() {
+        for (opcode, result) in [(0x8011, 0b1110), (0x8012, 0b1000), (0x8013, 0b0110)] {
+            for resets_vf in [false, true] {
+                let mut emulator = Emulator::new();
+                emulator.configuration.quirks.logic_resets_vf = resets_vf;
+                emulator.memory.write_u16(CHIP8_START as u16, opcode);
+                *emulator.cpu.register_mut(0) = 0b1100;
+                *emulator.cpu.register_mut(1) = 0b1010;
+                *emulator.cpu.register_mut(0xF) = 1;
+
+                emulator.tick();
+                assert_eq!(result, *emulator.cpu.register(0), "{:#06X}", opcode);
+                assert_eq!(!resets_vf as u8, *emulator.cpu.carry(), "{:#06X}", opcode);
+            }
+        }
+    }
+
+    #[test]
+    fn can_bcd() {
+        let mut emulator = Emulator::new();
+        emulator.memory.write_u16(CHIP8_START as u16, 0xF033);
+        *emulator.cpu.register_mut(0) = 234;
+        *emulator.cpu.i_mut() = 0x0300;
+
+        emulator.tick();
+        assert_eq!(2, emulator.memory.read_u8(*emulator.cpu.i()));
+        assert_eq!(3, emulator.memory.read_u8(*emulator.cpu.i() + 1));
+        assert_eq!(4, emulator.memory.read_u8(*emulator.cpu.i() + 2));
+    }
+
+    #[test]
+    fn bcd_near_end_of_memory_faults() {
+        let mut emulator = Emulator::new();
+        emulator.memory.write_u16(CHIP8_START as u16, 0xF033);
+        *emulator.cpu.register_mut(0) = 234;
+        *emulator.cpu.i_mut() = 0xFFE;
+
+        emulator.tick();
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::MemoryOutOfBounds {
+                pc: 0x200,
+                address: 0xFFE
+            })),
+            emulator.halt_reason()
+        );
+        assert_eq!(0, emulator.memory.read_u8(0xFFE));
+    }
+
+    #[test]
+    fn dump_and_load_move_i_by_style() {
+        for (style, final_i) in [
+            (DumpLoadStyle::IncrementI, 0x304),
+            (DumpLoadStyle::IncrementIByX, 0x303),
+            (DumpLoadStyle::StaticIRegister, 0x300),
+        ] {
+            let mut emulator = Emulator::new();
+            emulator.configuration.quirks.dump_load = style;
+            emulator.memory.write_u16(CHIP8_START as u16, 0xF355);
+            for register in 0..4 {
+                *emulator.cpu.register_mut(register) = 0xA0 + register;
+            }
+            *emulator.cpu.i_mut() = 0x300;
+            emulator.tick();
+            let mut bytes = [0; 5];
+            emulator.memory.snapshot_into(0x300..0x305, &mut bytes);
+            assert_eq!([0xA0, 0xA1, 0xA2, 0xA3, 0x00], bytes, "{:?}", style);
+            assert_eq!(final_i, *emulator.cpu.i(), "{:?}", style);
+
+            emulator.memory.write_u16(CHIP8_START as u16 + 2, 0xF365);
+            emulator.memory.write_u8(0x303, 0xB3);
+            *emulator.cpu.register_mut(0) = 0;
+            *emulator.cpu.i_mut() = 0x300;
+            emulator.tick();
+            assert_eq!(
+                [0xA0, 0xA1, 0xA2, 0xB3],
+                emulator.dump_registers()[..4],
+                "{:?}",
+                style
+            );
+            assert_eq!(final_i, *emulator.cpu.i(), "{:?}", style);
+        }
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn i_wraps_to_the_address_space() {
+        for (configuration, address) in [
+            (EmulatorConfiguration::default(), 0x345),
+            (EmulatorConfiguration::xo_chip(), 0x2345),
+        ] {
+            let mut emulator = Emulator::new().with_configuration(configuration).unwrap();
+            emulator
+                .load_rom(&[0xF1, 0x55, 0xF1, 0x65, 0xF0, 0x33])
+                .unwrap();
+            *emulator.cpu.register_mut(0) = 0x12;
+            *emulator.cpu.register_mut(1) = 0x34;
+            *emulator.cpu.i_mut() = 0x2345;
+            emulator.tick();
+            assert_eq!(0x12, emulator.memory.read_u8(address));
+            assert_eq!(0x34, emulator.memory.read_u8(address + 1));
+
+            *emulator.cpu.register_mut(0) = 0;
+            *emulator.cpu.register_mut(1) = 0;
+            *emulator.cpu.i_mut() = 0x2345;
+            emulator.tick();
+            assert_eq!([0x12, 0x34], emulator.dump_registers()[..2]);
+
+            *emulator.cpu.i_mut() = 0x2345;
+            emulator.tick();
+            let mut bytes = [0; 3];
+            emulator
+                .memory
+                .snapshot_into(address..address + 3, &mut bytes);
+            assert_eq!([0, 1, 8], bytes);
+        }
+    }
+
+    #[test]
+    fn dump_near_end_of_memory_faults() {
+        let mut emulator = Emulator::new();
+        emulator.memory.write_u16(CHIP8_START as u16, 0xF155);
+        emulator.memory.write_u16(CHIP8_START as u16 + 2, 0xF255);
+        *emulator.cpu.register_mut(0) = 1;
+        *emulator.cpu.register_mut(1) = 2;
+        *emulator.cpu.i_mut() = 0xFFE;
+
+        // Two registers still fit
+        emulator.tick();
+        assert_eq!(None, emulator.halt_reason());
+        assert_eq!(2, emulator.memory.read_u8(0xFFF));
+
+        emulator.tick();
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::MemoryOutOfBounds {
+                pc: 0x202,
+                address: 0xFFE
+            })),
+            emulator.halt_reason()
+        );
+    }
+
+    #[test]
+    fn draw_near_end_of_memory_faults() {
+        let mut emulator = Emulator::new();
+        emulator.memory.write_u16(CHIP8_START as u16, 0xD01F);
+        *emulator.cpu.i_mut() = 0xFFF;
+
+        emulator.tick();
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::MemoryOutOfBounds {
+                pc: 0x200,
+                address: 0xFFF
+            })),
+            emulator.halt_reason()
+        );
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(48))]
+
+        /// Every preset, at 4 KiB and 64 KiB, survives random roms and keys
+        #[test]
+        fn random_roms_never_panic(
+            preset in proptest::sample::select(&crate::config::PRESET_NAMES[..]),
+            memory_size in proptest::sample::select(&[
+                crate::config::CHIP8_MEMORY_SIZE,
+                crate::config::XO_CHIP_MEMORY_SIZE,
+            ][..]),
+            wrap in proptest::bool::ANY,
+            rom in proptest::collection::vec(proptest::num::u8::ANY, MEMORY_SIZE - CHIP8_START),
+            keys in proptest::collection::vec(proptest::num::u8::ANY, 10_000),
+        ) {
+            let mut configuration: EmulatorConfiguration = preset.parse().unwrap();
+            configuration.memory_size = memory_size;
+            if wrap {
+                configuration.pc_overflow = PcOverflowStyle::Wrap;
+            }
+            proptest::prop_assume!(configuration.validate().is_ok());
+            // Repeat the rom to fill all of memory, so 64 KiB roms reach the top
+            let rom_len = memory_size - configuration.start_address as usize;
+            let rom: Vec<u8> = rom.iter().copied().cycle().take(rom_len).collect();
+            let mut emulator = Emulator::new().with_configuration(configuration).unwrap();
+            emulator.load_rom(&rom).unwrap();
+            for key in keys {
+                if key & 0x10 == 0 {
+                    emulator.press_key(key);
+                } else {
+                    emulator.release_key(key);
+                }
+                if emulator.try_tick().is_err() {
+                    emulator.load_rom(&rom).unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn can_run_timers() {
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        *emulator.cpu.register_mut(0) = 60;
+        emulator.memory.write_u16(CHIP8_START as u16, 0xF015);
+
+        emulator.tick();
+        assert_eq!(60, *emulator.cpu.delay());
+
+        emulator.advance_time_ms(500);
+        emulator.tick();
+        assert_eq!(30, *emulator.cpu.delay());
+    }
+
+    #[test]
+    fn host_can_drive_timers() {
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.configuration.timers = TimerStyle::HostDriven;
+        *emulator.cpu.register_mut(0) = 60;
+        emulator.memory.write_u16(CHIP8_START as u16, 0xF015);
+        emulator.memory.write_u16(CHIP8_START as u16 + 2, 0xF018);
+
+        emulator.tick();
+        emulator.tick();
+        assert_eq!(60, emulator.delay());
+
+        // Time passing on the clock is ignored
+        emulator.advance_time_ms(500);
+        emulator.tick();
+        assert_eq!(60, emulator.delay());
+
+        // The cpu is paused, only the host drains the timers
+        for _ in 0..59 {
+            emulator.tick_timers();
+        }
+        assert_eq!(1, emulator.delay());
+        assert!(emulator.is_sound_on());
+        emulator.tick_timers();
+        emulator.tick_timers();
+        assert_eq!(0, emulator.delay());
+        assert!(!emulator.is_sound_on());
+    }
+
+    #[test]
+    fn timers_can_count_instructions() {
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.configuration.timers = TimerStyle::PerInstructions(10);
+        *emulator.cpu.register_mut(0) = 100;
+        emulator.memory.write_u16(CHIP8_START as u16, 0xF015);
+        emulator.memory.write_u16(CHIP8_START as u16 + 2, 0x1202);
+        emulator.tick();
+
+        emulator.advance_time_ms(10_000);
+        for _ in 0..700 {
+            emulator.tick();
+        }
+        assert_eq!(30, emulator.delay());
+    }
+
+    #[test]
+    fn timer_phase_is_independent_of_reloads() {
+        // The timer steps whenever another 1/60 s passed since it started
+        let step_times: Vec<u64> = (1..10u64).map(|step| (step * 1000).div_ceil(60)).collect();
+
+        for phase in 0..20 {
+            let mut emulator = Emulator::with_clock(ManualClock::new());
+            *emulator.cpu.register_mut(0) = 2;
+            emulator.memory.write_u16(CHIP8_START as u16, 0xF015);
+            emulator.memory.write_u16(CHIP8_START as u16 + 2, 0x1202);
+            emulator.advance_time_ms(phase);
+            emulator.tick();
+
+            let mut decrements = Vec::new();
+            while emulator.delay() > 0 {
+                let delay = emulator.delay();
+                emulator.advance_time_ms(1);
+                emulator.tick();
+                if emulator.delay() < delay {
+                    decrements.push(emulator.clock().now_millis());
+                }
+            }
+
+            let expected: Vec<u64> = step_times
+                .iter()
+                .copied()
+                .filter(|time| *time > phase)
+                .take(2)
+                .collect();
+            assert_eq!(expected, decrements, "loaded at {} ms", phase);
+        }
+    }
+
+    #[test]
+    fn long_pauses_drain_timers() {
+        for value in [1, 0x7F, 0xFF] {
+            let mut emulator = Emulator::with_clock(ManualClock::new());
+            *emulator.cpu.register_mut(0) = value;
+            emulator.memory.write_u16(CHIP8_START as u16, 0xF015);
+            emulator.memory.write_u16(CHIP8_START as u16 + 2, 0xF018);
+            emulator.tick();
+            emulator.tick();
+
+            emulator.advance_time_ms(10_000);
+            emulator.tick();
+            assert_eq!(0, emulator.delay());
+            assert!(!emulator.is_sound_on());
+        }
+    }
+
+    #[test]
+    fn can_read_remaining_sound() {
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        *emulator.cpu.register_mut(0) = 60;
+        emulator.memory.write_u16(CHIP8_START as u16, 0xF018);
+        emulator.tick();
+        assert_eq!(60, emulator.sound());
+        assert_eq!(Duration::from_secs(1), emulator.sound_remaining());
+        assert_eq!(Duration::ZERO, emulator.delay_remaining());
+
+        emulator.advance_time_ms(500);
+        emulator.tick();
+        assert_eq!(30, emulator.sound());
+        assert_eq!(Duration::from_millis(500), emulator.sound_remaining());
+
+        emulator.advance_time_ms(500);
+        emulator.tick();
+        assert_eq!(0, emulator.sound());
+        assert_eq!(Duration::ZERO, emulator.sound_remaining());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn sound_changes_are_reported() {
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x02, // Load 2 into v0
+            0xF0, 0x18, // Set the sound timer to v0
+            0x12, 0x04, // Loop forever
+        ];
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.load_rom(&rom).unwrap();
+        emulator.tick();
+        emulator.tick();
+        assert_eq!(
+            vec![SoundEvent::Started { tick: 2, millis: 0 }],
+            emulator.take_sound_events().collect::<Vec<_>>()
+        );
+
+        emulator.advance_time_ms(20);
+        emulator.tick();
+        assert_eq!(0, emulator.take_sound_events().count());
+        emulator.advance_time_ms(20);
+        emulator.tick();
+        emulator.tick();
+        assert_eq!(
+            vec![SoundEvent::Stopped {
+                tick: 4,
+                millis: 34
+            }],
+            emulator.take_sound_events().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn audio_stops_when_sound_runs_out() {
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.load_rom(&[0x60, 0x03, 0xF0, 0x18]).unwrap();
+        emulator.tick();
+        emulator.tick();
+
+        // 3 steps of the timer last 1/20 s, so 60 samples at 1200 Hz
+        let mut buf = [0.0; 100];
+        emulator.fill_audio(&mut buf, 1200);
+        assert!(buf[..60].iter().all(|sample| sample.abs() == 1.0));
+        assert!(buf[60..].iter().all(|sample| *sample == 0.0));
+
+        emulator.tick_timers();
+        let mut buf = [0; 100];
+        emulator.fill_audio_i16(&mut buf, 1200);
+        assert_ne!(0, buf[39]);
+        assert_eq!(0, buf[40]);
+    }
+
+    #[test]
+    fn audio_leaves_out_the_passed_part_of_a_step() {
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.load_rom(&[0x60, 0x06, 0xF0, 0x18]).unwrap();
+        emulator.tick();
+        emulator.tick();
+
+        // 8 ms are 0.48 steps, leaving 92 of the 100 ms of the beep
+        emulator.advance_time_ms(8);
+        let mut buf = [0.0; 120];
+        emulator.fill_audio(&mut buf, 1000);
+        assert!(buf[..92].iter().all(|sample| sample.abs() == 1.0));
+        assert!(buf[92..].iter().all(|sample| *sample == 0.0));
+
+        // Ticking keeps the partial step as the timer's remainder
+        emulator.advance_time_ms(2);
+        emulator.tick();
+        assert_eq!(6, emulator.sound());
+        emulator.fill_audio(&mut buf, 1000);
+        assert!(buf[..90].iter().all(|sample| sample.abs() == 1.0));
+        assert!(buf[90..].iter().all(|sample| *sample == 0.0));
+    }
+
+    /// Collects the edges a [`SoundSink`] is told about
+    #[cfg(feature = "alloc")]
+    struct RecordingSink(std::sync::Arc<std::sync::Mutex<Vec<bool>>>);
+
+    #[cfg(feature = "alloc")]
+    impl SoundSink for RecordingSink {
+        fn sound_changed(&mut self, on: bool) {
+            self.0.lock().unwrap().push(on);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn sound_sink_sees_each_edge_once() {
+        let edges = std::sync::Arc::default();
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.configuration.timers = TimerStyle::HostDriven;
+        emulator.set_sound_sink(RecordingSink(std::sync::Arc::clone(&edges)));
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x03, // Load 3 into v0
+            0xF0, 0x18, // Set the sound timer to v0
+            0x12, 0x04, // Loop forever
+        ];
+        emulator.load_rom(&rom).unwrap();
+        for _ in 0..2 {
+            emulator.tick();
+        }
+        assert_eq!(vec![true], *edges.lock().unwrap());
+        for _ in 0..5 {
+            emulator.tick_timers();
+            emulator.tick();
+        }
+        assert_eq!(vec![true, false], *edges.lock().unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn sound_stops_between_ticks() {
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator
+            .load_rom(&[0x60, 0x03, 0xF0, 0x18, 0x12, 0x04])
+            .unwrap();
+        emulator.tick();
+        emulator.tick();
+        emulator.advance_time_ms(100);
+        emulator.tick();
+        let events: Vec<_> = emulator.take_sound_events().collect();
+        assert_eq!(
+            vec![
+                SoundEvent::Started { tick: 2, millis: 0 },
+                SoundEvent::Stopped {
+                    tick: 3,
+                    millis: 50
+                }
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn frame_audio_covers_the_beep() {
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.configuration.timers = TimerStyle::HostDriven;
+        emulator.configuration.instructions_per_frame = 2;
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x01, // Load 1 into v0
+            0x00, 0xE0, // Clear the screen
+            0xF0, 0x18, // 0x204: Set the sound timer to v0, first in the second frame
+            0x12, 0x06, // Loop forever
+        ];
+        emulator.load_rom(&rom).unwrap();
+        let mut frames = [[1.0; 800]; 4];
+        for frame in frames.iter_mut() {
+            emulator.run_frame_with_audio(frame, 48_000).unwrap();
+        }
+        assert!(frames[0].iter().all(|sample| *sample == 0.0));
+        assert!(frames[1].iter().all(|sample| sample.abs() == 1.0));
+        assert!(frames[2].iter().all(|sample| *sample == 0.0));
+        assert!(frames[3].iter().all(|sample| *sample == 0.0));
+        assert_eq!(8, emulator.tick_count());
+    }
+
+    #[test]
+    fn beep_can_start_mid_frame() {
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.configuration.timers = TimerStyle::HostDriven;
+        emulator.configuration.instructions_per_frame = 4;
+        emulator.load_rom(&[0x60, 0x02, 0xF0, 0x18]).unwrap();
+        let mut samples = [0.0; 800];
+        emulator.run_frame_with_audio(&mut samples, 48_000).unwrap();
+        assert!(samples[..200].iter().all(|sample| *sample == 0.0));
+        assert!(samples[200..].iter().all(|sample| sample.abs() == 1.0));
+        assert_eq!(1, emulator.sound());
+
+        // The rom runs into empty memory
+        emulator.configuration.invalid_opcode = InvalidOpcodeStyle::Halt;
+        let mut samples = [1.0; 800];
+        assert!(emulator.run_frame_with_audio(&mut samples, 48_000).is_err());
+        assert!(samples.iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn muting_silences_the_sound_sink() {
+        let edges = std::sync::Arc::default();
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.configuration.timers = TimerStyle::HostDriven;
+        emulator.set_sound_sink(RecordingSink(std::sync::Arc::clone(&edges)));
+        emulator.load_rom(&[0x60, 0x03, 0xF0, 0x18]).unwrap();
+        emulator.tick();
+        emulator.tick();
+        emulator.set_muted(true);
+        emulator.set_muted(true);
+        assert_eq!(vec![true, false], *edges.lock().unwrap());
+
+        for _ in 0..3 {
+            emulator.tick_timers();
+        }
+        emulator.set_muted(false);
+        assert!(!emulator.is_sound_on());
+        assert_eq!(vec![true, false], *edges.lock().unwrap());
+    }
+
+    #[test]
+    fn muting_keeps_the_sound_register() {
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x05, // Load 5 into v0
+            0xF0, 0x18, // Set the sound timer to v0
+            0xF1, 0x07, // Load the delay timer into v1, just to tick
+            0x12, 0x04, // Loop back
+        ];
+        let run = |muted: bool| {
+            let mut emulator = Emulator::with_clock(ManualClock::new());
+            emulator.set_muted(muted);
+            emulator.load_rom(&rom).unwrap();
+            let mut audible = Vec::new();
+            for _ in 0..10 {
+                emulator.advance_time_ms(10);
+                emulator.tick();
+                let mut buf = [0.0; 8];
+                emulator.fill_audio(&mut buf, 8000);
+                audible.push(buf.iter().any(|sample| *sample != 0.0));
+                assert_eq!(emulator.is_sound_on() && !muted, emulator.is_audible());
+            }
+            (emulator.cpu_snapshot(), emulator.is_sound_on(), audible)
+        };
+
+        let (unmuted, unmuted_sound, unmuted_audible) = run(false);
+        let (muted, muted_sound, muted_audible) = run(true);
+        assert_eq!(unmuted, muted);
+        assert_eq!(unmuted_sound, muted_sound);
+        assert!(unmuted_audible.iter().any(|audible| *audible));
+        assert!(muted_audible.iter().all(|audible| !audible));
+    }
+
+    #[test]
+    fn buzzer_follows_configuration() {
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.load_rom(&[0x60, 0x3C, 0xF0, 0x18]).unwrap();
+        emulator.tick();
+        emulator.tick();
+
+        // A period of 8 samples at 1000 Hz
+        emulator.configuration.buzzer_hz = 1000.0;
+        emulator.configuration.buzzer_volume = 1.5;
+        let mut buf = [0.0; 16];
+        emulator.fill_audio(&mut buf, 8000);
+        let high = [1.0; 4];
+        let low = [-1.0; 4];
+        assert_eq!([high, low, high, low].concat(), buf);
+
+        // A period of 4 samples at 2000 Hz
+        emulator.configuration.buzzer_hz = 2000.0;
+        emulator.configuration.buzzer_volume = 0.5;
+        emulator.fill_audio(&mut buf, 8000);
+        let high = [0.5; 2];
+        let low = [-0.5; 2];
+        assert_eq!([high, low, high, low, high, low, high, low].concat(), buf);
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn xo_chip_plays_audio_pattern() {
+        #[rustfmt::skip]
+        let rom = [
+            0xA2, 0x0C, // Point I at the pattern
+            0xF0, 0x02, // Load the audio pattern
+            0x60, 0x70, // Load 112 into v0
+            0xF0, 0x3A, // Set the pitch to v0, one octave up
+            0xF0, 0x18, // Set the sound timer to v0
+            0x12, 0x0A, // Loop forever
+            0xC0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01,
+        ];
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.configuration.platform = Platform::XoChip;
+        emulator.load_rom(&rom).unwrap();
+        for _ in 0..5 {
+            emulator.tick();
+        }
+        assert_eq!(Some(&rom[12..]), emulator.audio_pattern().map(|p| &p[..]));
+        assert_eq!(112, emulator.pitch());
+
+        // 8000 bits per second play two bits per sample at 4000 Hz
+        let mut buf = [0.0; 4];
+        emulator.fill_audio(&mut buf, 4000);
+        assert_eq!([1.0, -1.0, -1.0, -1.0], buf);
+    }
+
+    #[test]
+    fn audio_pattern_needs_xo_chip() {
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        emulator.configuration.invalid_opcode = InvalidOpcodeStyle::Halt;
+        emulator.load_rom(&[0xF0, 0x02]).unwrap();
+        emulator.tick();
+        assert_eq!(None, emulator.audio_pattern());
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::InvalidOpcode {
+                pc: 0x200,
+                opcode: 0xF002
+            })),
+            emulator.halt_reason()
+        );
+
+        emulator.load_rom(&[0xF5, 0x3A]).unwrap();
+        emulator.tick();
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::InvalidOpcode {
+                pc: 0x200,
+                opcode: 0xF53A
+            })),
+            emulator.halt_reason()
+        );
+    }
+
+    /// Switches to hires, draws a 0 at 100, 50 and switches back
+    const HIRES_ROM: [u8; 14] = [
+        0x00, 0xFF, 0x60, 0x64, 0x61, 0x32, 0x62, 0x00, 0xF2, 0x29, 0xD0, 0x15, 0x00, 0xFE,
+    ];
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn can_draw_in_hires() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.platform = Platform::SuperChip;
+        emulator.load_rom(&HIRES_ROM).unwrap();
+        emulator.tick();
+        assert_eq!(DisplayMode::HighRes, emulator.display_mode());
+
+        for _ in 0..5 {
+            emulator.tick();
+        }
+        assert!(emulator.is_pixel_on(100, 50));
+        assert!(emulator.is_pixel_on(103, 54));
+        assert!(!emulator.is_pixel_on(101, 51));
+        assert_eq!(0, *emulator.cpu.carry());
+        #[cfg(feature = "std")]
+        {
+            let screen = emulator.display.to_string();
+            assert_eq!(64, screen.lines().count());
+            assert!(screen.lines().all(|line| line.chars().count() == 128));
+        }
+
+        emulator.tick();
+        assert_eq!(DisplayMode::LowRes, emulator.display_mode());
+        assert!(!emulator.is_pixel_on(50, 25));
+    }
+
+    /// A hollow 16x16 square
+    const LARGE_SQUARE: [u8; 32] = [
+        0xFF, 0xFF, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80,
+        0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01,
+        0xFF, 0xFF,
+    ];
+
+    #[test]
+    fn can_draw_large_sprites_in_hires() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.platform = Platform::SuperChip;
+        let mut rom = vec![
+            0x00, 0xFF, 0xA2, 0x0C, 0x60, 0x0A, 0x61, 0x05, 0xD0, 0x10, 0xD0, 0x10,
+        ];
+        rom.extend_from_slice(&LARGE_SQUARE);
+        emulator.load_rom(&rom).unwrap();
+        *emulator.cpu.register_mut(0xF) = 1;
+        for _ in 0..5 {
+            emulator.tick();
+        }
+
+        for (x, y) in [(10, 5), (25, 5), (10, 20), (25, 20), (17, 5), (10, 12)] {
+            assert!(emulator.is_pixel_on(x, y), "{}, {}", x, y);
+        }
+        for (x, y) in [(11, 6), (24, 19), (26, 5), (10, 21), (9, 5)] {
+            assert!(!emulator.is_pixel_on(x, y), "{}, {}", x, y);
+        }
+        assert_eq!(0, *emulator.cpu.carry());
+
+        emulator.tick();
+        assert!(!emulator.is_pixel_on(10, 5));
+        assert_eq!(1, *emulator.cpu.carry());
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn large_sprites_are_clipped() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.platform = Platform::SuperChip;
+        let mut rom = vec![0x00, 0xFF, 0xA2, 0x0A, 0x60, 0x78, 0x61, 0x3C, 0xD0, 0x10];
+        rom.extend_from_slice(&LARGE_SQUARE);
+        emulator.load_rom(&rom).unwrap();
+        for _ in 0..5 {
+            emulator.tick();
+        }
+        assert!(emulator.is_pixel_on(127, 60));
+        assert!(emulator.is_pixel_on(120, 63));
+        assert!(!emulator.is_pixel_on(0, 60));
+        assert!(!emulator.is_pixel_on(120, 0));
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn hires_collisions_follow_preset() {
+        for (configuration, collision) in [
+            (EmulatorConfiguration::super_chip_legacy(), 3),
+            (EmulatorConfiguration::super_chip_modern(), 1),
+        ] {
+            let mut emulator = Emulator::new().with_configuration(configuration).unwrap();
+            let mut rom = vec![
+                0x00, 0xFF, 0xA2, 0x0C, 0x60, 0x00, 0x61, 0x0D, 0xD0, 0x00, 0xD0, 0x10,
+            ];
+            rom.extend_from_slice(&LARGE_SQUARE);
+            emulator.load_rom(&rom).unwrap();
+            for _ in 0..6 {
+                emulator.tick();
+            }
+            assert_eq!(collision, *emulator.cpu.carry());
+        }
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn hires_collisions_can_count_clipped_rows() {
+        for (style, collision) in [
+            (CollisionStyle::Flag, 0),
+            (CollisionStyle::Rows, 0),
+            (CollisionStyle::RowsAndClipped, 12),
+        ] {
+            let mut emulator = Emulator::new();
+            emulator.configuration.platform = Platform::SuperChip;
+            emulator.configuration.quirks.collision = style;
+            let mut rom = vec![0x00, 0xFF, 0xA2, 0x0A, 0x60, 0x00, 0x61, 0x3C, 0xD0, 0x10];
+            rom.extend_from_slice(&LARGE_SQUARE);
+            emulator.load_rom(&rom).unwrap();
+            for _ in 0..5 {
+                emulator.tick();
+            }
+            assert_eq!(collision, *emulator.cpu.carry(), "{:?}", style);
+        }
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn lores_collisions_are_a_flag() {
+        let mut emulator = Emulator::new()
+            .with_configuration(EmulatorConfiguration::super_chip_legacy())
+            .unwrap();
+        emulator
+            .load_rom(&[0xF0, 0x29, 0x61, 0x1E, 0xD0, 0x05, 0xD0, 0x05, 0xD0, 0x15])
+            .unwrap();
+        for _ in 0..4 {
+            emulator.tick();
+        }
+        assert_eq!(1, *emulator.cpu.carry());
+        emulator.tick();
+        assert_eq!(0, *emulator.cpu.carry());
+    }
+
+    #[test]
+    fn lores_dxy0_follows_style() {
+        let mut rom = vec![0xA2, 0x04, 0xD0, 0x10];
+        rom.extend_from_slice(&LARGE_SQUARE);
+        for style in [
+            LoresSpriteStyle::Nothing,
+            LoresSpriteStyle::Tall,
+            LoresSpriteStyle::Large,
+        ] {
+            let mut emulator = Emulator::new();
+            emulator.configuration.platform = Platform::SuperChip;
+            emulator.configuration.quirks.lores_dxy0 = style;
+            emulator.load_rom(&rom).unwrap();
+            emulator.tick();
+            emulator.tick();
+
+            let pixels = [(0, 0), (8, 0), (15, 0), (7, 15), (0, 15)]
+                .map(|(x, y)| emulator.is_pixel_on(x, y));
+            let expected = match style {
+                LoresSpriteStyle::Nothing => [false, false, false, false, false],
+                // Reads 16 single bytes, so the last row is the 0x01 of row 7
+                LoresSpriteStyle::Tall => [true, false, false, true, false],
+                LoresSpriteStyle::Large => [true, true, true, true, true],
+            };
+            assert_eq!(expected, pixels, "{:?}", style);
+        }
+
+        // Plain chip-8 draws a sprite of zero rows
+        let mut emulator = Emulator::new().with_rom(&rom).unwrap();
+        emulator.tick();
+        emulator.tick();
+        assert!(!emulator.is_pixel_on(0, 0));
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn can_draw_large_digits() {
+        for (configuration, digit) in [
+            (EmulatorConfiguration::super_chip_modern(), 3u8),
+            (EmulatorConfiguration::xo_chip(), 0x1E),
+        ] {
+            let mut emulator = Emulator::new().with_configuration(configuration).unwrap();
+            emulator
+                .load_rom(&[0x00, 0xFF, 0x60, digit, 0xF0, 0x30, 0xD1, 0x1A])
+                .unwrap();
+            for _ in 0..4 {
+                emulator.tick();
+            }
+
+            let glyph = (digit & 0x0F) as usize * 10;
+            let expected = &LARGE_FONT_SPRITES[glyph..glyph + 10];
+            assert_eq!(
+                DEFAULT_FONT_BASE + LARGE_FONT_OFFSET + glyph as u16,
+                emulator.i()
+            );
+            assert_sprite_at(&emulator.display, 0, 0, expected);
+        }
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn large_font_moves_with_font_base() {
+        let configuration = EmulatorConfiguration {
+            font_base: 0x000,
+            ..EmulatorConfiguration::super_chip_modern()
+        };
+        let mut emulator = Emulator::new().with_configuration(configuration).unwrap();
+        emulator.load_rom(&[0x60, 0x07, 0xF0, 0x30]).unwrap();
+        emulator.tick();
+        emulator.tick();
+        assert_eq!(LARGE_FONT_OFFSET + 70, emulator.i());
+        let mut glyph = [0; 10];
+        emulator
+            .memory
+            .snapshot_into(emulator.i()..emulator.i() + 10, &mut glyph);
+        assert_eq!(LARGE_FONT_SPRITES[70..80], glyph);
+
+        let mut emulator = Emulator::new();
+        emulator.configuration.invalid_opcode = InvalidOpcodeStyle::Halt;
+        emulator.load_rom(&[0xF0, 0x30]).unwrap();
+        emulator.tick();
+        assert!(emulator.is_halted());
+    }
+
+    /// Sets V0 to V9 to 1..=10, saves them to the flags and clears them
+    #[cfg(feature = "schip")]
+    const SAVE_FLAGS_ROM: [u8; 26] = [
+        0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0x63, 0x04, 0x64, 0x05, 0x65, 0x06, 0x66, 0x07, 0x67,
+        0x08, 0x68, 0x09, 0x69, 0x0A, 0xF9, 0x75, 0x00, 0xE0, 0x00, 0xE0,
+    ];
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn flags_survive_loading_a_rom() {
+        for (platform, saved) in [(Platform::SuperChip, 8u8), (Platform::XoChip, 10)] {
+            let mut emulator = Emulator::new();
+            emulator.configuration.platform = platform;
+            emulator.load_rom(&SAVE_FLAGS_ROM).unwrap();
+            for _ in 0..11 {
+                emulator.tick();
+            }
+            assert_eq!(
+                usize::from(saved),
+                emulator.flags().iter().filter(|flag| **flag != 0).count()
+            );
+
+            emulator.load_rom(&[0xFF, 0x85]).unwrap();
+            assert_eq!(0, *emulator.cpu.register(0));
+            emulator.tick();
+            for register in 0..16u8 {
+                let expected = if register < saved { register + 1 } else { 0 };
+                assert_eq!(expected, *emulator.cpu.register(register), "{:?}", platform);
+            }
+        }
+    }
+
+    #[test]
+    fn flags_need_super_chip() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.invalid_opcode = InvalidOpcodeStyle::Halt;
+        emulator.load_rom(&[0x60, 0x01, 0xF0, 0x75]).unwrap();
+        emulator.tick();
+        emulator.tick();
+        assert_eq!([0; MAX_FLAGS], *emulator.flags());
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::InvalidOpcode {
+                pc: 0x202,
+                opcode: 0xF075
+            })),
+            emulator.halt_reason()
+        );
+    }
+
+    #[cfg(all(feature = "std", feature = "schip"))]
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn flags_can_persist_to_file() {
+        let path = std::env::temp_dir().join("chip8_emulator_flags_test.bin");
+        let _ = std::fs::remove_file(&path);
+        let mut emulator = Emulator::new();
+        emulator.configuration.platform = Platform::SuperChip;
+        emulator.set_flag_store(crate::FileFlagStore::new(&path));
+        emulator.load_rom(&SAVE_FLAGS_ROM).unwrap();
+        for _ in 0..11 {
+            emulator.tick();
+        }
+
+        let mut emulator = Emulator::new();
+        emulator.configuration.platform = Platform::SuperChip;
+        emulator.set_flag_store(crate::FileFlagStore::new(&path));
+        std::fs::remove_file(&path).unwrap();
+        emulator.load_rom(&[0xF3, 0x85]).unwrap();
+        emulator.tick();
+        assert_eq!([1, 2, 3, 4], emulator.dump_registers()[..4]);
+        assert_eq!(0, *emulator.cpu.register(4));
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn mode_switch_can_keep_the_picture() {
+        let mut emulator = Emulator::new();
+        emulator.configuration = EmulatorConfiguration::super_chip_legacy();
+        emulator.load_rom(&HIRES_ROM).unwrap();
+        for _ in 0..7 {
+            emulator.tick();
+        }
+        assert_eq!(DisplayMode::LowRes, emulator.display_mode());
+        assert!(emulator.is_pixel_on(50, 25));
+        assert!(emulator.is_pixel_on(51, 27));
+        assert!(!emulator.is_pixel_on(52, 25));
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn can_scroll_the_display() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.platform = Platform::SuperChip;
+        emulator
+            .load_rom(&[0xF0, 0x29, 0xD0, 0x15, 0x00, 0xC2, 0x00, 0xFB, 0x00, 0xFC])
+            .unwrap();
+        emulator.tick();
+        emulator.tick();
+        assert!(emulator.is_pixel_on(3, 0));
+
+        emulator.tick();
+        assert!(!emulator.is_pixel_on(3, 0));
+        assert!(emulator.is_pixel_on(3, 2));
+        emulator.tick();
+        assert!(!emulator.is_pixel_on(0, 2));
+        assert!(emulator.is_pixel_on(7, 2));
+        emulator.tick();
+        assert!(emulator.is_pixel_on(0, 2));
+        assert!(emulator.is_pixel_on(3, 3));
+        assert!(!emulator.is_pixel_on(4, 2));
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn lores_scroll_follows_preset() {
+        for (configuration, top_row_on) in [
+            (EmulatorConfiguration::super_chip_legacy(), true),
+            (EmulatorConfiguration::super_chip_modern(), false),
+        ] {
+            let mut emulator = Emulator::new().with_configuration(configuration).unwrap();
+            emulator
+                .load_rom(&[0xF0, 0x29, 0xD0, 0x15, 0x00, 0xC1])
+                .unwrap();
+            for _ in 0..3 {
+                emulator.tick();
+            }
+            assert_eq!(top_row_on, emulator.is_pixel_on(1, 0));
+            assert!(emulator.is_pixel_on(1, 1));
+            assert!(!emulator.is_pixel_on(1, 2));
+            assert!(emulator.is_pixel_on(1, 5));
+            assert!(!emulator.is_pixel_on(1, 6));
+        }
+    }
+
+    #[test]
+    fn scrolling_needs_super_chip() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.invalid_opcode = InvalidOpcodeStyle::Halt;
+        emulator
+            .load_rom(&[0xF0, 0x29, 0xD0, 0x15, 0x00, 0xC2])
+            .unwrap();
+        for _ in 0..3 {
+            emulator.tick();
+        }
+        assert!(emulator.is_pixel_on(3, 0));
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::InvalidOpcode {
+                pc: 0x204,
+                opcode: 0x00C2
+            })),
+            emulator.halt_reason()
+        );
+    }
+
+    #[test]
+    fn display_mode_needs_super_chip() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.invalid_opcode = InvalidOpcodeStyle::Halt;
+        emulator.load_rom(&HIRES_ROM).unwrap();
+        emulator.tick();
+        assert_eq!(DisplayMode::LowRes, emulator.display_mode());
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::InvalidOpcode {
+                pc: 0x200,
+                opcode: 0x00FF
+            })),
+            emulator.halt_reason()
+        );
+    }
+
+    #[test]
+    fn loading_a_rom_resets_display_mode() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.platform = Platform::XoChip;
+        emulator.load_rom(&HIRES_ROM).unwrap();
+        emulator.tick();
+        emulator.load_rom(&HIRES_ROM).unwrap();
+        assert_eq!(DisplayMode::LowRes, emulator.display_mode());
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn can_load_long_i() {
+        let mut emulator = Emulator::new()
+            .with_configuration(EmulatorConfiguration::xo_chip())
+            .unwrap();
+        emulator
+            .load_rom(&[0xF0, 0x00, 0x12, 0x34, 0x60, 0x2A, 0xF0, 0x55])
+            .unwrap();
+        emulator.tick();
+        assert_eq!(0x1234, emulator.i());
+        assert_eq!(CHIP8_START as u16 + 4, emulator.pc());
+
+        emulator.tick();
+        emulator.tick();
+        assert_eq!(0x2A, emulator.memory.read_u8(0x1234));
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn skips_step_over_long_i() {
+        // Each skip is taken and followed by F000 NNNN
+        let skips: [[u8; 2]; 6] = [
+            [0x30, 0x00],
+            [0x40, 0x01],
+            [0x50, 0x20],
+            [0x90, 0x10],
+            [0xE0, 0x9E],
+            [0xE0, 0xA1],
+        ];
+        for skip in skips {
+            for (configuration, pc) in [
+                (EmulatorConfiguration::xo_chip(), 0x206),
+                (EmulatorConfiguration::super_chip(), 0x204),
+            ] {
+                let mut emulator = Emulator::new().with_configuration(configuration).unwrap();
+                emulator
+                    .load_rom(&[skip[0], skip[1], 0xF0, 0x00, 0x12, 0x34, 0x61, 0x01])
+                    .unwrap();
+                *emulator.cpu.register_mut(1) = 1;
+                if skip == [0xE0, 0x9E] {
+                    emulator.press_key(0);
+                }
+                emulator.tick();
+                assert_eq!(pc, emulator.pc(), "{:02X?}", skip);
+            }
+        }
+    }
+
+    /// The colors of the first pixels of a row
+    #[cfg(feature = "xochip")]
+    fn row_colors<C: Clock>(emulator: &Emulator<C>, y: u8) -> [u8; 7] {
+        core::array::from_fn(|x| emulator.pixel_color(x as u8, y))
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn planes_combine_into_colors() {
+        let mut emulator = Emulator::new()
+            .with_configuration(EmulatorConfiguration::xo_chip())
+            .unwrap();
+        emulator
+            .load_rom(&[0xF0, 0x29, 0xD0, 0x15, 0xF2, 0x01, 0x61, 0x02, 0xD1, 0x05])
+            .unwrap();
+        for _ in 0..5 {
+            emulator.tick();
+        }
+        assert_eq!([1, 1, 3, 3, 2, 2, 0], row_colors(&emulator, 0));
+        assert_eq!([1, 0, 2, 1, 0, 2, 0], row_colors(&emulator, 1));
+        assert_eq!(0, *emulator.cpu.carry());
+        assert!(emulator.is_pixel_on(5, 0));
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn clear_and_scroll_affect_selected_planes() {
+        let mut emulator = Emulator::new()
+            .with_configuration(EmulatorConfiguration::xo_chip())
+            .unwrap();
+        emulator
+            .load_rom(&[
+                0xF0, 0x29, 0xF3, 0x01, 0xD0, 0x15, 0xF1, 0x01, 0x00, 0xE0, 0xF2, 0x01, 0x00, 0xFB,
+            ])
+            .unwrap();
+        for _ in 0..3 {
+            emulator.tick();
+        }
+        // Both planes drew the 0, followed by the 1 in the second plane
+        assert_eq!([1, 1, 3, 1, 0, 0, 0], row_colors(&emulator, 0));
+
+        emulator.tick();
+        emulator.tick();
+        assert_eq!([0, 0, 2, 0, 0, 0, 0], row_colors(&emulator, 0));
+
+        emulator.tick();
+        emulator.tick();
+        assert_eq!([0, 0, 0, 0, 0, 0, 2], row_colors(&emulator, 0));
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn drawing_on_both_planes_collides_on_either() {
+        let mut emulator = Emulator::new()
+            .with_configuration(EmulatorConfiguration::xo_chip())
+            .unwrap();
+        emulator
+            .load_rom(&[0xF0, 0x29, 0xF2, 0x01, 0xD0, 0x15, 0xF3, 0x01, 0xD0, 0x15])
+            .unwrap();
+        for _ in 0..5 {
+            emulator.tick();
+        }
+        assert_eq!(1, *emulator.cpu.carry());
+        // The second plane holds the 0 drawn before xor the 1
+        assert_eq!([3, 3, 1, 3, 0, 0, 0], row_colors(&emulator, 0));
+        assert_eq!([3, 2, 2, 3, 0, 0, 0], row_colors(&emulator, 1));
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn draw_info_reports_collisions_per_plane() {
+        let mut emulator = Emulator::new()
+            .with_configuration(EmulatorConfiguration::xo_chip())
+            .unwrap();
+        assert_eq!(None, emulator.last_draw_info());
+        // Draw on the second plane alone, then on both planes
+        emulator
+            .load_rom(&[
+                0xF2, 0x01, 0xA2, 0x0E, 0xD0, 0x11, 0xF3, 0x01, 0xA2, 0x0D, 0xD0, 0x11, 0x00, 0x00,
+                0x80, 0x80,
+            ])
+            .unwrap();
+        *emulator.cpu.register_mut(0) = 3;
+        *emulator.cpu.register_mut(1) = 2;
+        for _ in 0..3 {
+            emulator.tick();
+        }
+        let first = DrawInfo {
+            x: 3,
+            y: 2,
+            planes: 0b10,
+            collisions: 0,
+        };
+        assert_eq!(Some(&first), emulator.last_draw_info());
+
+        for _ in 0..3 {
+            emulator.tick();
+        }
+        assert_eq!(1, *emulator.cpu.carry());
+        assert_eq!(
+            Some(&DrawInfo {
+                planes: 0b11,
+                collisions: 0b10,
+                ..first
+            }),
+            emulator.last_draw_info()
+        );
+    }
+
+    #[test]
+    fn planes_need_xo_chip() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.platform = Platform::SuperChip;
+        emulator.configuration.invalid_opcode = InvalidOpcodeStyle::Halt;
+        emulator.load_rom(&[0xF2, 0x01]).unwrap();
+        emulator.tick();
+        assert_eq!(1, emulator.display.selected_planes());
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::InvalidOpcode {
+                pc: 0x200,
+                opcode: 0xF201
+            })),
+            emulator.halt_reason()
+        );
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn can_save_and_load_register_ranges() {
+        // (opcode, registers in the order they are stored)
+        let ranges: [(u16, &[u8]); 3] =
+            [(0x5240, &[2, 3, 4]), (0x5420, &[4, 3, 2]), (0x5770, &[7])];
+        for (opcode, registers) in ranges {
+            let mut emulator = Emulator::new()
+                .with_configuration(EmulatorConfiguration::xo_chip())
+                .unwrap();
+            let [high, low] = (opcode | 0x2).to_be_bytes();
+            let [load_high, load_low] = (opcode | 0x3).to_be_bytes();
+            emulator
+                .load_rom(&[0xA3, 0x00, high, low, load_high, load_low])
+                .unwrap();
+            for register in 0..16 {
+                *emulator.cpu.register_mut(register) = 0x10 + register;
+            }
+            emulator.tick();
+            emulator.tick();
+            for (offset, register) in registers.iter().enumerate() {
+                assert_eq!(
+                    0x10 + register,
+                    emulator.memory.read_u8(0x300 + offset as u16),
+                    "{:04X}",
+                    opcode
+                );
+            }
+            assert_eq!(0, emulator.memory.read_u8(0x300 + registers.len() as u16));
+            assert_eq!(0x300, emulator.i());
+
+            for register in 0..16 {
+                *emulator.cpu.register_mut(register) = 0;
+            }
+            emulator.tick();
+            for register in 0..16 {
+                let expected = match registers.contains(&register) {
+                    true => 0x10 + register,
+                    false => 0,
+                };
+                assert_eq!(expected, *emulator.cpu.register(register), "{:04X}", opcode);
+            }
+            assert_eq!(0x300, emulator.i());
+        }
+    }
+
+    #[test]
+    fn register_ranges_need_xo_chip() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.platform = Platform::SuperChip;
+        emulator.configuration.invalid_opcode = InvalidOpcodeStyle::Halt;
+        emulator.load_rom(&[0x51, 0x22]).unwrap();
+        emulator.tick();
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::InvalidOpcode {
+                pc: 0x200,
+                opcode: 0x5122
+            })),
+            emulator.halt_reason()
+        );
+    }
+
+    #[test]
+    fn long_i_needs_xo_chip() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.platform = Platform::SuperChip;
+        emulator.configuration.invalid_opcode = InvalidOpcodeStyle::Halt;
+        emulator.load_rom(&[0xF0, 0x00, 0x12, 0x34]).unwrap();
+        emulator.tick();
+        assert_eq!(0, emulator.i());
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::InvalidOpcode {
+                pc: 0x200,
+                opcode: 0xF000
+            })),
+            emulator.halt_reason()
+        );
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn xo_chip_loads_large_roms() {
+        let mut rom = vec![0; 8 * 1024];
+        rom[..6].copy_from_slice(&[0xF0, 0x00, 0x21, 0xFF, 0xF0, 0x65]);
+        rom[0x1FFF] = 0x42;
+
+        let mut emulator = Emulator::new();
+        assert_eq!(
+            Err(RomError::TooLarge {
+                len: 8 * 1024,
+                max: MEMORY_SIZE - CHIP8_START
+            }),
+            emulator.load_rom(&rom)
+        );
+
+        let mut emulator = Emulator::new()
+            .with_configuration(EmulatorConfiguration::xo_chip())
+            .unwrap();
+        emulator.load_rom(&rom).unwrap();
+        emulator.tick();
+        emulator.tick();
+        assert_eq!(0x42, emulator.get_register(Register::V0));
+        assert_eq!(Ok(()), emulator.set_i(0xFFFF));
+        assert_eq!(Ok(()), emulator.set_pc(0xFFFE));
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn dumps_and_loads_at_the_top_of_64_kib() {
+        let mut emulator = Emulator::new()
+            .with_configuration(EmulatorConfiguration::xo_chip())
+            .unwrap();
+        emulator.configuration.quirks.dump_load = DumpLoadStyle::IncrementI;
+        // LD [I], VF and LD VF, [I]
+        emulator.load_rom(&[0xFF, 0x55, 0xFF, 0x65]).unwrap();
+        emulator.set_register(Register::VF, 0x42);
+        emulator.set_i(0xFFF0).unwrap();
+        emulator.tick();
+        assert_eq!(None, emulator.halt_reason());
+        assert_eq!(0x42, emulator.memory.read_u8(0xFFFF));
+        // I wraps around the address space
+        assert_eq!(0x0000, emulator.i());
+
+        emulator.set_i(0xFFF0).unwrap();
+        emulator.tick();
+        assert_eq!(None, emulator.halt_reason());
+        assert_eq!(0x42, emulator.get_register(Register::VF));
+        assert_eq!(0x0000, emulator.i());
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn hosts_see_resolution_switches() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.platform = Platform::SuperChip;
+        emulator
+            .load_rom(&[0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFE])
+            .unwrap();
+        assert_eq!((64, 32), emulator.resolution());
+        assert!(!emulator.is_hires());
+
+        let mut frame = vec![0; 128 * 64 * 4];
+        for (resolution, switches) in [((128, 64), 1), ((128, 64), 1), ((64, 32), 2)] {
+            emulator.tick();
+            assert_eq!(resolution, emulator.resolution());
+            assert_eq!(resolution.0 == 128, emulator.is_hires());
+            assert_eq!(switches, emulator.mode_switches());
+
+            let (width, height) = (resolution.0 as usize, resolution.1 as usize);
+            #[cfg(feature = "std")]
+            {
+                let text = format!("{}", emulator.display);
+                assert_eq!(height, text.lines().count());
+                assert!(text.lines().all(|line| line.chars().count() == width));
+            }
+
+            let len = width * height * 4;
+            assert_eq!(
+                len,
+                emulator.render_rgba_into(&mut frame, 1, &Palette::octo())
+            );
+            let scale = 128 / width;
+            let full = emulator.render_rgba_into(&mut frame, scale, &Palette::octo());
+            assert_eq!(128 * 64 * 4, full);
         }
+
+        emulator.load_rom(&[0x00, 0xFF]).unwrap();
+        emulator.tick();
+        emulator.load_rom(&[0x00, 0xFF]).unwrap();
+        assert_eq!(4, emulator.mode_switches());
     }
 
-    fn shift_left(&mut self, write: u8, read: u8) {
-        self.copy_register(write, read);
-        self.shift_left_in_place(write);
+    #[test]
+    fn renders_rgba_pixels() {
+        const ON: [u8; 4] = [0xFF; 4];
+        const OFF: [u8; 4] = [0, 0, 0, 0xFF];
+        const PALETTE: Palette = Palette([OFF, ON, ON, ON]);
+        let mut emulator = Emulator::new();
+        emulator.load_rom(&[0xF0, 0x29, 0xD0, 0x01]).unwrap();
+        emulator.tick();
+        emulator.tick();
+
+        let mut frame = vec![0; 128 * 64 * 4];
+        emulator.render_rgba_into(&mut frame, 1, &PALETTE);
+        assert_eq!(pixels(&frame[..20]), [ON, ON, ON, ON, OFF]);
+        emulator.render_rgba_into(&mut frame, 2, &PALETTE);
+        assert_eq!(pixels(&frame[..36]), [ON, ON, ON, ON, ON, ON, ON, ON, OFF]);
+        assert_eq!(pixels(&frame[128 * 4 + 28..][..8]), [ON, OFF]);
+        assert_eq!(pixels(&frame[2 * 128 * 4..][..4]), [OFF]);
+
+        assert_eq!(0, emulator.render_rgba_into(&mut frame[..3], 1, &PALETTE));
+        assert_eq!(8, emulator.render_rgba_into(&mut frame[..10], 1, &PALETTE));
     }
 
-    fn shift_left_in_place(&mut self, register: u8) {
-        let leftmost = *self.cpu.register(register) & 0b1000_0000;
-        *self.cpu.register_mut(register) <<= 1;
-        if leftmost == 0b1000_0000 {
-            self.cpu.carry_on();
-        } else {
-            self.cpu.carry_off();
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn renders_every_palette_color() {
+        let mut emulator = Emulator::new()
+            .with_configuration(EmulatorConfiguration::xo_chip())
+            .unwrap();
+        // Colors 0 to 3 from the left, with one sprite row per plane
+        emulator
+            .load_rom(&[0xF3, 0x01, 0xA2, 0x08, 0xD0, 0x01, 0x12, 0x06, 0x50, 0x30])
+            .unwrap();
+        for _ in 0..3 {
+            emulator.tick();
         }
+
+        let palette = Palette::octo();
+        let mut frame = [0; 5 * 4];
+        emulator.render_rgba_into(&mut frame, 1, &palette);
+        let [background, first, second, both] = palette.0;
+        assert_eq!(
+            pixels(&frame),
+            [background, first, second, both, background]
+        );
     }
 
-    fn load_all_static(&mut self, until_register: u8) {
-        let mut start_address = *self.cpu.i();
-        for i in 0..=until_register {
-            *self.cpu.register_mut(i) = self.memory.read_u8(start_address + i as u16);
+    fn pixels(bytes: &[u8]) -> Vec<[u8; 4]> {
+        bytes
+            .chunks_exact(4)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+            .collect()
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn runs_until_breakpoint() {
+        let mut emulator = Emulator::new();
+        emulator.load_test_rom().unwrap();
+        // The draw inside the subroutine at 0x242, called from 0x2BE
+        let id = emulator.add_breakpoint(0x244);
+        emulator.add_breakpoint(0x400);
+
+        assert_eq!(
+            StopReason::Breakpoint(0x244),
+            emulator.run_until_breakpoint(1000)
+        );
+        assert_eq!(0x244, emulator.pc());
+        assert_eq!(vec![0x2BE], emulator.call_sites().collect::<Vec<_>>());
+        let ticks = emulator.tick_count();
+
+        // Continuing runs past the breakpoint
+        assert_eq!(StopReason::MaxTicks, emulator.run_until_breakpoint(1));
+        assert_eq!(ticks + 1, emulator.tick_count());
+        assert!(emulator.remove_breakpoint(id));
+        assert!(!emulator.remove_breakpoint(id));
+        // The rom ends jumping to itself
+        assert_eq!(
+            StopReason::IdleLoop { pc: 0x3DC },
+            emulator.run_until_breakpoint(1000)
+        );
+        assert_eq!(StopReason::MaxTicks, emulator.run_until_breakpoint(0));
+
+        emulator.clear_breakpoints();
+        emulator.load_rom(&[0xF0, 0x0A]).unwrap();
+        assert_eq!(StopReason::WaitingForKey, emulator.run_until_breakpoint(10));
+        emulator.pause();
+        assert_eq!(StopReason::Paused, emulator.run_until_breakpoint(10));
+        emulator.resume();
+        emulator.load_rom(&[0xFF, 0xFF]).unwrap();
+        emulator.configuration.invalid_opcode = InvalidOpcodeStyle::Halt;
+        assert_eq!(StopReason::Halted, emulator.run_until_breakpoint(10));
+    }
+
+    #[test]
+    fn steps_report_what_ran() {
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x05, // 0x200: LD V0, 0x05
+            0x30, 0x05, // 0x202: SE V0, 0x05
+            0x00, 0xE0, // 0x204: skipped
+            0xA2, 0x0E, // 0x206: LD I, 0x20E
+            0xD0, 0x01, // 0x208: DRW V0, V0, 1
+            0xF1, 0x0A, // 0x20A: LD V1, K
+            0x00, 0x00,
+            0x80,       // 0x20E: sprite
+        ];
+        let mut emulator = Emulator::new();
+        emulator.load_rom(&rom).unwrap();
+        let steps = [
+            (0x200, 0x6005, "LD V0, 0x05", 0x202),
+            (0x202, 0x3005, "SE V0, 0x05", 0x206),
+            (0x206, 0xA20E, "LD I, 0x20E", 0x208),
+            (0x208, 0xD001, "DRW V0, V0, 1", 0x20A),
+            (0x20A, 0xF10A, "LD V1, K", 0x20A),
+        ];
+        for (index, (pc, opcode, mnemonic, next_pc)) in steps.into_iter().enumerate() {
+            let step = emulator.step().unwrap();
+            assert_eq!((pc, opcode, next_pc), (step.pc, step.opcode, step.next_pc));
+            assert_eq!(mnemonic, step.instruction.to_string());
+            assert_eq!(index == 1, step.skipped);
+            assert_eq!(index == 3, step.drew);
+            assert_eq!(index == 4, step.waited_for_key);
         }
+
+        emulator.pause();
+        assert_eq!(None, emulator.step());
     }
 
-    fn load_all_variable(&mut self, until_register: u8) {
-        for i in 0..=until_register {
-            *self.cpu.i_mut() += i as u16;
-            *self.cpu.register_mut(i) = self.memory.read_u8(*self.cpu.i());
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn profiles_the_hot_loop() {
+        let mut emulator = Emulator::new();
+        assert!(emulator.address_profile().is_empty());
+        emulator.enable_address_profiling();
+        emulator
+            .load_rom(include_bytes!("../roms/IBM_Logo.ch8"))
+            .unwrap();
+        for _ in 0..1000 {
+            emulator.tick();
         }
+        let hottest = emulator.top_n(2);
+        assert_eq!(2, hottest.len());
+        let (address, count, instruction) = hottest[0];
+        assert_eq!(0x228, address);
+        assert!(count > 900);
+        assert_eq!("JP 0x228", instruction.to_string());
+        assert_eq!(1, hottest[1].1);
+        assert_eq!(count, emulator.address_profile()[(0x228 - 0x200) / 2]);
+        assert_eq!(1000, emulator.address_profile().iter().sum::<u32>());
+
+        emulator
+            .load_rom(include_bytes!("../roms/IBM_Logo.ch8"))
+            .unwrap();
+        assert!(emulator.top_n(1).is_empty());
+        emulator.disable_address_profiling();
+        emulator.tick();
+        assert!(emulator.address_profile().is_empty());
     }
 
-    fn dump_all_static(&mut self, until_register: u8) {
-        let mut start_address = *self.cpu.i();
-        for i in 0..=until_register {
-            self.memory
-                .write_u8(start_address + i as u16, *self.cpu.register(i));
+    #[test]
+    #[cfg(feature = "std")]
+    fn dumps_the_whole_state() {
+        let mut emulator = Emulator::new();
+        emulator
+            .load_rom(include_bytes!("../roms/IBM_Logo.ch8"))
+            .unwrap();
+        for _ in 0..25 {
+            emulator.tick();
         }
+        assert_eq!(include_str!("../roms/IBM_Logo.dump"), emulator.dump_state());
+        let summary = format!("{:?}", emulator);
+        assert!(summary.starts_with("Emulator { cpu: CpuSnapshot { v: ["));
+        assert!(summary.contains("tick_count: 25"));
     }
 
-    fn dump_all_variable(&mut self, until_register: u8) {
-        for i in 0..=until_register {
-            *self.cpu.i_mut() += i as u16;
-            self.memory.write_u8(*self.cpu.i(), *self.cpu.register(i));
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn logs_memory_accesses_by_instruction() {
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x0A, // 0x200: LD V0, 0x0A
+            0x61, 0x0B, // 0x202: LD V1, 0x0B
+            0x62, 0x0C, // 0x204: LD V2, 0x0C
+            0xA3, 0x00, // 0x206: LD I, 0x300
+            0xF2, 0x55, // 0x208: LD [I], V2
+            0xA3, 0x01, // 0x20A: LD I, 0x301
+            0xF1, 0x65, // 0x20C: LD V1, [I]
+        ];
+        let mut emulator = Emulator::new();
+        emulator.load_rom(&rom).unwrap();
+        emulator.enable_memory_access_log(0x300..0x302, false);
+        for _ in 0..5 {
+            emulator.tick();
+        }
+        let write = |address, value| MemoryAccess {
+            tick: 5,
+            pc: 0x208,
+            address,
+            kind: AccessKind::Write,
+            value,
+        };
+        let accesses: Vec<_> = emulator.memory_access_log().collect();
+        assert_eq!(vec![write(0x300, 0x0A), write(0x301, 0x0B)], accesses);
+        assert_eq!(0, emulator.memory_access_log().count());
+
+        emulator.tick();
+        emulator.tick();
+        assert_eq!(0, emulator.memory_access_log().count());
+
+        let mut emulator = Emulator::new();
+        emulator.load_rom(&rom).unwrap();
+        emulator.enable_memory_access_log(0x300..0x303, true);
+        for _ in 0..7 {
+            emulator.tick();
         }
+        let reads: Vec<_> = emulator
+            .memory_access_log()
+            .filter(|access| access.kind == AccessKind::Read)
+            .map(|access| (access.tick, access.pc, access.address, access.value))
+            .collect();
+        assert_eq!(
+            vec![(7, 0x20C, 0x301, 0x0B), (7, 0x20C, 0x302, 0x0C)],
+            reads
+        );
+
+        emulator.disable_memory_access_log();
+        assert_eq!(0, emulator.memory_access_log().count());
     }
 
-    fn draw(&mut self, register_x: u8, register_y: u8, value: u8) {
-        let x = *self.cpu.register(register_x) % 64;
-        let y = *self.cpu.register(register_y) % 32;
-        let height = value;
-        let start_address = *self.cpu.i();
-        let mut did_turn_off_pixel = false;
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn breaks_on_instruction_kinds() {
+        let mut emulator = Emulator::new();
+        emulator
+            .load_rom(include_bytes!("../roms/IBM_Logo.ch8"))
+            .unwrap();
+        emulator.resume();
+        let draw = emulator.add_kind_breakpoint(InstructionKind::DrawSprite);
+        assert_eq!(
+            StopReason::Breakpoint(0x208),
+            emulator.run_until_breakpoint(100)
+        );
+        assert_eq!(
+            StopReason::Breakpoint(0x20E),
+            emulator.run_until_breakpoint(100)
+        );
 
-        for (y_offset, address) in (start_address..start_address + height as u16).enumerate() {
-            let y_pos = y as usize + y_offset;
-            if y_pos > 32 {
-                break;
-            }
-            let y_pos = y_pos as u8;
+        emulator.remove_breakpoint(draw);
+        emulator.add_kind_breakpoint(InstructionKind::Jump);
+        assert_eq!(
+            StopReason::Breakpoint(0x228),
+            emulator.run_until_breakpoint(100)
+        );
+    }
 
-            // Bits are right-to-left, but we draw left-to right
-            // so we need to reverse the sprite bits after reading
-            let sprite_row = self.memory.read_u8(address).reverse_bits();
-            for x_offset in 0..u8::BITS {
-                let x_pos = x as u32 + x_offset;
-                if x_pos > 64 {
-                    break;
-                }
-                let x_pos = x_pos as u8;
+    /// Counts V3 up from 0 in a loop at 0x202
+    #[cfg(feature = "alloc")]
+    const COUNTING_LOOP_ROM: [u8; 6] = [0x63, 0x00, 0x73, 0x01, 0x12, 0x02];
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn conditional_breakpoints_stop_at_the_right_iteration() {
+        let mut emulator = Emulator::new();
+        emulator.load_rom(&COUNTING_LOOP_ROM).unwrap();
+        emulator.add_breakpoint_when(0x202, BreakCondition::RegisterEquals(Register::V3, 5));
+        assert_eq!(
+            StopReason::Breakpoint(0x202),
+            emulator.run_until_breakpoint(100)
+        );
+        assert_eq!(5, emulator.get_register(Register::V3));
+        assert_eq!(11, emulator.tick_count());
+
+        emulator.clear_breakpoints();
+        emulator.add_breakpoint_when(
+            0x202,
+            BreakCondition::MemoryEquals {
+                address: 0x203,
+                value: 2,
+            },
+        );
+        assert_eq!(StopReason::MaxTicks, emulator.run_until_breakpoint(100));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn register_watches_fire_on_the_writing_instruction() {
+        let mut emulator = Emulator::new();
+        emulator.load_rom(&COUNTING_LOOP_ROM).unwrap();
+        let changed = emulator.add_register_watch(Register::V3, WatchMode::Changed);
+        let StopReason::Watch(hit) = emulator.run_until_breakpoint(100) else {
+            panic!("the watch did not fire");
+        };
+        assert_eq!(
+            (changed, 0x202, 0, 1),
+            (hit.id, hit.pc, hit.before, hit.after)
+        );
+        assert_eq!(0x204, emulator.pc());
+
+        emulator.remove_breakpoint(changed);
+        emulator.add_register_watch(Register::V3, WatchMode::Equals(5));
+        let StopReason::Watch(hit) = emulator.run_until_breakpoint(100) else {
+            panic!("the watch did not fire");
+        };
+        assert_eq!((0x202, 4, 5), (hit.pc, hit.before, hit.after));
+
+        emulator.clear_breakpoints();
+        assert_eq!(StopReason::MaxTicks, emulator.run_until_breakpoint(100));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn breakpoint_predicates_see_the_cpu_before_executing() {
+        let mut emulator = Emulator::new();
+        emulator.load_rom(&COUNTING_LOOP_ROM).unwrap();
+        emulator.add_breakpoint_if(0x204, |cpu| cpu.v[3] == 3);
+        assert_eq!(
+            StopReason::Breakpoint(0x204),
+            emulator.run_until_breakpoint(100)
+        );
+        assert_eq!(3, emulator.get_register(Register::V3));
+        assert_eq!(0x204, emulator.pc());
+    }
+
+    /// Calls a subroutine at 0x206, which calls another one at 0x20E
+    #[cfg(feature = "alloc")]
+    const NESTED_CALLS_ROM: [u8; 20] = [
+        0x22, 0x06, 0x60, 0x01, 0x12, 0x04, 0x61, 0x02, 0x22, 0x0E, 0x62, 0x03, 0x00, 0xEE, 0x63,
+        0x04, 0x64, 0x05, 0x00, 0xEE,
+    ];
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn steps_over_calls() {
+        let mut emulator = Emulator::new();
+        emulator.load_rom(&NESTED_CALLS_ROM).unwrap();
+        assert_eq!(StopReason::Stepped, emulator.step_over(100));
+        assert_eq!((0x202, 0), (emulator.pc(), emulator.call_depth()));
+        assert_eq!(4, emulator.get_register(Register::V3));
+
+        emulator.load_rom(&NESTED_CALLS_ROM).unwrap();
+        emulator.tick();
+        assert_eq!(StopReason::Stepped, emulator.step_over(100));
+        assert_eq!((0x208, 1), (emulator.pc(), emulator.call_depth()));
+        assert_eq!(StopReason::Stepped, emulator.step_over(100));
+        assert_eq!((0x20A, 1), (emulator.pc(), emulator.call_depth()));
+
+        emulator.load_rom(&NESTED_CALLS_ROM).unwrap();
+        assert_eq!(StopReason::MaxTicks, emulator.step_over(2));
+        assert_eq!(0x208, emulator.pc());
+        emulator.add_breakpoint(0x210);
+        emulator.load_rom(&NESTED_CALLS_ROM).unwrap();
+        assert_eq!(StopReason::Breakpoint(0x210), emulator.step_over(100));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn steps_out_of_subroutines() {
+        let mut emulator = Emulator::new();
+        emulator.load_rom(&NESTED_CALLS_ROM).unwrap();
+        for _ in 0..3 {
+            emulator.tick();
+        }
+        assert_eq!((0x20E, 2), (emulator.pc(), emulator.call_depth()));
+        assert_eq!(StopReason::Stepped, emulator.step_out(100));
+        assert_eq!((0x20A, 1), (emulator.pc(), emulator.call_depth()));
+        assert_eq!(StopReason::Stepped, emulator.step_out(100));
+        assert_eq!((0x202, 0), (emulator.pc(), emulator.call_depth()));
+        assert_eq!(StopReason::IdleLoop { pc: 0x204 }, emulator.step_out(5));
+
+        emulator.configuration.invalid_opcode = InvalidOpcodeStyle::Halt;
+        emulator.load_rom(&[0x22, 0x02, 0xFF, 0xFF]).unwrap();
+        emulator.tick();
+        assert_eq!(StopReason::Halted, emulator.step_out(100));
+        assert_eq!(StopReason::Halted, emulator.step_over(100));
+    }
+
+    #[test]
+    fn pausing_freezes_timers() {
+        let mut emulator = Emulator::with_clock(ManualClock::new());
+        *emulator.cpu.register_mut(0) = 60;
+        emulator.memory.write_u16(CHIP8_START as u16, 0xF015);
+        emulator.tick();
+
+        emulator.pause();
+        assert!(emulator.is_paused());
+        emulator.advance_time_ms(10_000);
+        assert_eq!(Ok(TickOutcome::Paused), emulator.try_tick());
+        assert_eq!(60, emulator.delay());
+        assert_eq!(CHIP8_START as u16 + 2, emulator.pc());
+
+        emulator.resume();
+        assert!(!emulator.is_paused());
+        emulator.tick();
+        assert_eq!(60, emulator.delay());
+
+        emulator.advance_time_ms(500);
+        emulator.tick();
+        assert_eq!(30, emulator.delay());
+    }
+
+    #[test]
+    fn can_run_subroutines() {
+        let mut emulator = Emulator::new();
+        let subroutine_address = 0x0300;
+        emulator.memory.write_u16(CHIP8_START as u16, 0x2300);
+        emulator.memory.write_u16(subroutine_address, 0x00EE);
+
+        assert_eq!(CHIP8_START as u16, *emulator.cpu.pc());
+        emulator.tick();
+        assert_eq!(subroutine_address, *emulator.cpu.pc());
+        emulator.tick();
+        assert_eq!(CHIP8_START as u16 + 2, *emulator.cpu.pc());
+    }
+
+    #[test]
+    fn can_inspect_call_stack() {
+        let mut emulator = Emulator::new();
+        emulator.memory.write_u16(CHIP8_START as u16, 0x2300);
+        emulator.memory.write_u16(0x300, 0x2400);
+        emulator.memory.write_u16(0x400, 0x00EE);
+        emulator.memory.write_u16(0x302, 0x00EE);
+
+        emulator.tick();
+        emulator.tick();
+        assert_eq!(2, emulator.call_depth());
+        assert_eq!(&[0x202, 0x302], emulator.call_stack());
+        assert_eq!(
+            vec![0x200, 0x300],
+            emulator.call_sites().collect::<Vec<_>>()
+        );
+
+        emulator.tick();
+        assert_eq!(1, emulator.call_depth());
+        assert_eq!(&[0x202], emulator.call_stack());
+        emulator.tick();
+        assert_eq!(0, emulator.call_depth());
+    }
+
+    #[test]
+    fn stack_overflow_faults() {
+        // Call into itself forever
+        let rom = [0x22, 0x00];
+        let mut emulator = Emulator::new().with_rom(&rom).unwrap();
+
+        for _ in 0..16 {
+            emulator.tick();
+        }
+        assert_eq!(None, emulator.halt_reason());
+
+        emulator.tick();
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::StackOverflow { pc: 0x200 })),
+            emulator.halt_reason()
+        );
+        emulator.tick();
+        assert_eq!(0x202, *emulator.cpu.pc());
+    }
+
+    #[test]
+    fn stack_underflow_faults() {
+        let rom = [0x00, 0xEE];
+        let mut emulator = Emulator::new().with_rom(&rom).unwrap();
+
+        emulator.tick();
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::StackUnderflow { pc: 0x200 })),
+            emulator.halt_reason()
+        );
+
+        let fingerprint = emulator.state_fingerprint();
+        emulator.tick();
+        emulator.tick();
+        assert_eq!(fingerprint, emulator.state_fingerprint());
+    }
+
+    #[test]
+    fn try_tick_reports_errors() {
+        let mut emulator = Emulator::new().with_rom(&[0x00, 0xEE]).unwrap();
+        let error = Chip8Error::StackUnderflow { pc: 0x200 };
+        assert_eq!(Err(error.clone()), emulator.try_tick());
+        assert_eq!(Err(error), emulator.try_tick());
+
+        let mut emulator = Emulator::new().with_rom(&[0x22, 0x00]).unwrap();
+        for _ in 0..16 {
+            assert_eq!(Ok(TickOutcome::Executed), emulator.try_tick());
+        }
+        assert_eq!(
+            Err(Chip8Error::StackOverflow { pc: 0x200 }),
+            emulator.try_tick()
+        );
+
+        let mut emulator = Emulator::new().with_rom(&[0x60, 0x01]).unwrap();
+        let outcome = (0..MEMORY_SIZE).map(|_| emulator.try_tick()).last();
+        assert_eq!(Some(Err(Chip8Error::PcOutOfRange { pc: 0x1000 })), outcome);
+
+        let mut emulator = Emulator::new().with_rom(&[0xF0, 0x0A]).unwrap();
+        assert_eq!(Ok(TickOutcome::WaitingForKey), emulator.try_tick());
+    }
 
-                let should_flip = sprite_row >> x_offset & 1 == 1;
-                if !should_flip {
-                    continue;
+    #[test]
+    fn invalid_opcodes_are_skipped() {
+        let mut emulator = Emulator::new().with_rom(&[0x8F, 0xF9, 0x60, 0x01]).unwrap();
+        assert_eq!(Ok(TickOutcome::Executed), emulator.try_tick());
+        assert_eq!(
+            Some(&Chip8Error::InvalidOpcode {
+                pc: 0x200,
+                opcode: 0x8FF9
+            }),
+            emulator.last_fault()
+        );
+        assert_eq!(None, emulator.halt_reason());
+
+        emulator.tick();
+        assert_eq!(1, *emulator.cpu.register(0));
+    }
+
+    /// The level, the span and the fields of every event
+    #[cfg(feature = "tracing")]
+    #[derive(Clone, Default)]
+    struct CaptureLayer(std::sync::Arc<std::sync::Mutex<Vec<CapturedEvent>>>);
+
+    #[cfg(feature = "tracing")]
+    type CapturedEvent = (tracing::Level, Option<&'static str>, Vec<(String, String)>);
+
+    #[cfg(feature = "tracing")]
+    impl<S> tracing_subscriber::Layer<S> for CaptureLayer
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            context: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            struct Fields(Vec<(String, String)>);
+            impl tracing::field::Visit for Fields {
+                fn record_debug(
+                    &mut self,
+                    field: &tracing::field::Field,
+                    value: &dyn core::fmt::Debug,
+                ) {
+                    self.0
+                        .push((field.name().to_string(), format!("{:?}", value)));
                 }
+            }
+            let mut fields = Fields(Vec::new());
+            event.record(&mut fields);
+            let span = context.event_span(event).map(|span| span.name());
+            self.0
+                .lock()
+                .unwrap()
+                .push((*event.metadata().level(), span, fields.0));
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn invalid_opcodes_emit_structured_warnings() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let capture = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let mut emulator = Emulator::new().with_rom(&[0x8F, 0xF9, 0x60, 0x01]).unwrap();
+            emulator.tick();
+            emulator.tick();
+        });
+
+        let events = capture.0.lock().unwrap();
+        let field = |fields: &[(String, String)], name: &str| {
+            fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value.clone())
+        };
+        let (_, span, fields) = events
+            .iter()
+            .find(|(level, _, _)| *level == tracing::Level::WARN)
+            .unwrap();
+        assert_eq!(Some("tick"), *span);
+        assert_eq!(Some("invalid opcode".to_string()), field(fields, "message"));
+        assert_eq!(Some("512".to_string()), field(fields, "pc"));
+        assert_eq!(Some(0x8FF9.to_string()), field(fields, "opcode"));
+
+        let (_, span, fields) = events
+            .iter()
+            .rfind(|(_, _, fields)| field(fields, "mnemonic").is_some())
+            .unwrap();
+        assert_eq!(Some("tick"), *span);
+        assert_eq!(Some("514".to_string()), field(fields, "pc"));
+        assert_eq!(Some("LD V0, 0x01".to_string()), field(fields, "mnemonic"));
+    }
+
+    #[test]
+    fn invalid_opcodes_can_halt() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.invalid_opcode = InvalidOpcodeStyle::Halt;
+        emulator.load_rom(&[0x8F, 0xF9, 0x60, 0x01]).unwrap();
+
+        let error = Chip8Error::InvalidOpcode {
+            pc: 0x200,
+            opcode: 0x8FF9,
+        };
+        assert_eq!(Err(error.clone()), emulator.try_tick());
+        assert_eq!(Some(&error), emulator.last_fault());
+        assert_eq!(
+            Some(&HaltReason::Fault(error.clone())),
+            emulator.halt_reason()
+        );
+
+        emulator.tick();
+        assert_eq!(0, *emulator.cpu.register(0));
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn rom_can_exit() {
+        let mut emulator = Emulator::new();
+        emulator.configuration.platform = Platform::SuperChip;
+        emulator.load_rom(&[0x60, 0x01, 0x00, 0xFD]).unwrap();
+        assert_eq!(Ok(TickOutcome::Executed), emulator.try_tick());
+        assert_eq!(Ok(TickOutcome::Exited), emulator.try_tick());
+        assert!(emulator.is_halted());
+        assert_eq!(Some(&HaltReason::Exited), emulator.halt_reason());
+        assert_eq!(None, emulator.last_fault());
 
-                did_turn_off_pixel |= self.display.flip_pixel(x_pos, y_pos);
-            }
+        let tick_count = emulator.tick_count();
+        for _ in 0..3 {
+            assert_eq!(Ok(TickOutcome::Exited), emulator.try_tick());
+            assert_eq!(0x204, emulator.pc());
         }
+        assert_eq!(tick_count, emulator.tick_count());
 
-        if did_turn_off_pixel {
-            self.cpu.carry_on();
-        }
+        emulator.load_rom(&[0x00, 0xFD]).unwrap();
+        assert!(!emulator.is_halted());
+        assert_eq!(None, emulator.halt_reason());
     }
 
-    fn wait_key(&mut self, key_register: u8) {
-        self.register_awaiting_input = Some(key_register);
-        self.cpu.rollback_pc();
-    }
+    #[test]
+    fn can_recover_from_halt() {
+        let mut emulator = Emulator::new().with_rom(&[0x00, 0xEE]).unwrap();
+        assert!(!emulator.is_halted());
 
-    fn resume_from_wait_key(&mut self, key_pressed: u8) {
-        if let Some(register) = self.register_awaiting_input {
-            *self.cpu.register_mut(register) = key_pressed;
-            self.register_awaiting_input = None;
-            self.cpu.advance_pc();
-        } else {
-            log::warn!("Waited for key input, but did not set a register to receive said input...");
-        }
-    }
+        emulator.tick();
+        assert!(emulator.is_halted());
+        let error = Chip8Error::StackUnderflow { pc: 0x200 };
+        assert_eq!(
+            Some(&HaltReason::Fault(error.clone())),
+            emulator.halt_reason()
+        );
 
-    fn load_delay(&mut self, register: u8) {
-        *self.cpu.register_mut(register) = *self.cpu.delay();
-    }
+        let fingerprint = emulator.state_fingerprint();
+        assert_eq!(Err(error), emulator.try_tick());
+        assert_eq!(fingerprint, emulator.state_fingerprint());
 
-    fn set_delay(&mut self, register: u8) {
-        self.delay_timer.tick();
-        *self.cpu.delay_mut() = *self.cpu.register(register);
+        emulator.load_rom(&[0x60, 0x01]).unwrap();
+        assert!(!emulator.is_halted());
+        assert_eq!(None, emulator.halt_reason());
+        assert_eq!(Ok(TickOutcome::Executed), emulator.try_tick());
+        assert_eq!(1, *emulator.cpu.register(0));
     }
 
-    fn set_sound(&mut self, register: u8) {
-        self.sound_timer.tick();
-        *self.cpu.sound_mut() = *self.cpu.register(register);
+    #[test]
+    #[cfg(feature = "std")]
+    fn errors_display_pc() {
+        let error = Chip8Error::StackOverflow { pc: 0x2A4 };
+        assert_eq!(
+            "stack overflow calling a subroutine at 0x2A4",
+            error.to_string()
+        );
     }
-}
 
-#[cfg(test)]
-mod test {
-    use crate::memory::CHIP8_START;
+    #[test]
+    fn with_configuration_validates() {
+        let configuration = EmulatorConfiguration {
+            stack_depth: 0,
+            ..EmulatorConfiguration::cosmac_vip()
+        };
+        assert_eq!(
+            Some(ConfigError::InvalidStackDepth),
+            Emulator::new().with_configuration(configuration).err()
+        );
 
-    use super::*;
+        let configuration = EmulatorConfiguration {
+            start_address: ETI660_START_ADDRESS,
+            ..EmulatorConfiguration::cosmac_vip()
+        };
+        let emulator = Emulator::new().with_configuration(configuration).unwrap();
+        assert_eq!(configuration, emulator.configuration);
+        assert_eq!(ETI660_START_ADDRESS, emulator.pc());
+    }
 
     #[test]
-    fn can_jump() {
+    fn stack_depth_is_configurable() {
+        let rom = [0x22, 0x00];
         let mut emulator = Emulator::new();
-        emulator.memory.write_u16(CHIP8_START as u16, 0x1300);
+        emulator.configuration.stack_depth = crate::config::ORIGINAL_STACK_DEPTH;
+        emulator.load_rom(&rom).unwrap();
 
-        assert_eq!(CHIP8_START as u16, *emulator.cpu.pc());
+        for _ in 0..12 {
+            emulator.tick();
+        }
+        assert_eq!(None, emulator.halt_reason());
         emulator.tick();
-        assert_eq!(0x0300, *emulator.cpu.pc());
+        assert!(emulator.halt_reason().is_some());
     }
 
     #[test]
-    fn can_skip_instructions() {
+    fn buffers_cannot_overflow() {
         let mut emulator = Emulator::new();
-        let ptr_start = CHIP8_START as u16;
-        emulator.memory.write_u16(ptr_start, 0x3012);
-        *emulator.cpu.register_mut(0) = 0x12;
+        emulator.configuration.pc_overflow = PcOverflowStyle::Wrap;
+        *emulator.cpu.pc_mut() = 0x0FFF;
 
-        // Value equals value stored in register 0
-        assert_eq!(ptr_start, *emulator.cpu.pc());
         emulator.tick();
-        assert_eq!(ptr_start + 4, *emulator.cpu.pc());
+        assert_eq!(CHIP8_START as u16 + 2, *emulator.cpu.pc());
+        *emulator.cpu.pc_mut() = 0x0FFE;
 
-        // Value not equals value stored in register 0
-        emulator.memory.write_u16(ptr_start + 4, 0x4005);
+        // The last word in memory is still a valid instruction
         emulator.tick();
-        assert_eq!(ptr_start + 8, *emulator.cpu.pc());
-
-        // Values stored in registers 0 and 1 are equal
-        emulator.memory.write_u16(ptr_start + 8, 0x5010);
-        *emulator.cpu.register_mut(1) = 0x12;
+        assert_eq!(MEMORY_SIZE as u16, *emulator.cpu.pc());
         emulator.tick();
-        assert_eq!(ptr_start + 12, *emulator.cpu.pc());
+        assert_eq!(CHIP8_START as u16 + 2, *emulator.cpu.pc());
+        assert_eq!(None, emulator.halt_reason());
+    }
 
-        // Values stored in registers 0 and 1 are not equal
-        emulator.memory.write_u16(ptr_start + 12, 0x9010);
-        *emulator.cpu.register_mut(0) = 0x11;
-        emulator.tick();
-        assert_eq!(ptr_start + 16, *emulator.cpu.pc());
+    #[test]
+    fn running_past_memory_faults() {
+        let rom = [0x60, 0x01];
+        let mut emulator = Emulator::new().with_rom(&rom).unwrap();
+
+        for _ in 0..MEMORY_SIZE {
+            emulator.tick();
+        }
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::PcOutOfRange {
+                pc: MEMORY_SIZE as u16
+            })),
+            emulator.halt_reason()
+        );
+        assert_eq!(1, *emulator.cpu.register(0));
     }
 
     #[test]
-    fn can_load() {
+    fn can_start_at_eti660_address() {
         let mut emulator = Emulator::new();
-        let ptr = CHIP8_START as u16;
-        emulator.memory.write_u16(ptr, 0x6012);
+        emulator.configuration.start_address = ETI660_START_ADDRESS;
+        emulator.load_rom(&[0x60, 0x12]).unwrap();
 
-        // Load 0x12 into register 0
-        assert_ne!(*emulator.cpu.register(0), 0x12);
-        emulator.tick();
-        assert_eq!(*emulator.cpu.register(0), 0x12);
-
-        // Copy the content of register 0 into register 5
-        emulator.memory.write_u16(ptr + 2, 0x8500);
+        assert_eq!(ETI660_START_ADDRESS, *emulator.cpu.pc());
         emulator.tick();
-        assert_eq!(*emulator.cpu.register(5), 0x12);
+        assert_eq!(0x12, *emulator.cpu.register(0));
+        assert_eq!(ETI660_START_ADDRESS + 2, *emulator.cpu.pc());
+    }
 
-        // Load 0x0300 into register I
-        emulator.memory.write_u16(ptr + 4, 0xA300);
-        emulator.tick();
-        assert_eq!(*emulator.cpu.i(), 0x0300);
+    /// Assert the 8 pixel wide sprite with the given rows is drawn at x, y
+    fn assert_sprite_at(display: &DisplayBuffer, x: u8, y: u8, rows: &[u8]) {
+        for (row_offset, row) in rows.iter().enumerate() {
+            for x_offset in 0..8 {
+                let expected = row & (0x80 >> x_offset) != 0;
+                let (x, y) = (x + x_offset, y + row_offset as u8);
+                assert_eq!(expected, display.is_pixel_on(x, y), "{}, {}", x, y);
+            }
+        }
     }
 
     #[test]
-    fn can_add() {
+    fn can_load_custom_font() {
+        let mut font = FONT_SPRITES;
+        // Replace the A glyph with a checkerboard
+        font[50..55].copy_from_slice(&[0xA0, 0x50, 0xA0, 0x50, 0xA0]);
+
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0x0A, // Load 0xA into v0
+            0xF0, 0x29, // Load address of glyph in v0 into I
+            0x61, 0x00, // Load 0 into v1
+            0xD1, 0x15, // Draw the glyph at v1, v1
+        ];
         let mut emulator = Emulator::new();
-        let ptr = CHIP8_START as u16;
-        emulator.memory.write_u16(ptr, 0x7112);
-        *emulator.cpu.register_mut(1) = 0x05;
+        emulator.configuration.font_base = 0x000;
+        emulator.load_font(&font);
+        emulator.load_rom(&rom).unwrap();
 
-        // Add 0x12 to whatever is stored in register 1
-        emulator.tick();
-        assert_eq!(0x05 + 0x12, *emulator.cpu.register(1));
+        for _ in 0..4 {
+            emulator.tick();
+        }
+        assert_eq!(50, *emulator.cpu.i());
+        assert_sprite_at(&emulator.display, 0, 0, &[0xA0, 0x50, 0xA0, 0x50, 0xA0]);
+    }
 
-        // Store 0x03 in register 2 and add registers 1 and 2
-        *emulator.cpu.register_mut(2) = 0x03;
-        emulator.cpu.carry_on();
-        emulator.memory.write_u16(ptr + 2, 0x8124);
-        emulator.tick();
-        assert_eq!(0x05 + 0x12 + 0x03, *emulator.cpu.register(1));
-        assert_eq!(0, *emulator.cpu.carry());
+    #[test]
+    fn font_lookup_is_masked() {
+        #[rustfmt::skip]
+        let rom = [
+            0x60, 0xAB, // Load 0xAB into v0
+            0xF0, 0x29, // Load address of glyph in v0 into I
+            0x61, 0x00, // Load 0 into v1
+            0xD1, 0x15, // Draw the glyph at v1, v1
+        ];
+        let mut emulator = Emulator::new().with_rom(&rom).unwrap();
+        for _ in 0..4 {
+            emulator.tick();
+        }
 
-        // Add whatever is stored in register 1 to register I
-        emulator.memory.write_u16(ptr + 4, 0xF11E);
-        emulator.tick();
-        assert_eq!(0x05 + 0x12 + 0x03, *emulator.cpu.i());
+        assert_eq!(DEFAULT_FONT_BASE + 0xB * 5, *emulator.cpu.i());
+        assert_sprite_at(&emulator.display, 0, 0, &FONT_SPRITES[55..60]);
     }
 
     #[test]
-    fn can_bcd() {
+    fn can_load_rom_filling_memory() {
         let mut emulator = Emulator::new();
-        emulator.memory.write_u16(CHIP8_START as u16, 0xF033);
-        *emulator.cpu.register_mut(0) = 234;
-        *emulator.cpu.i_mut() = 0x0300;
-
-        emulator.tick();
-        assert_eq!(2, emulator.memory.read_u8(*emulator.cpu.i() + 0));
-        assert_eq!(3, emulator.memory.read_u8(*emulator.cpu.i() + 1));
-        assert_eq!(4, emulator.memory.read_u8(*emulator.cpu.i() + 2));
+        let rom = [0xAA; MEMORY_SIZE - CHIP8_START];
+        assert_eq!(Ok(()), emulator.load_rom(&rom));
+        assert_eq!(0xAA, emulator.memory.read_u8(MEMORY_SIZE as u16 - 1));
     }
 
     #[test]
-    #[cfg(feature = "std")]
-    fn can_run_timers() {
+    fn cannot_load_too_large_rom() {
         let mut emulator = Emulator::new();
-        *emulator.cpu.register_mut(0) = 60;
-        emulator.memory.write_u16(CHIP8_START as u16, 0xF015);
-
-        emulator.tick();
-        assert_eq!(60, *emulator.cpu.delay());
-
-        std::thread::sleep(core::time::Duration::from_millis(500));
-        emulator.tick();
-        assert_eq!(30, *emulator.cpu.delay());
+        let checksum = emulator.memory.checksum();
+        let rom = [0xAA; MEMORY_SIZE - CHIP8_START + 1];
+        assert_eq!(
+            Err(RomError::TooLarge {
+                len: 3585,
+                max: 3584
+            }),
+            emulator.load_rom(&rom)
+        );
+        assert_eq!(checksum, emulator.memory.checksum());
     }
 
     #[test]
-    fn can_run_subroutines() {
+    fn cannot_load_empty_rom() {
         let mut emulator = Emulator::new();
-        let subroutine_address = 0x0300;
-        emulator.memory.write_u16(CHIP8_START as u16, 0x2300);
-        emulator.memory.write_u16(subroutine_address, 0x00EE);
-
-        assert_eq!(CHIP8_START as u16, *emulator.cpu.pc());
-        emulator.tick();
-        assert_eq!(subroutine_address, *emulator.cpu.pc());
-        emulator.tick();
-        assert_eq!(CHIP8_START as u16 + 2, *emulator.cpu.pc());
+        let checksum = emulator.memory.checksum();
+        assert_eq!(Err(RomError::Empty), emulator.load_rom(&[]));
+        assert_eq!(checksum, emulator.memory.checksum());
     }
 
     #[test]
-    fn buffers_cannot_overflow() {
-        let mut emulator = Emulator::new();
-        *emulator.cpu.pc_mut() = 0x0FFF;
+    #[cfg(feature = "alloc")]
+    fn can_snapshot_and_restore_memory() {
+        #[rustfmt::skip]
+        let rom = [
+            0xA3, 0x00, // Load 0x300 into I
+            0x60, 0x42, // Load 0x42 into v0
+            0xF0, 0x55, // Dump v0 at I
+        ];
+        let mut emulator = Emulator::new().with_rom(&rom).unwrap();
+        let snapshot = emulator.snapshot_memory(0x300..0x304);
+        assert_eq!(0x300..0x304, snapshot.range());
 
-        emulator.tick();
-        assert_eq!(CHIP8_START as u16 + 2, *emulator.cpu.pc());
-        *emulator.cpu.pc_mut() = 0x0FFE;
+        for _ in 0..3 {
+            emulator.tick();
+        }
+        assert_eq!(0x42, emulator.memory.read_u8(0x300));
 
-        emulator.tick();
-        assert_eq!(CHIP8_START as u16 + 2, *emulator.cpu.pc());
+        emulator.restore_memory(&snapshot);
+        let mut buffer = [0xFF; 4];
+        assert_eq!(4, emulator.snapshot_memory_into(0x300..0x304, &mut buffer));
+        assert_eq!([0; 4], buffer);
     }
 
     #[test]
-    #[cfg(feature = "std")]
-    fn passes_bc_test_rom() {
-        let rom = include_bytes!("../roms/BC_test.ch8");
-        let mut emulator = Emulator::new().with_rom(rom);
-        // emulator.configuration.shift = ShiftStyle::CopyThenShift;
+    fn same_rom_has_same_fingerprint() {
+        let rom = include_bytes!("../roms/IBM_Logo.ch8");
+        let mut emulator_a = Emulator::new().with_rom(rom).unwrap();
+        let mut emulator_b = Emulator::new().with_rom(rom).unwrap();
 
-        for _ in 0..400 {
-            emulator.tick();
+        for _ in 0..21 {
+            emulator_a.tick();
+            emulator_b.tick();
         }
+        assert_eq!(
+            emulator_a.state_fingerprint(),
+            emulator_b.state_fingerprint()
+        );
+    }
 
-        println!("{}", emulator.display);
+    #[test]
+    fn fingerprint_diverges_on_key_press() {
+        #[rustfmt::skip]
+        let rom = [
+            0xE0, 0xA1, // Skip next instruction if key in v0 is not pressed
+            0x61, 0x01, // Load 1 into v1
+            0x12, 0x00, // Jump back to start
+        ];
+        let mut emulator_a = Emulator::new().with_rom(&rom).unwrap();
+        let mut emulator_b = Emulator::new().with_rom(&rom).unwrap();
         assert_eq!(
-            "◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
+            emulator_a.state_fingerprint(),
+            emulator_b.state_fingerprint()
+        );
+
+        emulator_a.press_key(0);
+        emulator_b.press_key(1);
+        emulator_a.tick();
+        emulator_b.tick();
+        assert_ne!(
+            emulator_a.state_fingerprint(),
+            emulator_b.state_fingerprint()
+        );
+    }
+
+    /// The screen BC_test shows once all of its tests passed
+    #[cfg(feature = "std")]
+    const BC_TEST_PASSED: &str = "◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
 ◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
 ◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
 ◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
@@ -744,25 +5826,52 @@ mod test {
 ◻◻◼◻◼◻◻◻◻◼◻◻◻◻◻◻◻◼◻◼◻◻◼◻◻◻◻◻◻◼◻◻◼◻◻◻◻◼◻◻◻◻◼◻◼◻◻◼◻◼◻◻◼◻◻◻◻◻◼◻◻◻◻◻
 ◻◻◼◼◻◻◻◻◻◼◻◻◻◻◻◻◻◼◼◻◻◻◻◼◼◻◻◼◼◻◻◻◻◼◼◻◻◼◼◼◻◻◻◼◻◻◻◻◼◼◻◻◻◼◼◻◻◻◼◻◼◻◻◻
 ◻◻◻◻◻◻◻◼◼◼◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
-",
-            format!("{}", emulator.display),
-        )
-    }
+";
 
-    #[cfg(feature = "std")]
     #[test]
-    // #[ignore]
-    fn passes_opcode_test_rom() {
-        let rom = include_bytes!("../roms/test_opcode.ch8");
-        let mut emulator = Emulator::new().with_rom(rom);
+    #[cfg(feature = "std")]
+    fn passes_bc_test_rom() {
+        let rom = include_bytes!("../roms/BC_test.ch8");
+        let mut emulator = Emulator::new().with_rom(rom).unwrap();
+        // emulator.configuration.quirks.shift = ShiftStyle::CopyThenShift;
 
         for _ in 0..400 {
             emulator.tick();
         }
 
         println!("{}", emulator.display);
-        assert_eq!(
-            "◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
+        assert_eq!(BC_TEST_PASSED, format!("{}", emulator.display))
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn presets_pass_bc_test_rom() {
+        let rom = include_bytes!("../roms/BC_test.ch8");
+        // BC_test expects the modern dump and load, which leaves I unchanged
+        for (name, configuration) in [
+            (
+                "super_chip_legacy",
+                EmulatorConfiguration::super_chip_legacy(),
+            ),
+            (
+                "super_chip_modern",
+                EmulatorConfiguration::super_chip_modern(),
+            ),
+        ] {
+            let mut emulator = Emulator::new();
+            emulator.configuration = configuration;
+            emulator.load_rom(rom).unwrap();
+            for _ in 0..400 {
+                emulator.tick();
+            }
+            assert_eq!(BC_TEST_PASSED, format!("{}", emulator.display), "{}", name);
+        }
+    }
+
+    /// The screen test_opcode shows once all of its tests passed
+    #[cfg(feature = "std")]
+    const OPCODE_TEST_PASSED: &str =
+        "◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
 ◻◼◼◼◻◼◻◼◻◻◼◼◼◻◼◻◼◻◻◻◻◻◻◼◼◼◻◼◼◼◻◻◼◼◼◻◼◻◼◻◻◻◻◻◼◼◼◻◻◼◼◻◼◼◼◻◼◻◼◻◻◻◻◻
 ◻◻◼◼◻◻◼◻◻◻◼◻◼◻◼◼◻◻◻◻◻◻◻◼◻◼◻◼◼◻◻◻◼◻◼◻◼◼◻◻◻◻◻◻◼◼◼◻◻◼◻◻◼◻◼◻◼◼◻◻◻◻◻◻
 ◻◻◻◼◻◼◻◼◻◻◼◻◼◻◼◻◼◻◻◻◻◻◻◼◻◼◻◼◻◻◻◻◼◻◼◻◼◻◼◻◻◻◻◻◼◻◼◻◻◻◼◻◼◻◼◻◼◻◼◻◻◻◻◻
@@ -794,24 +5903,26 @@ mod test {
 ◻◼◻◼◻◼◻◼◻◻◼◼◼◻◼◻◼◻◻◻◻◻◻◼◼◼◻◻◻◼◻◻◼◼◼◻◼◻◼◻◻◻◻◻◼◼◼◻◼◻◼◻◼◼◼◻◼◻◼◻◻◻◻◻
 ◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
 ◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
-",
-            format!("{}", emulator.display)
-        );
-    }
+";
 
     #[cfg(feature = "std")]
     #[test]
-    fn can_draw_ibm_logo() {
-        let rom = include_bytes!("../roms/IBM_Logo.ch8");
-        let mut emulator = Emulator::new().with_rom(rom);
+    // #[ignore]
+    fn passes_opcode_test_rom() {
+        let rom = include_bytes!("../roms/test_opcode.ch8");
+        let mut emulator = Emulator::new().with_rom(rom).unwrap();
 
-        for _ in 0..21 {
+        for _ in 0..400 {
             emulator.tick();
         }
 
         println!("{}", emulator.display);
-        assert_eq!(
-            "◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
+        assert_eq!(OPCODE_TEST_PASSED, format!("{}", emulator.display));
+    }
+
+    /// The IBM logo, fully drawn
+    #[cfg(feature = "std")]
+    const IBM_LOGO_SCREEN: &str = "◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
 ◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
 ◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
 ◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
@@ -843,8 +5954,233 @@ mod test {
 ◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
 ◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
 ◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻◻
-",
-            format!("{}", emulator.display)
+";
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn can_draw_ibm_logo() {
+        let rom = include_bytes!("../roms/IBM_Logo.ch8");
+        let mut emulator = Emulator::new().with_rom(rom).unwrap();
+
+        for _ in 0..21 {
+            emulator.tick();
+        }
+
+        println!("{}", emulator.display);
+        assert_eq!(IBM_LOGO_SCREEN, format!("{}", emulator.display));
+    }
+
+    /// Draws a large 5 in hires, scrolls it, saves and restores the registers
+    /// through the flags, draws a second 5 and a 16x16 square, then exits.
+    /// The Timendus test roms are not vendored in roms/, as their license
+    /// could not be checked against the MIT license of this crate, so this
+    /// hand-assembled rom covers the instructions and roms/knight.ch8 is
+    /// the real SUPER-CHIP rom.
+    #[rustfmt::skip]
+    const SUPER_CHIP_ROM: [u8; 72] = [
+        0x00, 0xFF, // 0x200: HIGH
+        0x60, 0x05, // 0x202: LD V0, 0x05
+        0xF0, 0x30, // 0x204: LD HF, V0
+        0x61, 0x0A, // 0x206: LD V1, 0x0A
+        0x62, 0x0A, // 0x208: LD V2, 0x0A
+        0xD1, 0x2A, // 0x20A: DRW V1, V2, 10
+        0x00, 0xC4, // 0x20C: SCD 4
+        0x00, 0xFB, // 0x20E: SCR
+        0xF2, 0x75, // 0x210: LD R, V2
+        0x60, 0x00, // 0x212: LD V0, 0x00
+        0x61, 0x00, // 0x214: LD V1, 0x00
+        0x62, 0x00, // 0x216: LD V2, 0x00
+        0xF2, 0x85, // 0x218: LD V2, R
+        0x71, 0x20, // 0x21A: ADD V1, 0x20
+        0xF0, 0x30, // 0x21C: LD HF, V0
+        0xD1, 0x2A, // 0x21E: DRW V1, V2, 10
+        0x64, 0x50, // 0x220: LD V4, 0x50
+        0xA2, 0x28, // 0x222: LD I, 0x228
+        0xD4, 0x40, // 0x224: DRW V4, V4, 0
+        0x00, 0xFD, // 0x226: EXIT
+        0xFF, 0xFF, // 0x228: 16x16 square
+        0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01,
+        0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01,
+        0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01,
+        0x80, 0x01, 0x80, 0x01,
+        0xFF, 0xFF,
+    ];
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn super_chip_rom_runs_with_preset() {
+        for configuration in [
+            EmulatorConfiguration::super_chip(),
+            EmulatorConfiguration::super_chip_legacy(),
+        ] {
+            let mut emulator = Emulator::new().with_configuration(configuration).unwrap();
+            emulator.load_rom(&SUPER_CHIP_ROM).unwrap();
+            for _ in 0..30 {
+                emulator.tick();
+            }
+            assert_eq!(Some(&HaltReason::Exited), emulator.halt_reason());
+            assert_eq!(0x93D3_29C8_854C_1236, emulator.display.checksum());
+        }
+    }
+
+    /// Run Knight by Simon Pacis from roms/ up to its title screen,
+    /// which waits for key 0xD, and return the display checksum
+    #[cfg(feature = "schip")]
+    fn knight_title_checksum(configuration: EmulatorConfiguration) -> u64 {
+        let mut emulator = Emulator::with_clock(ManualClock::new())
+            .with_configuration(configuration)
+            .unwrap();
+        emulator
+            .load_rom(include_bytes!("../roms/knight.ch8"))
+            .unwrap();
+        for _ in 0..500 {
+            emulator.tick();
+        }
+        assert_eq!(None, emulator.halt_reason());
+        assert_eq!(DisplayMode::HighRes, emulator.display_mode());
+        emulator.display.checksum()
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn knight_runs_with_super_chip_preset() {
+        for configuration in [
+            EmulatorConfiguration::super_chip(),
+            EmulatorConfiguration::super_chip_legacy(),
+        ] {
+            assert_eq!(0x8E27_AEE2_559D_82A0, knight_title_checksum(configuration));
+        }
+    }
+
+    #[test]
+    fn super_chip_rom_is_invalid_on_chip8() {
+        let mut emulator = Emulator::new()
+            .with_configuration(EmulatorConfiguration {
+                invalid_opcode: InvalidOpcodeStyle::Halt,
+                ..EmulatorConfiguration::cosmac_vip()
+            })
+            .unwrap();
+        emulator.load_rom(&SUPER_CHIP_ROM).unwrap();
+        emulator.tick();
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::InvalidOpcode {
+                pc: 0x200,
+                opcode: 0x00FF
+            })),
+            emulator.halt_reason()
+        );
+    }
+
+    /// Draws a box on both planes, scrolls the first plane up, round trips
+    /// two registers through 5XY2 and 5XY3, then exits.
+    /// This hand-assembled rom is the XO-CHIP acceptance test. Knight in
+    /// roms/ is listed for XO-CHIP as well, but uses none of its instructions.
+    #[cfg(feature = "xochip")]
+    #[rustfmt::skip]
+    const XO_CHIP_ROM: [u8; 48] = [
+        0xF3, 0x01, // 0x200: PLANE 3
+        0xF0, 0x00, // 0x202: LD I, LONG
+        0x02, 0x28, // 0x204: 0x0228
+        0xF0, 0x02, // 0x206: AUDIO
+        0x60, 0x08, // 0x208: LD V0, 0x08
+        0x61, 0x04, // 0x20A: LD V1, 0x04
+        0xD0, 0x14, // 0x20C: DRW V0, V1, 4
+        0xF1, 0x01, // 0x20E: PLANE 1
+        0x00, 0xD1, // 0x210: SCU 1
+        0x62, 0xAA, // 0x212: LD V2, 0xAA
+        0x63, 0x55, // 0x214: LD V3, 0x55
+        0xF0, 0x00, // 0x216: LD I, LONG
+        0x03, 0x00, // 0x218: 0x0300
+        0x52, 0x32, // 0x21A: SAVE V2 - V3
+        0x62, 0x00, // 0x21C: LD V2, 0x00
+        0x63, 0x00, // 0x21E: LD V3, 0x00
+        0x52, 0x33, // 0x220: LOAD V2 - V3
+        0x33, 0x55, // 0x222: SE V3, 0x55
+        0x12, 0x24, // 0x224: JP 0x224
+        0x00, 0xFD, // 0x226: EXIT
+        0xF0, 0x90, // 0x228: box on the first plane
+        0x90, 0xF0,
+        0xFF, 0x81, // 0x22C: box on the second plane
+        0x81, 0xFF,
+    ];
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn xo_chip_rom_runs_with_preset() {
+        for configuration in [EmulatorConfiguration::xo_chip(), "xo-chip".parse().unwrap()] {
+            let mut emulator = Emulator::new().with_configuration(configuration).unwrap();
+            emulator.load_rom(&XO_CHIP_ROM).unwrap();
+            for _ in 0..30 {
+                emulator.tick();
+            }
+            assert_eq!(Some(&HaltReason::Exited), emulator.halt_reason());
+            assert_eq!(0xAA, *emulator.cpu.register(2));
+            // The first plane moved up a row, the second stayed put
+            let expected = [
+                [1, 1, 1, 1, 0, 0, 0, 0],
+                [3, 2, 2, 3, 2, 2, 2, 2],
+                [3, 0, 0, 1, 0, 0, 0, 2],
+                [3, 1, 1, 1, 0, 0, 0, 2],
+                [2, 2, 2, 2, 2, 2, 2, 2],
+            ];
+            for (y, row) in (3..8).zip(expected) {
+                let colors: [u8; 8] =
+                    core::array::from_fn(|x| emulator.pixel_color(8 + x as u8, y));
+                assert_eq!(row, colors, "row {}", y);
+            }
+        }
+    }
+
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn knight_runs_with_xo_chip_preset() {
+        assert_eq!(
+            0x8E27_AEE2_559D_82A0,
+            knight_title_checksum(EmulatorConfiguration::xo_chip())
+        );
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn scroll_up_needs_xo_chip() {
+        let mut emulator = Emulator::new()
+            .with_configuration(EmulatorConfiguration {
+                invalid_opcode: InvalidOpcodeStyle::Halt,
+                ..EmulatorConfiguration::super_chip()
+            })
+            .unwrap();
+        emulator.load_rom(&[0x00, 0xD2]).unwrap();
+        emulator.tick();
+        assert_eq!(
+            Some(&HaltReason::Fault(Chip8Error::InvalidOpcode {
+                pc: 0x200,
+                opcode: 0x00D2
+            })),
+            emulator.halt_reason()
         );
     }
+
+    #[cfg(all(feature = "std", feature = "rom-db", feature = "schip"))]
+    #[test]
+    fn known_roms_pass_with_their_configuration() {
+        let roms: [(&[u8], usize, &str); 3] = [
+            (include_bytes!("../roms/BC_test.ch8"), 400, BC_TEST_PASSED),
+            (include_bytes!("../roms/IBM_Logo.ch8"), 21, IBM_LOGO_SCREEN),
+            (
+                include_bytes!("../roms/test_opcode.ch8"),
+                400,
+                OPCODE_TEST_PASSED,
+            ),
+        ];
+
+        for (rom, ticks, screen) in roms {
+            let mut emulator = Emulator::new();
+            emulator.configuration = EmulatorConfiguration::for_rom(rom).unwrap();
+            emulator.load_rom(rom).unwrap();
+            for _ in 0..ticks {
+                emulator.tick();
+            }
+            assert_eq!(screen, format!("{}", emulator.display));
+        }
+    }
 }
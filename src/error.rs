@@ -0,0 +1,328 @@
+/// Errors that can occur while loading a rom into the emulator
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomError {
+    /// The rom does not fit into the memory between the
+    /// configured start address and the end of memory
+    TooLarge { len: usize, max: usize },
+    /// The rom does not contain a single byte
+    Empty,
+}
+
+impl core::fmt::Display for RomError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RomError::TooLarge { len, max } => {
+                write!(
+                    f,
+                    "rom of {} bytes exceeds the {} available bytes",
+                    len, max
+                )
+            }
+            RomError::Empty => write!(f, "rom is empty"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RomError {}
+
+/// Errors raised while executing a rom. They stop the emulator,
+/// as the rom did something the emulator can not recover from.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Chip8Error {
+    /// A subroutine was called while the stack was already full.
+    /// Holds the address of the offending call.
+    StackOverflow { pc: u16 },
+    /// Returned from a subroutine while the stack was empty.
+    /// Holds the address of the offending return.
+    StackUnderflow { pc: u16 },
+    /// The program counter ran past the end of memory
+    PcOutOfRange { pc: u16 },
+    /// The word at pc could not be decoded into an instruction
+    InvalidOpcode { pc: u16, opcode: u16 },
+    /// The instruction at pc tried to access memory
+    /// past the end, starting at address
+    MemoryOutOfBounds { pc: u16, address: u16 },
+}
+
+impl core::fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Chip8Error::StackOverflow { pc } => {
+                write!(f, "stack overflow calling a subroutine at {:#05X}", pc)
+            }
+            Chip8Error::StackUnderflow { pc } => {
+                write!(
+                    f,
+                    "stack underflow returning from a subroutine at {:#05X}",
+                    pc
+                )
+            }
+            Chip8Error::PcOutOfRange { pc } => {
+                write!(
+                    f,
+                    "program counter ran past the end of memory at {:#05X}",
+                    pc
+                )
+            }
+            Chip8Error::InvalidOpcode { pc, opcode } => {
+                write!(f, "invalid opcode {:#06X} at {:#05X}", opcode, pc)
+            }
+            Chip8Error::MemoryOutOfBounds { pc, address } => {
+                write!(
+                    f,
+                    "memory access out of bounds from {:#05X} at {:#05X}",
+                    address, pc
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Chip8Error {}
+
+/// Errors in building an [`crate::config::EmulatorConfiguration`]
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The name does not match any of the presets
+    UnknownPreset,
+    /// The platform's extensions were left out by disabling their cargo feature
+    PlatformDisabled,
+    /// The memory is smaller than 4 KiB or larger than 64 KiB,
+    /// or than 4 KiB without the `xochip` feature
+    InvalidMemorySize,
+    /// The start address leaves no room for a rom
+    InvalidStartAddress,
+    /// The font sprites do not fit into memory at the font base
+    InvalidFontBase,
+    /// Loading a rom would overwrite the font sprites
+    FontOverlapsProgram,
+    /// Not even a single subroutine can be called
+    InvalidStackDepth,
+    /// A frame or timer step would never end, as it takes zero instructions
+    NoInstructions,
+    /// The buzzer frequency is not a positive number
+    InvalidBuzzer,
+    /// A configuration file names a key that does not exist
+    UnknownKey { line: usize },
+    /// A configuration file has a value of the wrong type or an unknown name
+    InvalidValue { line: usize },
+    /// A line of a configuration file could not be parsed
+    Syntax { line: usize },
+    /// A configuration file sets the same key twice in one table
+    DuplicateKey { line: usize },
+    /// A configuration file starts a table where only top level keys are allowed
+    UnsupportedTable { line: usize },
+    /// A configuration file could not be read
+    #[cfg(feature = "std")]
+    Io(std::io::ErrorKind),
+}
+
+impl core::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConfigError::UnknownPreset => {
+                write!(f, "unknown preset, expected one of ")?;
+                for (index, name) in crate::config::PRESET_NAMES.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", name)?;
+                }
+                Ok(())
+            }
+            ConfigError::PlatformDisabled => {
+                write!(f, "the platform is not enabled in the cargo features")
+            }
+            ConfigError::InvalidMemorySize if cfg!(feature = "xochip") => {
+                write!(f, "the memory size has to be between 4 KiB and 64 KiB")
+            }
+            ConfigError::InvalidMemorySize => write!(f, "the memory size has to be 4 KiB"),
+            ConfigError::InvalidStartAddress => write!(f, "the start address is out of memory"),
+            ConfigError::InvalidFontBase => write!(f, "the font does not fit into memory"),
+            ConfigError::FontOverlapsProgram => {
+                write!(
+                    f,
+                    "the font overlaps the program, starting at the start address"
+                )
+            }
+            ConfigError::InvalidStackDepth => write!(f, "the stack depth has to be at least 1"),
+            ConfigError::NoInstructions => {
+                write!(f, "frames and timer steps need at least 1 instruction")
+            }
+            ConfigError::InvalidBuzzer => write!(f, "the buzzer frequency has to be positive"),
+            ConfigError::UnknownKey { line } => write!(f, "unknown key on line {}", line),
+            ConfigError::InvalidValue { line } => write!(f, "invalid value on line {}", line),
+            ConfigError::Syntax { line } => write!(f, "could not parse line {}", line),
+            ConfigError::DuplicateKey { line } => write!(f, "duplicate key on line {}", line),
+            ConfigError::UnsupportedTable { line } => {
+                write!(f, "tables are not supported, found one on line {}", line)
+            }
+            #[cfg(feature = "std")]
+            ConfigError::Io(kind) => write!(f, "could not read the configuration: {}", kind),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConfigError {}
+
+/// A word that could not be decoded into an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError {
+    /// The address the word was loaded from
+    pub pc: u16,
+    pub opcode: u16,
+}
+
+impl core::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "could not decode {:#06X} at {:#05X}",
+            self.opcode, self.pc
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+impl From<DecodeError> for Chip8Error {
+    fn from(value: DecodeError) -> Self {
+        Chip8Error::InvalidOpcode {
+            pc: value.pc,
+            opcode: value.opcode,
+        }
+    }
+}
+
+/// A register index outside of V0..=VF
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidRegister(pub u8);
+
+impl core::fmt::Display for InvalidRegister {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid register index {:#04X}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidRegister {}
+
+/// An address outside of the emulator memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidAddress(pub u16);
+
+impl core::fmt::Display for InvalidAddress {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "address {:#05X} is outside of memory", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidAddress {}
+
+/// Errors opening the sound device for [`crate::audio::CpalBuzzer`]
+#[cfg(feature = "cpal")]
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum AudioError {
+    /// The host has no output device
+    NoDevice,
+    /// The device did not report an output configuration
+    DefaultConfig(cpal::DefaultStreamConfigError),
+    /// The device plays samples neither as f32 nor as i16
+    UnsupportedFormat(cpal::SampleFormat),
+    BuildStream(cpal::BuildStreamError),
+    PlayStream(cpal::PlayStreamError),
+}
+
+#[cfg(feature = "cpal")]
+impl core::fmt::Display for AudioError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AudioError::NoDevice => write!(f, "no audio output device"),
+            AudioError::DefaultConfig(err) => write!(f, "no output configuration: {}", err),
+            AudioError::UnsupportedFormat(format) => {
+                write!(f, "unsupported sample format {}", format)
+            }
+            AudioError::BuildStream(err) => write!(f, "could not open the output stream: {}", err),
+            AudioError::PlayStream(err) => write!(f, "could not play the output stream: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "cpal")]
+impl std::error::Error for AudioError {}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chip8_errors_contain_pc() {
+        let errors = [
+            Chip8Error::StackOverflow { pc: 0x2A4 },
+            Chip8Error::StackUnderflow { pc: 0x2A4 },
+            Chip8Error::PcOutOfRange { pc: 0x2A4 },
+            Chip8Error::InvalidOpcode {
+                pc: 0x2A4,
+                opcode: 0x8FF9,
+            },
+            Chip8Error::MemoryOutOfBounds {
+                pc: 0x2A4,
+                address: 0xFFE,
+            },
+        ];
+        for error in errors {
+            assert!(error.to_string().contains("0x2A4"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn decode_errors_convert_to_invalid_opcode() {
+        let error = DecodeError {
+            pc: 0x2A4,
+            opcode: 0x8FF9,
+        };
+        assert_eq!("could not decode 0x8FF9 at 0x2A4", error.to_string());
+        assert_eq!(
+            Chip8Error::InvalidOpcode {
+                pc: 0x2A4,
+                opcode: 0x8FF9
+            },
+            error.into()
+        );
+    }
+
+    #[test]
+    fn other_errors_can_be_formatted() {
+        assert_eq!(
+            "rom of 4000 bytes exceeds the 3584 available bytes",
+            RomError::TooLarge {
+                len: 4000,
+                max: 3584
+            }
+            .to_string()
+        );
+        assert_eq!("rom is empty", RomError::Empty.to_string());
+        assert_eq!(
+            "unknown preset, expected one of cosmac-vip, chip-48, schip-legacy, schip, xo-chip",
+            ConfigError::UnknownPreset.to_string()
+        );
+        assert_eq!(
+            "invalid register index 0x10",
+            InvalidRegister(0x10).to_string()
+        );
+        assert_eq!(
+            "address 0x1000 is outside of memory",
+            InvalidAddress(0x1000).to_string()
+        );
+    }
+}
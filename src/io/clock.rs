@@ -0,0 +1,93 @@
+/// A monotonic source of time, used to run the delay and sound timers.
+/// Implement this on top of a hardware timer to run the emulator on
+/// platforms without a default clock.
+pub trait Clock {
+    /// Milliseconds since some fixed point in time
+    fn now_millis(&self) -> u64;
+}
+
+/// The default clock on std targets, based on [`std::time::Instant`]
+#[cfg(feature = "std")]
+pub struct StdClock {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl StdClock {
+    pub fn new() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now_millis(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+}
+
+/// The default clock in the browser, based on `Date.now()`
+#[cfg(feature = "js")]
+#[derive(Default)]
+pub struct JsClock;
+
+#[cfg(feature = "js")]
+impl JsClock {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[cfg(feature = "js")]
+impl Clock for JsClock {
+    fn now_millis(&self) -> u64 {
+        js_sys::Date::now() as u64
+    }
+}
+
+/// A clock that only moves when advanced explicitly,
+/// which makes timer behavior deterministic e.g. in tests
+#[derive(Debug, Default, Clone)]
+pub struct ManualClock {
+    millis: u64,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the clock forward by the given amount of milliseconds
+    pub fn advance(&mut self, millis: u64) {
+        self.millis = self.millis.saturating_add(millis);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> u64 {
+        self.millis
+    }
+}
+
+/// The clock used by [`crate::emulator::Emulator::new`]
+#[cfg(feature = "std")]
+pub type DefaultClock = StdClock;
+
+/// The clock used by [`crate::emulator::Emulator::new`]
+#[cfg(all(not(feature = "std"), feature = "js"))]
+pub type DefaultClock = JsClock;
+
+/// The clock used by [`crate::emulator::Emulator::new`]. Bare metal targets
+/// have no clock to read, so the host feeds in time with
+/// [`crate::emulator::Emulator::advance_time_ms`].
+#[cfg(not(any(feature = "std", feature = "js")))]
+pub type DefaultClock = ManualClock;
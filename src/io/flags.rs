@@ -0,0 +1,73 @@
+/// The number of user flags XO-CHIP offers. SUPER-CHIP only uses the first 8.
+pub const MAX_FLAGS: usize = 16;
+
+/// Persists the RPL user flags FX75 saves and FX85 restores, which some
+/// games use to keep e.g. a high score across resets. Without a store
+/// the flags only live as long as the emulator.
+pub trait FlagStore {
+    /// Read the flags saved before. Flags that were never saved stay zero.
+    fn load(&mut self, flags: &mut [u8; MAX_FLAGS]);
+
+    /// Persist the flags, called after every FX75
+    fn save(&mut self, flags: &[u8; MAX_FLAGS]);
+}
+
+/// Keeps the flags in a file, e.g. next to the rom
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFlagStore {
+    path: std::path::PathBuf,
+}
+
+#[cfg(feature = "std")]
+impl FileFlagStore {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+/// A missing file holds no flags yet. Other errors are logged,
+/// as the rom can keep running without its flags.
+#[cfg(feature = "std")]
+impl FlagStore for FileFlagStore {
+    fn load(&mut self, flags: &mut [u8; MAX_FLAGS]) {
+        match std::fs::read(&self.path) {
+            Ok(bytes) => {
+                let len = bytes.len().min(MAX_FLAGS);
+                flags[..len].copy_from_slice(&bytes[..len]);
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+            Err(error) => log_warn!("Could not read flags from {:?}: {}", self.path, error),
+        }
+    }
+
+    fn save(&mut self, flags: &[u8; MAX_FLAGS]) {
+        if let Err(error) = std::fs::write(&self.path, flags) {
+            log_warn!("Could not write flags to {:?}: {}", self.path, error);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn file_store_round_trips() {
+        let path = std::env::temp_dir().join("chip8_flag_store_test.bin");
+        let _ = std::fs::remove_file(&path);
+        let mut store = FileFlagStore::new(&path);
+
+        let mut flags = [7; MAX_FLAGS];
+        store.load(&mut flags);
+        assert_eq!([7; MAX_FLAGS], flags);
+
+        flags[0] = 42;
+        flags[15] = 1;
+        store.save(&flags);
+        let mut loaded = [0; MAX_FLAGS];
+        FileFlagStore::new(&path).load(&mut loaded);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(flags, loaded);
+    }
+}
@@ -1,21 +1,227 @@
+/// The number of key events kept for FX0A. Once the queue is full,
+/// the oldest event is dropped to make room for a new one.
+pub(crate) const KEY_EVENT_CAPACITY: usize = 16;
+
+/// A key going down or up. Only the lower nibble of the key is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyEvent {
+    Down(u8),
+    Up(u8),
+}
+
+/// A source of key states the emulator reads when it needs them, e.g.
+/// a polled matrix keypad. EX9E, EXA1 and FX0A all read through it.
+pub trait KeySource {
+    /// Whether the given key, in 0x0..=0xF, is held down
+    fn is_pressed(&self, key: u8) -> bool;
+
+    /// Called once at the start of every tick, to refresh the key states
+    fn poll(&mut self) {}
+
+    fn any_pressed(&self) -> bool {
+        self.first_pressed().is_some()
+    }
+
+    /// The lowest pressed key. Whenever several keys are held,
+    /// the one with the lowest value wins, e.g. for FX0A.
+    fn first_pressed(&self) -> Option<u8> {
+        (0..16).find(|key| self.is_pressed(*key))
+    }
+}
+
+/// The 16 keys of the hex keypad. Keys are addressed by
+/// their hex value, only the lower nibble of a key is used,
+/// so e.g. 0x13 refers to key 0x3.
+/// Next to the pressed state, every change is recorded in a small
+/// queue of [`KeyEvent`], so each press can be consumed exactly once.
+#[derive(Clone)]
 pub(crate) struct Keyboard {
     keys: [bool; 16],
+    events: [KeyEvent; KEY_EVENT_CAPACITY],
+    /// The index of the oldest event
+    head: usize,
+    len: usize,
 }
 
 impl Keyboard {
     pub const fn new() -> Self {
-        Self { keys: [false; 16] }
+        Self {
+            keys: [false; 16],
+            events: [KeyEvent::Up(0); KEY_EVENT_CAPACITY],
+            head: 0,
+            len: 0,
+        }
     }
 
     pub fn is_pressed(&self, key: u8) -> bool {
-        self.keys[key as usize]
+        self.keys[(key & 0x0F) as usize]
+    }
+
+    /// All pressed keys in ascending order
+    pub fn pressed_keys(&self) -> impl Iterator<Item = u8> + '_ {
+        self.keys
+            .iter()
+            .enumerate()
+            .filter(|(_, pressed)| **pressed)
+            .map(|(key, _)| key as u8)
     }
 
     pub fn press(&mut self, key: u8) {
-        self.keys[key as usize] = true;
+        self.push_event(KeyEvent::Down(key));
     }
 
     pub fn release(&mut self, key: u8) {
-        self.keys[key as usize] = false;
+        self.push_event(KeyEvent::Up(key));
+    }
+
+    /// Update the key state and record the event in the queue
+    pub fn push_event(&mut self, event: KeyEvent) {
+        let event = match event {
+            KeyEvent::Down(key) => {
+                self.keys[(key & 0x0F) as usize] = true;
+                KeyEvent::Down(key & 0x0F)
+            }
+            KeyEvent::Up(key) => {
+                self.keys[(key & 0x0F) as usize] = false;
+                KeyEvent::Up(key & 0x0F)
+            }
+        };
+        self.queue_event(event);
+    }
+
+    /// Record the event in the queue, keeping the key state
+    pub fn queue_event(&mut self, event: KeyEvent) {
+        if self.len == KEY_EVENT_CAPACITY {
+            self.head = (self.head + 1) % KEY_EVENT_CAPACITY;
+            self.len -= 1;
+        }
+        self.events[(self.head + self.len) % KEY_EVENT_CAPACITY] = event;
+        self.len += 1;
+    }
+
+    /// Take the oldest event out of the queue
+    pub fn pop_event(&mut self) -> Option<KeyEvent> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.events[self.head];
+        self.head = (self.head + 1) % KEY_EVENT_CAPACITY;
+        self.len -= 1;
+        Some(event)
+    }
+
+    /// Forget the queued events, keeping the key states
+    pub fn clear_events(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// The state of all keys, with bit n set if key n is pressed
+    pub fn mask(&self) -> u16 {
+        self.keys
+            .iter()
+            .enumerate()
+            .fold(0, |mask, (key, pressed)| mask | (*pressed as u16) << key)
+    }
+
+    /// Set the state of all keys, recording an event for every key that changed
+    pub fn set_mask(&mut self, mask: u16) {
+        let changed = mask ^ self.mask();
+        for key in 0..16 {
+            if changed >> key & 1 == 0 {
+                continue;
+            }
+            if mask >> key & 1 == 1 {
+                self.press(key);
+            } else {
+                self.release(key);
+            }
+        }
+    }
+}
+
+impl KeySource for Keyboard {
+    fn is_pressed(&self, key: u8) -> bool {
+        Keyboard::is_pressed(self, key)
+    }
+
+    fn any_pressed(&self) -> bool {
+        self.keys.iter().any(|pressed| *pressed)
+    }
+
+    fn first_pressed(&self) -> Option<u8> {
+        self.pressed_keys().next()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keys_are_masked() {
+        let mut keyboard = Keyboard::new();
+        keyboard.press(0x1F);
+        assert!(keyboard.is_pressed(0xF));
+        assert!(keyboard.is_pressed(0xFF));
+        keyboard.release(0xF);
+        assert!(!keyboard.is_pressed(0x1F));
+    }
+
+    #[test]
+    fn mask_round_trips() {
+        let mut keyboard = Keyboard::new();
+        keyboard.set_mask(0b1001_0000_0000_1000);
+        assert!(keyboard.is_pressed(0x3));
+        assert!(keyboard.is_pressed(0xC));
+        assert!(keyboard.is_pressed(0xF));
+        assert!(!keyboard.is_pressed(0x0));
+        keyboard.press(0x0);
+        assert_eq!(0b1001_0000_0000_1001, keyboard.mask());
+    }
+
+    #[test]
+    fn can_query_pressed_keys() {
+        let mut keyboard = Keyboard::new();
+        assert!(!keyboard.any_pressed());
+        assert_eq!(None, keyboard.first_pressed());
+        assert_eq!(0, keyboard.pressed_keys().count());
+
+        keyboard.press(0xB);
+        assert!(keyboard.any_pressed());
+        assert_eq!(Some(0xB), keyboard.first_pressed());
+
+        keyboard.press(0xE);
+        keyboard.press(0x2);
+        assert_eq!(Some(0x2), keyboard.first_pressed());
+        assert_eq!(
+            vec![0x2, 0xB, 0xE],
+            keyboard.pressed_keys().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn events_are_queued_in_order() {
+        let mut keyboard = Keyboard::new();
+        keyboard.press(0x13);
+        keyboard.set_mask(0b0001);
+        assert_eq!(Some(KeyEvent::Down(0x3)), keyboard.pop_event());
+        assert_eq!(Some(KeyEvent::Down(0x0)), keyboard.pop_event());
+        assert_eq!(Some(KeyEvent::Up(0x3)), keyboard.pop_event());
+        assert_eq!(None, keyboard.pop_event());
+    }
+
+    #[test]
+    fn full_queue_drops_oldest_event() {
+        let mut keyboard = Keyboard::new();
+        for key in 0..KEY_EVENT_CAPACITY as u8 + 2 {
+            keyboard.push_event(KeyEvent::Down(key));
+        }
+        assert_eq!(Some(KeyEvent::Down(2)), keyboard.pop_event());
+        for _ in 1..KEY_EVENT_CAPACITY {
+            assert!(keyboard.pop_event().is_some());
+        }
+        assert_eq!(None, keyboard.pop_event());
     }
 }
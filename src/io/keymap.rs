@@ -0,0 +1,102 @@
+/// Maps characters typed on a host keyboard to the 16 keys of the hex keypad.
+/// Characters are matched case-insensitively.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    /// The character for each key, indexed by the key value
+    chars: [char; 16],
+}
+
+impl KeyMap {
+    /// Create a map from the characters for keys 0x0..=0xF
+    pub fn from_chars(chars: [char; 16]) -> Self {
+        let mut map = Self { chars: [' '; 16] };
+        for (key, c) in chars.into_iter().enumerate() {
+            map = map.bind(key as u8, c);
+        }
+        map
+    }
+
+    /// The usual layout, mapping the left side of a QWERTY keyboard
+    /// onto the keypad:
+    ///
+    /// ```text
+    /// 1 2 3 4      1 2 3 C
+    /// Q W E R  ->  4 5 6 D
+    /// A S D F      7 8 9 E
+    /// Z X C V      A 0 B F
+    /// ```
+    pub fn qwerty() -> Self {
+        Self::from_chars([
+            'x', '1', '2', '3', 'q', 'w', 'e', 'a', 's', 'd', 'z', 'c', '4', 'r', 'f', 'v',
+        ])
+    }
+
+    /// The same physical layout as [`KeyMap::qwerty`] on an AZERTY keyboard
+    pub fn azerty() -> Self {
+        Self::from_chars([
+            'x', '1', '2', '3', 'a', 'z', 'e', 'q', 's', 'd', 'w', 'c', '4', 'r', 'f', 'v',
+        ])
+    }
+
+    /// Bind the character to the given key, replacing its previous character.
+    /// Only the lower nibble of the key is used.
+    pub fn bind(mut self, key: u8, c: char) -> Self {
+        self.chars[(key & 0x0F) as usize] = c.to_ascii_lowercase();
+        self
+    }
+
+    /// The key the character is bound to
+    pub fn map_char(&self, c: char) -> Option<u8> {
+        let c = c.to_ascii_lowercase();
+        self.chars
+            .iter()
+            .position(|bound| *bound == c)
+            .map(|key| key as u8)
+    }
+
+    /// The character bound to the given key.
+    /// Only the lower nibble of the key is used.
+    pub fn label(&self, key: u8) -> char {
+        self.chars[(key & 0x0F) as usize]
+    }
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::qwerty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn qwerty_is_canonical() {
+        let map = KeyMap::qwerty();
+        assert_eq!(Some(0xF), map.map_char('v'));
+        assert_eq!(Some(0xF), map.map_char('V'));
+        assert_eq!(Some(0x0), map.map_char('x'));
+        assert_eq!(Some(0xC), map.map_char('4'));
+        assert_eq!(Some(0x5), map.map_char('w'));
+        assert_eq!(None, map.map_char('p'));
+        assert_eq!(Some(0x5), KeyMap::azerty().map_char('z'));
+    }
+
+    #[test]
+    fn labels_round_trip() {
+        for map in [KeyMap::qwerty(), KeyMap::azerty()] {
+            for key in 0..16 {
+                assert_eq!(Some(key), map.map_char(map.label(key)));
+            }
+        }
+    }
+
+    #[test]
+    fn can_bind_custom_keys() {
+        let map = KeyMap::qwerty().bind(0x5, 'K');
+        assert_eq!(Some(0x5), map.map_char('k'));
+        assert_eq!(None, map.map_char('w'));
+        assert_eq!('k', map.label(0x15));
+    }
+}
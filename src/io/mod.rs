@@ -1,2 +1,7 @@
+pub(crate) mod clock;
+pub(crate) mod flags;
 pub(crate) mod keyboard;
+pub(crate) mod keymap;
+#[cfg(feature = "alloc")]
+pub(crate) mod recording;
 pub(crate) mod timer;
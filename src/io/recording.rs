@@ -0,0 +1,144 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use super::keyboard::KeyEvent;
+
+/// A key event together with the tick it happened before,
+/// counted from the start of the recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct InputEntry {
+    pub tick: u64,
+    pub event: KeyEvent,
+}
+
+/// The key input of a session, recorded with
+/// [`crate::emulator::Emulator::start_input_recording`]
+/// and played back with [`crate::emulator::Emulator::replay_input`].
+/// The entries are kept sorted by their tick.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(from = "Vec<InputEntry>", into = "Vec<InputEntry>")
+)]
+pub struct InputRecording {
+    entries: Vec<InputEntry>,
+}
+
+/// Sorts the entries by their tick, keeping the order of events within a tick
+impl From<Vec<InputEntry>> for InputRecording {
+    fn from(mut entries: Vec<InputEntry>) -> Self {
+        entries.sort_by_key(|entry| entry.tick);
+        Self { entries }
+    }
+}
+
+impl From<InputRecording> for Vec<InputEntry> {
+    fn from(recording: InputRecording) -> Self {
+        recording.entries
+    }
+}
+
+impl InputRecording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All recorded events, oldest first
+    pub fn entries(&self) -> &[InputEntry] {
+        &self.entries
+    }
+
+    /// Add an entry after all entries of the same or earlier ticks
+    pub fn push(&mut self, entry: InputEntry) {
+        let index = self
+            .entries
+            .iter()
+            .rposition(|other| other.tick <= entry.tick)
+            .map_or(0, |index| index + 1);
+        self.entries.insert(index, entry);
+    }
+
+    pub(crate) fn cursor(&self) -> ReplayCursor<'_> {
+        ReplayCursor {
+            entries: &self.entries,
+        }
+    }
+}
+
+/// Walks through the entries of a recording once,
+/// handing out the events due at each tick in turn
+pub(crate) struct ReplayCursor<'a> {
+    entries: &'a [InputEntry],
+}
+
+impl<'a> ReplayCursor<'a> {
+    /// The events to apply before the given tick. Ticks have to be
+    /// asked for in ascending order, earlier entries are used up.
+    pub(crate) fn events_until(&mut self, tick: u64) -> impl Iterator<Item = KeyEvent> + 'a {
+        let due = self
+            .entries
+            .iter()
+            .take_while(|entry| entry.tick <= tick)
+            .count();
+        let (due, rest) = self.entries.split_at(due);
+        self.entries = rest;
+        due.iter().map(|entry| entry.event)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(tick: u64, event: KeyEvent) -> InputEntry {
+        InputEntry { tick, event }
+    }
+
+    #[test]
+    fn entries_stay_sorted_by_tick() {
+        let mut recording = InputRecording::new();
+        recording.push(entry(5, KeyEvent::Down(1)));
+        recording.push(entry(2, KeyEvent::Down(2)));
+        recording.push(entry(5, KeyEvent::Up(1)));
+        assert_eq!(
+            &[
+                entry(2, KeyEvent::Down(2)),
+                entry(5, KeyEvent::Down(1)),
+                entry(5, KeyEvent::Up(1)),
+            ],
+            recording.entries()
+        );
+
+        let mut cursor = recording.cursor();
+        assert_eq!(0, cursor.events_until(1).count());
+        assert_eq!(
+            vec![KeyEvent::Down(2)],
+            cursor.events_until(4).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![KeyEvent::Down(1), KeyEvent::Up(1)],
+            cursor.events_until(5).collect::<Vec<_>>()
+        );
+        assert_eq!(0, cursor.events_until(6).count());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn recordings_round_trip_through_json() {
+        let mut recording = InputRecording::new();
+        recording.push(entry(3, KeyEvent::Down(0xA)));
+        recording.push(entry(7, KeyEvent::Up(0xA)));
+        let json = serde_json::to_string(&recording).unwrap();
+        assert_eq!(
+            r#"[{"tick":3,"event":{"Down":10}},{"tick":7,"event":{"Up":10}}]"#,
+            json
+        );
+        assert_eq!(recording, serde_json::from_str(&json).unwrap());
+
+        let unsorted = r#"[{"tick":7,"event":{"Up":10}},{"tick":3,"event":{"Down":10}}]"#;
+        assert_eq!(recording, serde_json::from_str(unsorted).unwrap());
+    }
+}
@@ -1,54 +1,121 @@
-const TICKS_PER_SECOND: u8 = 60;
-const MS_PER_TICK: u8 = (1000u16 / TICKS_PER_SECOND as u16) as u8;
+use super::clock::Clock;
+
+pub(crate) const TICKS_PER_SECOND: u64 = 60;
+
+/// How long a timer register with the given value takes to run out
+pub(crate) fn remaining(value: u8) -> core::time::Duration {
+    core::time::Duration::from_micros(value as u64 * 1_000_000 / TICKS_PER_SECOND)
+}
 
 /// A basic timer abstractions. Since I don't want to use threads
 /// to have a simpler model for WASM, the timer rather has to be
 /// polled using it's [`Timer::tick()`] function.
-#[cfg(feature = "std")]
+#[derive(Clone)]
 pub(crate) struct Timer {
-    last_tick: std::time::Instant,
+    last_tick: u64,
+    /// Time passed since the last full step, in 1/60 ms
+    /// so it can be carried over without rounding
+    remainder: u64,
 }
 
-#[cfg(feature = "std")]
 impl Timer {
-    pub fn new() -> Self {
+    pub fn new(clock: &impl Clock) -> Self {
         Self {
-            last_tick: std::time::Instant::now(),
+            last_tick: clock.now_millis(),
+            remainder: 0,
         }
     }
+
     /// Tick the timer and return the amount of steps
-    /// it took to get back in sync. The timer will store the [`Instant`]
+    /// it took to get back in sync. The timer will store the time
     /// this function got called on and calculate the number of steps
-    /// from the difference towards the last invocation to the tick function
-    pub fn tick(&mut self) -> u8 {
-        let elapsed = self.last_tick.elapsed().as_millis();
-        let steps = elapsed * TICKS_PER_SECOND as u128 / 1000;
-        self.last_tick = std::time::Instant::now();
+    /// from the difference towards the last invocation to the tick function.
+    /// Time left over from a partial step is kept for the next call.
+    /// The step count saturates at [`u32::MAX`].
+    pub fn tick(&mut self, clock: &impl Clock) -> u32 {
+        let now = clock.now_millis();
+        let elapsed = now.saturating_sub(self.last_tick);
+        let total = elapsed
+            .saturating_mul(TICKS_PER_SECOND)
+            .saturating_add(self.remainder);
+        let steps = total / 1000;
+        self.remainder = total % 1000;
+        self.last_tick = now;
 
-        steps as u8
+        steps.min(u32::MAX as u64) as u32
     }
-}
 
-#[cfg(all(not(feature = "std"), feature = "js"))]
-pub(crate) struct Timer {
-    last_tick: f64,
+    /// The time on the clock at which the given step after the last tick
+    /// happens, e.g. to find when a register drained by [`Timer::tick`]
+    /// reached 0 in between two ticks
+    pub fn step_millis(&self, step: u32) -> u64 {
+        let units = (step as u64 * 1000).saturating_sub(self.remainder);
+        self.last_tick + units.div_ceil(TICKS_PER_SECOND)
+    }
+
+    /// The part of the given number of steps after the last tick that is
+    /// still left at the time on the clock, in 1/60 ms like the remainder
+    pub fn units_left(&self, steps: u32, now: u64) -> u64 {
+        let elapsed = now
+            .saturating_sub(self.last_tick)
+            .saturating_mul(TICKS_PER_SECOND);
+        (steps as u64 * 1000)
+            .saturating_sub(self.remainder)
+            .saturating_sub(elapsed)
+    }
+
+    /// Skip the time passed since the last tick,
+    /// e.g. after the emulator was paused
+    pub fn resync(&mut self, clock: &impl Clock) {
+        self.last_tick = clock.now_millis();
+    }
 }
 
-#[cfg(all(not(feature = "std"), feature = "js"))]
-impl Timer {
-    pub fn new() -> Self {
-        Self {
-            last_tick: js_sys::Date::now(),
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::clock::ManualClock;
+
+    #[test]
+    fn counts_steps_of_manual_clock() {
+        let mut clock = ManualClock::new();
+        let mut timer = Timer::new(&clock);
+        assert_eq!(0, timer.tick(&clock));
+
+        clock.advance(500);
+        assert_eq!(30, timer.tick(&clock));
+
+        clock.advance(60_000);
+        assert_eq!(3600, timer.tick(&clock));
     }
 
-    pub fn tick(&mut self) -> u8 {
-        let now = js_sys::Date::now();
-        let elapsed = now - self.last_tick;
+    #[test]
+    fn knows_time_of_steps() {
+        let mut clock = ManualClock::new();
+        let mut timer = Timer::new(&clock);
+        assert_eq!(50, timer.step_millis(3));
 
-        let steps = elapsed as u128 * TICKS_PER_SECOND as u128 / 1000;
-        self.last_tick = now;
+        clock.advance(10);
+        timer.tick(&clock);
+        // 10 ms are 0.6 steps, the next step happens 6.67 ms later
+        assert_eq!(17, timer.step_millis(1));
+        assert_eq!(34, timer.step_millis(2));
 
-        steps as u8
+        // 0.6 steps passed on the last tick, another 0.3 since
+        clock.advance(5);
+        assert_eq!(1100, timer.units_left(2, clock.now_millis()));
+        assert_eq!(0, timer.units_left(0, clock.now_millis()));
+    }
+
+    #[test]
+    fn keeps_partial_steps() {
+        let mut clock = ManualClock::new();
+        let mut timer = Timer::new(&clock);
+        let mut steps = 0;
+        while clock.now_millis() < 10_000 {
+            clock.advance(7);
+            steps += timer.tick(&clock);
+        }
+        assert!((599..=601).contains(&steps), "{}", steps);
     }
 }
@@ -1,17 +1,45 @@
-#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(not(test), deny(clippy::unwrap_used, clippy::expect_used))]
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+extern crate alloc;
+
+#[macro_use]
+mod logging;
+
+pub mod audio;
 mod command;
 pub mod config;
 mod cpu;
+pub mod debug;
 mod display;
 pub mod emulator;
+pub mod error;
 mod io;
 mod memory;
 mod opcode;
+#[cfg(any(feature = "rom-db", feature = "std"))]
+mod rom_db;
+
+pub use cpu::{CpuSnapshot, Register};
+pub use display::{DisplayMode, Palette, Rgba};
+#[cfg(feature = "js")]
+pub use io::clock::JsClock;
+#[cfg(feature = "std")]
+pub use io::clock::StdClock;
+pub use io::clock::{Clock, DefaultClock, ManualClock};
+#[cfg(feature = "std")]
+pub use io::flags::FileFlagStore;
+pub use io::flags::{FlagStore, MAX_FLAGS};
+pub use io::keyboard::{KeyEvent, KeySource};
+pub use io::keymap::KeyMap;
+#[cfg(feature = "alloc")]
+pub use io::recording::{InputEntry, InputRecording};
+#[cfg(feature = "alloc")]
+pub use memory::{MemorySnapshot, MemoryWindow};
 
 #[cfg(test)]
 mod test {
-    use super::*;
     use std::io::Write;
 
     #[test]
@@ -0,0 +1,170 @@
+//! The logging macros of the crate, so the log crate can be left out.
+//! With the `tracing` feature they emit tracing events, otherwise with
+//! the `defmt` or `log` feature they forward to that crate, in this order.
+//! Without any of them they only check their arguments and emit nothing.
+
+#[cfg(feature = "tracing")]
+macro_rules! log_warn {
+    ($($arg:tt)+) => {
+        tracing::warn!($($arg)+)
+    };
+}
+
+#[cfg(all(feature = "defmt", not(feature = "tracing")))]
+macro_rules! log_warn {
+    (target: $target:expr, $($arg:tt)+) => {
+        log_warn!($($arg)+)
+    };
+    ($($arg:tt)+) => {
+        defmt::warn!("{=str}", $crate::logging::Message::format(format_args!($($arg)+)).as_str())
+    };
+}
+
+#[cfg(all(feature = "log", not(any(feature = "tracing", feature = "defmt"))))]
+macro_rules! log_warn {
+    ($($arg:tt)+) => {
+        log::warn!($($arg)+)
+    };
+}
+
+#[cfg(not(any(feature = "log", feature = "tracing", feature = "defmt")))]
+macro_rules! log_warn {
+    (target: $target:expr, $($arg:tt)+) => {
+        log_warn!($($arg)+)
+    };
+    ($($arg:tt)+) => {{
+        let _ = format_args!($($arg)+);
+    }};
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! log_trace {
+    ($($arg:tt)+) => {
+        tracing::trace!($($arg)+)
+    };
+}
+
+#[cfg(all(feature = "defmt", not(feature = "tracing")))]
+macro_rules! log_trace {
+    (target: $target:expr, $($arg:tt)+) => {
+        log_trace!($($arg)+)
+    };
+    ($($arg:tt)+) => {
+        defmt::trace!("{=str}", $crate::logging::Message::format(format_args!($($arg)+)).as_str())
+    };
+}
+
+#[cfg(all(feature = "log", not(any(feature = "tracing", feature = "defmt"))))]
+macro_rules! log_trace {
+    ($($arg:tt)+) => {
+        log::trace!($($arg)+)
+    };
+}
+
+#[cfg(not(any(feature = "log", feature = "tracing", feature = "defmt")))]
+macro_rules! log_trace {
+    (target: $target:expr, $($arg:tt)+) => {
+        log_trace!($($arg)+)
+    };
+    ($($arg:tt)+) => {{
+        let _ = format_args!($($arg)+);
+    }};
+}
+
+/// The longest message defmt gets, longer ones are cut off
+#[cfg(feature = "defmt")]
+const MESSAGE_LEN: usize = 128;
+
+/// A message formatted on the stack, as defmt only takes its
+/// own format strings and the crate may not be able to allocate
+#[cfg(feature = "defmt")]
+pub(crate) struct Message {
+    bytes: [u8; MESSAGE_LEN],
+    len: usize,
+}
+
+#[cfg(feature = "defmt")]
+impl Message {
+    pub(crate) fn format(args: core::fmt::Arguments<'_>) -> Self {
+        let mut message = Self {
+            bytes: [0; MESSAGE_LEN],
+            len: 0,
+        };
+        let _ = core::fmt::Write::write_fmt(&mut message, args);
+        message
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        // Only whole characters are ever written
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl core::fmt::Write for Message {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut end = s.len().min(MESSAGE_LEN - self.len);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.bytes[self.len..self.len + end].copy_from_slice(&s.as_bytes()[..end]);
+        self.len += end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn macros_take_the_log_syntax() {
+        let address = 0x200;
+        let result: Result<(), &str> = Err("invalid");
+        match result {
+            Ok(()) => log_trace!("fine"),
+            Err(error) => log_warn!("{} at {:#05X}", error, address),
+        }
+        log_trace!(target: "chip8::step", "{:04X}", address);
+        log_warn!(target: "chip8::step", "{}", address);
+    }
+
+    /// Counts the bytes defmt writes, so tests can link and check
+    /// that something was logged without decoding the frames
+    #[cfg(feature = "defmt")]
+    #[defmt::global_logger]
+    struct CountingLogger;
+
+    #[cfg(feature = "defmt")]
+    static WRITTEN: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    #[cfg(feature = "defmt")]
+    unsafe impl defmt::Logger for CountingLogger {
+        fn acquire() {}
+        unsafe fn flush() {}
+        unsafe fn release() {}
+        unsafe fn write(bytes: &[u8]) {
+            WRITTEN.fetch_add(bytes.len(), std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    #[cfg(feature = "defmt")]
+    defmt::timestamp!("{=u32}", 0);
+
+    #[cfg(feature = "defmt")]
+    #[test]
+    fn messages_are_cut_at_character_boundaries() {
+        let message = super::Message::format(format_args!("{}", "ä".repeat(100)));
+        assert_eq!("ä".repeat(64), message.as_str());
+    }
+
+    #[cfg(feature = "defmt")]
+    #[test]
+    fn state_types_format_with_defmt() {
+        use crate::{debug::Instruction, emulator::Emulator, error::Chip8Error};
+
+        let before = WRITTEN.load(std::sync::atomic::Ordering::Relaxed);
+        let error = Chip8Error::StackUnderflow { pc: 0x200 };
+        let snapshot = Emulator::new().cpu_snapshot();
+        defmt::error!("{} {} {}", Instruction::decode(0x6001), snapshot, error);
+        assert!(WRITTEN.load(std::sync::atomic::Ordering::Relaxed) > before);
+    }
+}
@@ -1,83 +1,506 @@
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+use core::ops::Range;
+
 pub(crate) const CHIP8_START: usize = 0x200;
+pub(crate) const ETI660_START: usize = 0x600;
 pub(crate) const MEMORY_SIZE: usize = 4096;
-const ETI660_START: usize = 0x200;
+/// The most memory the enabled features can address
+#[cfg(feature = "xochip")]
+pub(crate) const MAX_MEMORY_SIZE: usize = crate::config::XO_CHIP_MEMORY_SIZE;
+#[cfg(not(feature = "xochip"))]
+pub(crate) const MAX_MEMORY_SIZE: usize = MEMORY_SIZE;
+pub(crate) const MAX_STACK_DEPTH: usize = 16;
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A 64-bit FNV-1a hasher. It is used for all checksums
+/// in the emulator, as it is stable across platforms and versions
+/// and does not need to allocate.
+pub(crate) struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub(crate) fn new() -> Self {
+        Self(FNV_OFFSET_BASIS)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub(crate) fn write_u16(&mut self, value: u16) {
+        self.write(&value.to_be_bytes());
+    }
+
+    pub(crate) fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_be_bytes());
+    }
 
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// The memory is a fixed buffer large enough for the enabled features,
+/// of which only the configured size is addressable.
+/// Everything past that size is kept zeroed.
 pub(crate) struct Memory {
-    buffer: [u8; MEMORY_SIZE],
+    buffer: [u8; MAX_MEMORY_SIZE],
+    len: usize,
 }
 
 impl Memory {
+    #[cfg(test)]
     pub(crate) fn new() -> Self {
+        Self::with_size(MEMORY_SIZE)
+    }
+
+    /// Memory of the given number of bytes, capped at [`MAX_MEMORY_SIZE`]
+    pub(crate) fn with_size(size: usize) -> Self {
         Self {
-            buffer: [0; MEMORY_SIZE],
+            buffer: [0; MAX_MEMORY_SIZE],
+            len: size.min(MAX_MEMORY_SIZE),
         }
     }
 
-    pub(crate) fn read_u16(&self, ptr: u16) -> u16 {
-        let ptr = ptr as usize;
-        u16::from_be_bytes(
-            self.buffer[ptr..=ptr + 1]
-                .try_into()
-                .expect("Buffer big enough"),
-        )
+    pub(crate) fn len(&self) -> usize {
+        self.len
     }
 
-    pub(crate) fn clear_public(&mut self) {
-        self.buffer[CHIP8_START..MEMORY_SIZE].copy_from_slice(&[0; MEMORY_SIZE - CHIP8_START]);
+    /// Change the addressable size, capped at [`MAX_MEMORY_SIZE`].
+    /// Bytes that fall out of memory are cleared.
+    pub(crate) fn resize(&mut self, size: usize) {
+        let size = size.min(MAX_MEMORY_SIZE);
+        if size < self.len {
+            self.buffer[size..self.len].fill(0);
+        }
+        self.len = size;
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer[..self.len]
+    }
+
+    /// Read the big-endian word at ptr, or None if it
+    /// does not lie completely inside of memory
+    pub(crate) fn read_u16(&self, ptr: u16) -> Option<u16> {
+        let high = self.get_u8(ptr)?;
+        let low = self.get_u8(ptr.checked_add(1)?)?;
+        Some(u16::from_be_bytes([high, low]))
+    }
+
+    /// Read the byte at ptr, or None if it lies outside of memory
+    pub(crate) fn get_u8(&self, ptr: u16) -> Option<u8> {
+        self.bytes().get(ptr as usize).copied()
+    }
+
+    /// Clear all memory from the given program start address onwards.
+    /// Everything below it is reserved for the interpreter and stays untouched.
+    pub(crate) fn clear_public(&mut self, start: u16) {
+        self.bytes_mut()[start as usize..].fill(0);
     }
 
     pub(crate) fn read_u8(&self, ptr: u16) -> u8 {
-        self.buffer[ptr as usize]
+        self.bytes()[ptr as usize]
     }
 
     pub(crate) fn write_u8(&mut self, ptr: u16, value: u8) {
-        self.buffer[ptr as usize] = value;
+        self.bytes_mut()[ptr as usize] = value;
     }
 
+    #[cfg(test)]
     pub(crate) fn write_u16(&mut self, ptr: u16, value: u16) {
         let ptr = ptr as usize;
         let values = value.to_be_bytes();
-        self.buffer[ptr] = values[0];
-        self.buffer[ptr + 1] = values[1];
+        self.bytes_mut()[ptr..ptr + 2].copy_from_slice(&values);
+    }
+
+    /// Copy the values into memory starting at ptr. If they don't fit,
+    /// memory is left untouched and false is returned.
+    pub(crate) fn try_copy_from_slice(&mut self, ptr: u16, values: &[u8]) -> bool {
+        let start = ptr as usize;
+        match self.bytes_mut().get_mut(start..start + values.len()) {
+            Some(target) => {
+                target.copy_from_slice(values);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Clamp the given range to the memory bounds
+    fn clamp(&self, range: Range<u16>) -> Range<usize> {
+        let end = (range.end as usize).min(self.len());
+        let start = (range.start as usize).min(end);
+        start..end
+    }
+
+    /// Copy the given range of memory into a new [`MemorySnapshot`].
+    /// The range gets clamped to the memory bounds.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn snapshot(&self, range: Range<u16>) -> MemorySnapshot {
+        let range = self.clamp(range);
+        MemorySnapshot {
+            base: range.start as u16,
+            bytes: self.bytes()[range].to_vec(),
+        }
     }
 
-    pub(crate) fn copy_from_slice(&mut self, ptr: u16, values: &[u8]) {
-        self.buffer[(ptr as usize)..(ptr as usize) + values.len()].copy_from_slice(values);
+    /// Copy the given range of memory into the buffer, returning
+    /// the number of bytes copied. The range gets clamped to the
+    /// memory bounds and the length of the buffer.
+    pub(crate) fn snapshot_into(&self, range: Range<u16>, buffer: &mut [u8]) -> usize {
+        let range = self.clamp(range);
+        let len = range.len().min(buffer.len());
+        buffer[..len].copy_from_slice(&self.bytes()[range.start..range.start + len]);
+        len
+    }
+
+    /// Copy the bytes up to radius around the given address into a [`MemoryWindow`].
+    /// The window is clamped to the memory bounds on both ends.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn window(&self, center: u16, radius: u16) -> MemoryWindow {
+        let end = (center as usize + radius as usize + 1).min(self.len());
+        let start = (center.saturating_sub(radius) as usize).min(end);
+        MemoryWindow {
+            start: start as u16,
+            center,
+            bytes: self.bytes()[start..end].to_vec(),
+        }
+    }
+
+    /// Write the bytes stored in the snapshot back to where they were copied from
+    #[cfg(feature = "alloc")]
+    pub(crate) fn restore(&mut self, snapshot: &MemorySnapshot) {
+        let range = self.clamp(snapshot.range());
+        let len = range.len();
+        self.bytes_mut()[range].copy_from_slice(&snapshot.bytes[..len]);
+    }
+
+    /// The 64-bit FNV-1a hash over the full memory buffer,
+    /// including the interpreter area below the program start.
+    pub(crate) fn checksum(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        hasher.write(self.bytes());
+        hasher.finish()
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+/// A copy of a region of emulator memory,
+/// that can be written back with [`crate::emulator::Emulator::restore_memory`]
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemorySnapshot {
+    base: u16,
+    bytes: Vec<u8>,
+}
 
-    #[test]
-    fn can_load_store() {
-        let mut memory = Memory::new();
-        memory.write_u16(2, 0x200);
-        assert_eq!(0x200, memory.read_u16(2));
+#[cfg(feature = "alloc")]
+impl MemorySnapshot {
+    /// The address the snapshot was taken from
+    pub fn base(&self) -> u16 {
+        self.base
+    }
+
+    /// The number of bytes in the snapshot
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The memory range the snapshot covers
+    pub fn range(&self) -> Range<u16> {
+        self.base..self.base + self.bytes.len() as u16
+    }
+}
+
+/// A copy of the memory around an address, usually the program counter.
+/// Near the ends of memory the window is cut short instead of wrapping.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryWindow {
+    start: u16,
+    center: u16,
+    bytes: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl MemoryWindow {
+    /// The address of the first byte in the window
+    pub fn start(&self) -> u16 {
+        self.start
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The offset of the center address (e.g. the PC) into [`MemoryWindow::bytes`],
+    /// or None if it lies outside of memory
+    pub fn center_offset(&self) -> Option<usize> {
+        let offset = self.center.checked_sub(self.start)? as usize;
+        (offset < self.bytes.len()).then_some(offset)
+    }
+
+    /// The byte at the given address, if it is part of the window
+    pub fn byte_at(&self, address: u16) -> Option<u8> {
+        let offset = address.checked_sub(self.start)? as usize;
+        self.bytes.get(offset).copied()
+    }
+
+    /// The big-endian word starting at the given address, if it is part of the window
+    pub fn word_at(&self, address: u16) -> Option<u16> {
+        let high = self.byte_at(address)?;
+        let low = self.byte_at(address.checked_add(1)?)?;
+        Some(u16::from_be_bytes([high, low]))
+    }
+
+    /// All words in the window, aligned so that one starts at the center address.
+    /// Yields the address of each word together with its value.
+    pub fn words(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        // The window can end at 0x10000, so the addresses are counted in usize
+        let start = self.start as usize;
+        let first = start + (self.center.wrapping_sub(self.start) & 1) as usize;
+        (first..start + self.bytes.len())
+            .step_by(2)
+            .filter_map(|address| {
+                let address = u16::try_from(address).ok()?;
+                Some((address, self.word_at(address)?))
+            })
     }
 }
 
 pub(crate) struct Stack {
     ptr: usize,
-    buffer: [u16; 16],
+    depth: usize,
+    buffer: [u16; MAX_STACK_DEPTH],
 }
 
 impl Stack {
-    pub fn new() -> Self {
+    /// Create a new stack holding up to depth values.
+    /// The depth is capped at [`MAX_STACK_DEPTH`].
+    pub fn new(depth: usize) -> Self {
         Self {
             ptr: 0,
-            buffer: [0; 16],
+            depth: depth.min(MAX_STACK_DEPTH),
+            buffer: [0; MAX_STACK_DEPTH],
         }
     }
 
-    pub fn push(&mut self, value: u16) {
+    /// Push a value onto the stack. If the stack is full,
+    /// the value is discarded and false is returned.
+    pub fn push(&mut self, value: u16) -> bool {
+        if self.ptr >= self.depth {
+            return false;
+        }
         self.buffer[self.ptr] = value;
         self.ptr += 1;
+        true
+    }
+    /// Pop the most recently pushed value,
+    /// or None if the stack is empty
+    pub fn pop(&mut self) -> Option<u16> {
+        self.ptr = self.ptr.checked_sub(1)?;
+        Some(self.buffer[self.ptr])
+    }
+
+    /// The currently pushed values, oldest first
+    pub fn contents(&self) -> &[u16] {
+        &self.buffer[..self.ptr]
+    }
+
+    /// The stack pointer and the slot the next push writes,
+    /// which is all a push or pop changes
+    #[cfg(feature = "alloc")]
+    pub fn top(&self) -> (usize, u16) {
+        (self.ptr, self.buffer.get(self.ptr).copied().unwrap_or(0))
+    }
+
+    /// Undo pushes and pops back to the [`Stack::top`] saved before
+    #[cfg(feature = "alloc")]
+    pub fn restore_top(&mut self, (ptr, value): (usize, u16)) {
+        self.ptr = ptr.min(self.depth);
+        if let Some(slot) = self.buffer.get_mut(self.ptr) {
+            *slot = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn can_load_store() {
+        let mut memory = Memory::new();
+        memory.write_u16(2, 0x200);
+        assert_eq!(Some(0x200), memory.read_u16(2));
+    }
+
+    #[test]
+    fn read_u16_is_checked() {
+        let mut memory = Memory::new();
+        memory.write_u16(0xFFE, 0xABCD);
+        assert_eq!(Some(0xABCD), memory.read_u16(0xFFE));
+        assert_eq!(None, memory.read_u16(0xFFF));
+        assert_eq!(None, memory.read_u16(u16::MAX));
+    }
+
+    #[test]
+    #[cfg(feature = "xochip")]
+    fn xo_chip_memory_spans_the_address_space() {
+        let mut memory = Memory::with_size(crate::config::XO_CHIP_MEMORY_SIZE);
+        memory.write_u16(0xFFFE, 0xABCD);
+        assert_eq!(Some(0xABCD), memory.read_u16(0xFFFE));
+        assert_eq!(None, memory.read_u16(u16::MAX));
+        assert!(memory.try_copy_from_slice(0x1000, &[1; 0x2000]));
+        memory.clear_public(0x200);
+        assert_eq!(Some(0), memory.read_u16(0xFFFE));
+    }
+
+    #[test]
+    #[cfg(feature = "xochip")]
+    fn resizing_clears_the_bytes_past_the_end() {
+        let mut memory = Memory::with_size(crate::config::XO_CHIP_MEMORY_SIZE);
+        memory.write_u8(0x0FFF, 1);
+        memory.write_u8(0x1000, 2);
+        memory.resize(MEMORY_SIZE);
+        assert_eq!(MEMORY_SIZE, memory.len());
+        assert_eq!(None, memory.get_u8(0x1000));
+
+        memory.resize(crate::config::XO_CHIP_MEMORY_SIZE);
+        assert_eq!(Some(1), memory.get_u8(0x0FFF));
+        assert_eq!(Some(0), memory.get_u8(0x1000));
+    }
+
+    #[test]
+    fn try_copy_rejects_oob() {
+        let mut memory = Memory::new();
+        assert!(memory.try_copy_from_slice(0xFFE, &[1, 2]));
+        assert!(!memory.try_copy_from_slice(0xFFF, &[3, 4]));
+        assert_eq!(2, memory.read_u8(0xFFF));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn snapshots_are_clamped() {
+        let mut memory = Memory::new();
+        memory.write_u8(0xFFF, 7);
+
+        let snapshot = memory.snapshot(0xFFE..0x1010);
+        assert_eq!(0xFFE, snapshot.base());
+        assert_eq!(&[0, 7], snapshot.bytes());
+
+        let mut buffer = [0; 4];
+        assert_eq!(2, memory.snapshot_into(0xFFE..0x1010, &mut buffer));
+        assert_eq!([0, 7, 0, 0], buffer);
+        assert_eq!(1, memory.snapshot_into(0xFFF..0x1000, &mut buffer[..1]));
+        assert_eq!([7, 7, 0, 0], buffer);
+    }
+
+    #[test]
+    fn stack_respects_depth() {
+        let mut stack = Stack::new(2);
+        assert!(stack.push(1));
+        assert!(stack.push(2));
+        assert!(!stack.push(3));
+        assert_eq!(&[1, 2], stack.contents());
+        assert_eq!(Some(2), stack.pop());
+        assert_eq!(Some(1), stack.pop());
+        assert_eq!(None, stack.pop());
+
+        let stack = Stack::new(usize::MAX);
+        assert_eq!(MAX_STACK_DEPTH, stack.depth);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn windows_are_clamped() {
+        let mut memory = Memory::new();
+        memory.write_u16(0x000, 0x1234);
+        memory.write_u16(0xFFE, 0xABCD);
+
+        let window = memory.window(0x000, 4);
+        assert_eq!(0x000, window.start());
+        assert_eq!(5, window.bytes().len());
+        assert_eq!(Some(0), window.center_offset());
+        assert_eq!(Some(0x1234), window.word_at(0x000));
+
+        let window = memory.window(0x002, 4);
+        assert_eq!(0x000, window.start());
+        assert_eq!(Some(2), window.center_offset());
+        assert_eq!(
+            vec![(0x000, 0x1234), (0x002, 0x0000), (0x004, 0x0000)],
+            window.words().collect::<Vec<_>>()
+        );
+
+        let window = memory.window(0xFFE, 4);
+        assert_eq!(0xFFA, window.start());
+        assert_eq!(6, window.bytes().len());
+        assert_eq!(Some(4), window.center_offset());
+        assert_eq!(Some(0xABCD), window.word_at(0xFFE));
+        assert_eq!(None, window.word_at(0xFFF));
+
+        let window = memory.window(0x1000, 2);
+        assert_eq!(None, window.center_offset());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    #[cfg(feature = "xochip")]
+    fn windows_can_end_at_the_top_of_64_kib() {
+        let mut memory = Memory::with_size(crate::config::XO_CHIP_MEMORY_SIZE);
+        memory.write_u16(0xFFFE, 0xABCD);
+
+        let window = memory.window(0xFFFE, 0x100);
+        assert_eq!(0xFEFE, window.start());
+        let words: Vec<_> = window.words().collect();
+        assert_eq!(0x81, words.len());
+        assert_eq!(Some(&(0xFFFE, 0xABCD)), words.last());
+
+        let window = memory.window(0x8000, 0x8000);
+        assert_eq!(0x10000, window.bytes().len());
+        assert_eq!(0x8000, window.words().count());
+    }
+
+    #[test]
+    fn checksum_is_stable() {
+        let mut memory = Memory::new();
+        assert_eq!(memory.checksum(), Memory::new().checksum());
+
+        memory.write_u8(0xFFF, 1);
+        assert_ne!(memory.checksum(), Memory::new().checksum());
     }
-    pub fn pop(&mut self) -> u16 {
-        self.ptr -= 1;
-        let value = self.buffer[self.ptr];
-        value
+
+    #[test]
+    fn fnv1a_matches_reference() {
+        let mut hasher = Fnv1a::new();
+        assert_eq!(0xcbf2_9ce4_8422_2325, hasher.finish());
+        hasher.write(b"a");
+        assert_eq!(0xaf63_dc4c_8601_ec8c, hasher.finish());
+    }
+
+    #[test]
+    fn clear_public_keeps_interpreter_memory() {
+        let mut memory = Memory::new();
+        memory.write_u8(0x050, 0xF0);
+        memory.write_u8(ETI660_START as u16, 0x12);
+        memory.clear_public(ETI660_START as u16);
+        assert_eq!(0xF0, memory.read_u8(0x050));
+        assert_eq!(0x00, memory.read_u8(ETI660_START as u16));
     }
 }
@@ -1,7 +1,3 @@
-#[cfg(not(feature = "std"))]
-extern crate alloc;
-#[cfg(not(feature = "std"))]
-use alloc::{format, string::String, vec::Vec};
 /// All known OpCodes of the Chip8,
 /// as well as one variant for invalid opcodes
 #[derive(Debug, Eq, PartialEq)]
@@ -12,6 +8,34 @@ pub(crate) enum OpCode {
     /// 0x00EE
     /// Return from subroutine
     Return(u16),
+    /// 0x00CN
+    /// SUPER-CHIP: Scroll the display down by N pixels
+    #[cfg(feature = "schip")]
+    ScrollDown(u16),
+    /// 0x00DN
+    /// XO-CHIP: Scroll the display up by N pixels
+    #[cfg(feature = "xochip")]
+    ScrollUp(u16),
+    /// 0x00FB
+    /// SUPER-CHIP: Scroll the display right by 4 pixels
+    #[cfg(feature = "schip")]
+    ScrollRight(u16),
+    /// 0x00FC
+    /// SUPER-CHIP: Scroll the display left by 4 pixels
+    #[cfg(feature = "schip")]
+    ScrollLeft(u16),
+    /// 0x00FD
+    /// SUPER-CHIP: Exit the interpreter
+    #[cfg(feature = "schip")]
+    Exit(u16),
+    /// 0x00FE
+    /// SUPER-CHIP: Switch the display to the low resolution of 64x32 pixels
+    #[cfg(feature = "schip")]
+    LowRes(u16),
+    /// 0x00FF
+    /// SUPER-CHIP: Switch the display to the high resolution of 128x64 pixels
+    #[cfg(feature = "schip")]
+    HighRes(u16),
     /// 0x1NNN
     /// Jump to memory location NNN
     Jump(u16),
@@ -27,6 +51,14 @@ pub(crate) enum OpCode {
     /// 0x5XY0
     /// Skip instruction if values of registers X and Y are equal
     SkipIfRegistersAreEqual(u16),
+    /// 0x5XY2
+    /// XO-CHIP: Save the registers X to Y to memory at I
+    #[cfg(feature = "xochip")]
+    SaveRegisterRange(u16),
+    /// 0x5XY3
+    /// XO-CHIP: Load the registers X to Y from memory at I
+    #[cfg(feature = "xochip")]
+    LoadRegisterRange(u16),
     /// 0x6XNN
     /// Set the register X to the value NN
     Load(u16),
@@ -91,33 +123,73 @@ pub(crate) enum OpCode {
     SetSound(u16),
     AddI(u16),
     LoadSprite(u16),
+    /// 0xFX30
+    /// SUPER-CHIP: Point I at the large font glyph of the lower nibble of register X
+    #[cfg(feature = "schip")]
+    LoadLargeSprite(u16),
     LoadBcd(u16),
     DumpAll(u16),
     LoadAll(u16),
+    /// 0xFX75
+    /// SUPER-CHIP: Save the registers V0 to VX into the user flags
+    #[cfg(feature = "schip")]
+    SaveFlags(u16),
+    /// 0xFX85
+    /// SUPER-CHIP: Load the registers V0 to VX from the user flags
+    #[cfg(feature = "schip")]
+    LoadFlags(u16),
+    /// 0xFN01
+    /// XO-CHIP: Select the bit planes to draw on with the bitmask N
+    #[cfg(feature = "xochip")]
+    SelectPlanes(u16),
+    /// 0xF000 0xNNNN
+    /// XO-CHIP: Load the 16-bit address NNNN of the following word into I
+    #[cfg(feature = "xochip")]
+    LoadLongI(u16),
+    /// 0xF002
+    /// XO-CHIP: Copy the 16 bytes at I into the audio pattern buffer
+    #[cfg(feature = "xochip")]
+    LoadAudioPattern(u16),
+    /// 0xFX3A
+    /// XO-CHIP: Set the playback rate of the audio pattern to the value of register X
+    #[cfg(feature = "xochip")]
+    SetPitch(u16),
     Invalid(u16),
 }
 
-impl OpCode {
-    pub fn into_inner(self) -> u16 {
-        todo!()
-    }
-}
-
 impl From<u16> for OpCode {
     fn from(value: u16) -> Self {
         let repr: [char; 4] = raw_opcode_chars(value);
-        log::trace!("{:?}", repr);
+        log_trace!("{:?}", repr);
         match repr {
             [' ', ' ', 'E', _] => match repr[3] {
                 '0' => OpCode::ClearScreen(value),
                 'E' => OpCode::Return(value),
                 _ => OpCode::Invalid(value),
             },
+            #[cfg(feature = "schip")]
+            [' ', ' ', 'C', _] => OpCode::ScrollDown(value),
+            #[cfg(feature = "xochip")]
+            [' ', ' ', 'D', _] => OpCode::ScrollUp(value),
+            #[cfg(feature = "schip")]
+            [' ', ' ', 'F', 'B'] => OpCode::ScrollRight(value),
+            #[cfg(feature = "schip")]
+            [' ', ' ', 'F', 'C'] => OpCode::ScrollLeft(value),
+            #[cfg(feature = "schip")]
+            [' ', ' ', 'F', 'D'] => OpCode::Exit(value),
+            #[cfg(feature = "schip")]
+            [' ', ' ', 'F', 'E'] => OpCode::LowRes(value),
+            #[cfg(feature = "schip")]
+            [' ', ' ', 'F', 'F'] => OpCode::HighRes(value),
             ['1', ..] => OpCode::Jump(value),
             ['2', ..] => OpCode::Call(value),
             ['3', ..] => OpCode::SkipIfRegisterEqualsValue(value),
             ['4', ..] => OpCode::SkipIfRegisterNotEqualsValue(value),
-            ['5', ..] => OpCode::SkipIfRegistersAreEqual(value),
+            ['5', _, _, '0'] => OpCode::SkipIfRegistersAreEqual(value),
+            #[cfg(feature = "xochip")]
+            ['5', _, _, '2'] => OpCode::SaveRegisterRange(value),
+            #[cfg(feature = "xochip")]
+            ['5', _, _, '3'] => OpCode::LoadRegisterRange(value),
             ['6', ..] => OpCode::Load(value),
             ['7', ..] => OpCode::Add(value),
             ['8', ..] => decode_8_opcodes(repr, value),
@@ -134,13 +206,23 @@ impl From<u16> for OpCode {
     }
 }
 
+const HEX_DIGITS: [char; 16] = [
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'A', 'B', 'C', 'D', 'E', 'F',
+];
+
+/// The upper case hex digits of the opcode, right aligned like `{:4X}`,
+/// so leading zero nibbles are blanks.
 pub(crate) fn raw_opcode_chars(opcode: u16) -> [char; 4] {
-    format!("{:4X}", opcode)
-        .chars()
-        .into_iter()
-        .collect::<Vec<_>>()
-        .try_into()
-        .expect("Valid hex wrapper")
+    let mut chars = [' '; 4];
+    let mut is_leading = true;
+    for (index, char) in chars.iter_mut().enumerate() {
+        let nibble = (opcode >> (12 - index * 4)) & 0x0F;
+        is_leading &= nibble == 0 && index < 3;
+        if !is_leading {
+            *char = HEX_DIGITS[nibble as usize];
+        }
+    }
+    chars
 }
 
 fn decode_8_opcodes(repr: [char; 4], value: u16) -> OpCode {
@@ -166,9 +248,23 @@ fn decode_f_opcodes(repr: [char; 4], value: u16) -> OpCode {
         ['F', _, '1', '8'] => OpCode::SetSound(value),
         ['F', _, '1', 'E'] => OpCode::AddI(value),
         ['F', _, '2', '9'] => OpCode::LoadSprite(value),
+        #[cfg(feature = "schip")]
+        ['F', _, '3', '0'] => OpCode::LoadLargeSprite(value),
         ['F', _, '3', '3'] => OpCode::LoadBcd(value),
         ['F', _, '5', '5'] => OpCode::DumpAll(value),
         ['F', _, '6', '5'] => OpCode::LoadAll(value),
+        #[cfg(feature = "schip")]
+        ['F', _, '7', '5'] => OpCode::SaveFlags(value),
+        #[cfg(feature = "schip")]
+        ['F', _, '8', '5'] => OpCode::LoadFlags(value),
+        #[cfg(feature = "xochip")]
+        ['F', '0', '0', '0'] => OpCode::LoadLongI(value),
+        #[cfg(feature = "xochip")]
+        ['F', _, '0', '1'] => OpCode::SelectPlanes(value),
+        #[cfg(feature = "xochip")]
+        ['F', '0', '0', '2'] => OpCode::LoadAudioPattern(value),
+        #[cfg(feature = "xochip")]
+        ['F', _, '3', 'A'] => OpCode::SetPitch(value),
         _ => OpCode::Invalid(value),
     }
 }
@@ -177,6 +273,46 @@ fn decode_f_opcodes(repr: [char; 4], value: u16) -> OpCode {
 mod test {
     use super::*;
 
+    #[test]
+    fn chars_match_hex_formatting() {
+        assert_eq!([' ', ' ', 'E', '0'], raw_opcode_chars(0x00E0));
+        assert_eq!([' ', ' ', ' ', '0'], raw_opcode_chars(0x0000));
+        assert_eq!([' ', '1', '0', 'A'], raw_opcode_chars(0x010A));
+        assert_eq!(['F', '0', '6', '5'], raw_opcode_chars(0xF065));
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn resolution_changes_should_parse() {
+        assert_eq!(OpCode::LowRes(0x00FE), 0x00FE.into());
+        assert_eq!(OpCode::HighRes(0x00FF), 0x00FF.into());
+        assert_eq!(OpCode::Exit(0x00FD), 0x00FD.into());
+        assert_eq!(OpCode::Invalid(0x00F0), 0x00F0.into());
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn scrolls_should_parse() {
+        assert_eq!(OpCode::ScrollDown(0x00C3), 0x00C3.into());
+        #[cfg(feature = "xochip")]
+        assert_eq!(OpCode::ScrollUp(0x00D3), 0x00D3.into());
+        assert_eq!(OpCode::ScrollRight(0x00FB), 0x00FB.into());
+        assert_eq!(OpCode::ScrollLeft(0x00FC), 0x00FC.into());
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn large_sprite_should_parse() {
+        assert_eq!(OpCode::LoadLargeSprite(0xF530), 0xF530.into());
+    }
+
+    #[cfg(feature = "schip")]
+    #[test]
+    fn flags_should_parse() {
+        assert_eq!(OpCode::SaveFlags(0xF775), 0xF775.into());
+        assert_eq!(OpCode::LoadFlags(0xF785), 0xF785.into());
+    }
+
     #[test]
     fn cls_should_parse() {
         let opcode: u16 = 0x00E0;
@@ -317,4 +453,49 @@ mod test {
         let opcode: u16 = 0xF565;
         assert_eq!(OpCode::LoadAll(opcode), opcode.into());
     }
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn audio_pattern_should_parse() {
+        let opcode: u16 = 0xF002;
+        assert_eq!(OpCode::LoadAudioPattern(opcode), opcode.into());
+        let opcode: u16 = 0xF102;
+        assert_eq!(OpCode::Invalid(opcode), opcode.into());
+    }
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn long_i_should_parse() {
+        let opcode: u16 = 0xF000;
+        assert_eq!(OpCode::LoadLongI(opcode), opcode.into());
+        let opcode: u16 = 0xF100;
+        assert_eq!(OpCode::Invalid(opcode), opcode.into());
+    }
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn select_planes_should_parse() {
+        let opcode: u16 = 0xF301;
+        assert_eq!(OpCode::SelectPlanes(opcode), opcode.into());
+    }
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn register_ranges_should_parse() {
+        assert_eq!(OpCode::SkipIfRegistersAreEqual(0x5120), 0x5120.into());
+        assert_eq!(OpCode::SaveRegisterRange(0x5122), 0x5122.into());
+        assert_eq!(OpCode::LoadRegisterRange(0x5F03), 0x5F03.into());
+        assert_eq!(OpCode::Invalid(0x5121), 0x5121.into());
+    }
+    #[cfg(feature = "xochip")]
+    #[test]
+    fn pitch_should_parse() {
+        let opcode: u16 = 0xF53A;
+        assert_eq!(OpCode::SetPitch(opcode), opcode.into());
+    }
+    #[cfg(not(feature = "schip"))]
+    #[test]
+    fn extensions_are_invalid_without_features() {
+        for opcode in [
+            0x00C3, 0x00D3, 0x00FB, 0x00FF, 0x5122, 0xF000, 0xF301, 0xF575,
+        ] {
+            assert_eq!(OpCode::Invalid(opcode), opcode.into());
+        }
+    }
 }
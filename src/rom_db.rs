@@ -0,0 +1,77 @@
+//! A small database of roms with known quirk requirements,
+//! keyed by the CRC32 of the rom image.
+
+#[cfg(feature = "rom-db")]
+use crate::config::EmulatorConfiguration;
+
+/// A rom the database knows the recommended configuration for
+#[cfg(feature = "rom-db")]
+struct KnownRom {
+    crc32: u32,
+    len: usize,
+    configuration: fn() -> EmulatorConfiguration,
+}
+
+#[cfg(feature = "rom-db")]
+const KNOWN_ROMS: [KnownRom; 3] = [
+    // BC_test by BestCoder
+    KnownRom {
+        crc32: 0xa542_2aa5,
+        len: 470,
+        configuration: EmulatorConfiguration::super_chip_modern,
+    },
+    // IBM logo
+    KnownRom {
+        crc32: 0xc46c_a868,
+        len: 132,
+        configuration: EmulatorConfiguration::cosmac_vip,
+    },
+    // test_opcode by corax89
+    KnownRom {
+        crc32: 0x9011_a949,
+        len: 478,
+        configuration: EmulatorConfiguration::super_chip_modern,
+    },
+];
+
+/// The standard (IEEE 802.3) CRC32, computed bitwise
+/// as the roms are small and this runs once per load
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Look up the recommended configuration for the given rom
+#[cfg(feature = "rom-db")]
+pub(crate) fn lookup(rom: &[u8]) -> Option<EmulatorConfiguration> {
+    let crc = crc32(rom);
+    KNOWN_ROMS
+        .iter()
+        .find(|known| known.len == rom.len() && known.crc32 == crc)
+        .map(|known| (known.configuration)())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_reference() {
+        assert_eq!(0, crc32(&[]));
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+    }
+
+    #[cfg(feature = "rom-db")]
+    #[test]
+    fn unknown_roms_are_not_found() {
+        assert_eq!(None, lookup(&[0x12, 0x00]));
+        assert_eq!(None, lookup(include_bytes!("../roms/my_logo.ch8")));
+    }
+}